@@ -0,0 +1,76 @@
+import json
+import socket
+import argparse
+
+ACTIONS = [
+    "wait",
+    "shoot",
+    "move_forward",
+    "move_backward",
+    "rotate_left",
+    "rotate_right",
+    "spin_left",
+    "spin_right",
+]
+
+
+class TankEnv:
+    """Client for the engine's gym server (`game -gym 127.0.0.1:6000`).
+
+    Both tanks are controlled by the caller, `step` takes one action per player
+    and every value returned is a list indexed by player id.
+    """
+
+    def __init__(self, host="127.0.0.1", port=6000):
+        self.sock = socket.create_connection((host, port))
+        self.file = self.sock.makefile("rw", encoding="utf-8", newline="\n")
+
+    def _request(self, msg):
+        self.file.write(json.dumps(msg) + "\n")
+        self.file.flush()
+
+        response = json.loads(self.file.readline())
+        if "error" in response:
+            raise RuntimeError(response["error"])
+
+        return response
+
+    def reset(self, seed=0, map=None, reward=None, max_steps=None):
+        """Start a new episode. Returns the observation of each player (None once a tank is destroyed)."""
+        msg = {"cmd": "reset", "seed": seed, "map": map}
+        if reward is not None:
+            msg["reward"] = reward  # e.g. {"hit": 1.0, "damage": -1.0, "survival": 0.001}
+        if max_steps is not None:
+            msg["max_steps"] = max_steps
+
+        return self._request(msg)["obs"]
+
+    def step(self, actions):
+        """Apply one action per player. Returns (obs, reward, done, info)."""
+        response = self._request({"cmd": "step", "actions": list(actions)})
+
+        return response["obs"], response["reward"], response["done"], response["info"]
+
+    def close(self):
+        try:
+            self.file.write(json.dumps({"cmd": "close"}) + "\n")
+            self.file.flush()
+        finally:
+            self.sock.close()
+
+
+if __name__ == '__main__':
+    parser = argparse.ArgumentParser(description="Tank Game gym client")
+    parser.add_argument('--port', type=int, default=6000, help='Port of the gym server')
+    parser.add_argument('--map', type=str, default="test_1.ron", help='Map to play on')
+    args = parser.parse_args()
+
+    env = TankEnv(port=args.port)
+    obs = env.reset(seed=0, map=args.map)
+
+    done = False
+    while not done:
+        obs, reward, done, info = env.step(["spin_left", "wait"])
+
+    print(info)
+    env.close()
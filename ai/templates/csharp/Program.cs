@@ -19,13 +19,19 @@ app.MapPost("/start_game", async (HttpContext context) =>
 
     // TODO - Your game initialization code here
 
-    return Results.Ok(new { message = "" });
+    return Results.Ok(new { name = "csharp_template", protocol_version = 3 });
 });
 
 app.MapPost("/brain", async (HttpContext context) =>
 {
     var data = await JsonSerializer.DeserializeAsync<BrainRequest>(context.Request.Body);
 
+    // Problems with the previous answers, e.g. an unknown action
+    foreach (var error in data?.Errors ?? Array.Empty<string>())
+    {
+        Console.WriteLine($"Engine: {error}");
+    }
+
     // TODO - Implement AI decision-making logic based on the BrainRequest data
 
     return Results.Ok(new { action = "" });  // Example action
@@ -65,12 +71,18 @@ public class BrainRequest
     public float TurretRot { get; set; }
     public VisionData[] TurretVision { get; set; }
     public VisionData[] HullVision { get; set; }
+    public string[] Errors { get; set; }
 }
 
 public class VisionData
 {
     public float? Wall { get; set; }
     public float? Enemy { get; set; }
+    public float? DestructibleWall { get; set; }
+    public float? LowWall { get; set; }
+    public float? Water { get; set; }
+    public float? Hole { get; set; }
+    public float? Pickup { get; set; }
 }
 
 public class GameStatusRequest
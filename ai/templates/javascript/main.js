@@ -15,12 +15,17 @@ app.post('/start_game', (req, res) => {
 
     // TODO - YOUR CODE GOES HERE
 
-    res.json({ message: '' });
+    res.json({ name: 'javascript_template', protocol_version: 1 });
 });
 
 app.post('/brain', (req, res) => {
     const data = req.body;
 
+    // Problems with the previous answers, e.g. an unknown action
+    for (const error of data.errors ?? []) {
+        console.warn(`Engine: ${error}`);
+    }
+
     // TODO - YOUR CODE GOES HERE
 
     res.json({ action: '' });
@@ -0,0 +1,56 @@
+import argparse
+from concurrent import futures
+
+import grpc
+
+# Generated from the schema, run from this folder:
+#   python -m grpc_tools.protoc -I ../../../proto --python_out=. --grpc_python_out=. ../../../proto/tank_ai.proto
+import tank_ai_pb2
+import tank_ai_pb2_grpc
+
+# Minimal AI for the gRPC transport (`-p1 grpc://127.0.0.1:5000`).
+
+Action = tank_ai_pb2.Action
+
+PROTOCOL_VERSION = 3
+
+
+class TankAi(tank_ai_pb2_grpc.TankAiServicer):
+    def StartGame(self, request, context):
+        print(f"New game: {request.game_id}")
+        return tank_ai_pb2.BotInfo(name="python_grpc", protocol_version=PROTOCOL_VERSION)
+
+    def Brain(self, request, context):
+        # Problems with the previous answers, e.g. an unknown action
+        for error in request.errors:
+            print(f"Engine: {error}")
+
+        turret_vision = request.turret_vision
+        center = len(turret_vision) // 2
+
+        if turret_vision[center].HasField("enemy"):
+            return Action(action=Action.SHOOT)
+        for idx, vision in enumerate(turret_vision):
+            if vision.HasField("enemy"):
+                return Action(action=Action.SPIN_LEFT if idx < center else Action.SPIN_RIGHT)
+        return Action(action=Action.SPIN_LEFT)
+
+    def Win(self, request, context):
+        print(f"Game {request.game_id}: win")
+        return tank_ai_pb2.Ack()
+
+    def Loss(self, request, context):
+        print(f"Game {request.game_id}: loss")
+        return tank_ai_pb2.Ack()
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--port", type=int, default=5000)
+    args = parser.parse_args()
+
+    server = grpc.server(futures.ThreadPoolExecutor(max_workers=4))
+    tank_ai_pb2_grpc.add_TankAiServicer_to_server(TankAi(), server)
+    server.add_insecure_port(f"0.0.0.0:{args.port}")
+    server.start()
+    server.wait_for_termination()
@@ -27,7 +27,7 @@ async def start_game(request: Request):
 
     print(f"New game: {game_id}")
 
-    return {'message': f'Game {game_id} started successfully', 'game_id': game_id}
+    return {'message': f'Game {game_id} started successfully', 'game_id': game_id, 'name': 'python_template', 'protocol_version': 3}
 
 #ADDING HELPER FUNCTIONS
 
@@ -75,6 +75,10 @@ async def brain(request: Request):
         return {'error': 'Game ID is required'}, 400
     if game_id not in games:
         return {'error': 'Game not found'}, 404
+
+    # Problems with the previous answers, e.g. an unknown action
+    for error in data.get('errors', []):
+        print(f"Engine: {error}")
         # Always spin turret
     #turret_action = "spin_right"
 
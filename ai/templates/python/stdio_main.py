@@ -0,0 +1,37 @@
+import sys
+import json
+
+# Minimal AI for the subprocess transport (`-p1 "cmd:python stdio_main.py"`).
+# The engine writes one JSON message per line to stdin, answer `start_game` and each
+# `brain` message with one line on stdout. Use stderr for logging.
+
+PROTOCOL_VERSION = 3
+
+
+def brain(data):
+    # Problems with the previous answers, e.g. an unknown action
+    for error in data.get("errors", []):
+        print(f"Engine: {error}", file=sys.stderr)
+
+    turret_vision = data["turret_vision"]
+    center = len(turret_vision) // 2
+
+    if turret_vision[center] and "Enemy" in turret_vision[center]:
+        return "shoot"
+    for idx, vision in enumerate(turret_vision):
+        if vision and "Enemy" in vision:
+            return "spin_left" if idx < center else "spin_right"
+    return "spin_left"
+
+
+for line in sys.stdin:
+    data = json.loads(line)
+
+    match data["type"]:
+        case "start_game":
+            print(f"New game: {data['game_id']}", file=sys.stderr)
+            print(json.dumps({"name": "python_stdio", "protocol_version": PROTOCOL_VERSION}), flush=True)
+        case "brain":
+            print(json.dumps({"action": brain(data)}), flush=True)
+        case "win" | "loss":
+            print(f"Game {data['game_id']}: {data['type']}", file=sys.stderr)
@@ -0,0 +1,54 @@
+import json
+import asyncio
+import argparse
+
+import websockets
+
+# Minimal AI for the WebSocket transport (`-p1 ws://127.0.0.1:5000`).
+# The engine connects once and streams sensor data, answer `start_game` with what the bot
+# is and each `brain` message with an action.
+
+PROTOCOL_VERSION = 3
+
+
+def brain(data):
+    # Problems with the previous answers, e.g. an unknown action
+    for error in data.get("errors", []):
+        print(f"Engine: {error}")
+
+    turret_vision = data["turret_vision"]
+    center = len(turret_vision) // 2
+
+    if turret_vision[center] and "Enemy" in turret_vision[center]:
+        return "shoot"
+    for idx, vision in enumerate(turret_vision):
+        if vision and "Enemy" in vision:
+            return "spin_left" if idx < center else "spin_right"
+    return "spin_left"
+
+
+async def handler(websocket):
+    async for message in websocket:
+        data = json.loads(message)
+
+        match data["type"]:
+            case "start_game":
+                print(f"New game: {data['game_id']}")
+                await websocket.send(json.dumps({"name": "python_ws", "protocol_version": PROTOCOL_VERSION}))
+            case "brain":
+                await websocket.send(json.dumps({"action": brain(data)}))
+            case "win" | "loss":
+                print(f"Game {data['game_id']}: {data['type']}")
+
+
+async def main(port):
+    async with websockets.serve(handler, "0.0.0.0", port):
+        await asyncio.Future()
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--port", type=int, default=5000)
+    args = parser.parse_args()
+
+    asyncio.run(main(args.port))
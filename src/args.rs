@@ -5,10 +5,11 @@
 
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::env;
+use std::time::Duration;
 
 use uuid::Uuid;
 
-use crate::player::PlayerController;
+use crate::{engine::{map::{CurrentMap, SpawnPolicy}, tank::instruction::{ActionPolicy, MAX_ACTIONS_PER_TICK}}, player::{builtin::{self, BUILTIN_BOTS}, fault::ConnectionPolicy, key_board::KeyLayout, mouse::MouseAim, network::InputDelay, PlayerController}};
 
 /// A builder for configuring game settings.
 ///
@@ -19,9 +20,40 @@ pub struct GameBuilder {
     pub render: bool,
     pub player_1: PlayerController<0>,
     pub player_2: PlayerController<1>,
-    pub map: Option<String>,
-    pub delta_time: Option<u32>
+    pub map: CurrentMap,
+    pub delta_time: Option<u32>,
+    pub gym: Option<String>,
+    pub connection: ConnectionPolicy,
+    pub action_policy: ActionPolicy,
+    pub broadcast: Option<String>,
+    pub spectate: Option<String>,
+    pub join: Option<String>,
+    pub input_delay: Option<InputDelay>,
+    pub mouse_aim: Option<MouseAim>,
+    pub edit: Option<String>,
+    pub spawn_policy: Option<SpawnPolicy>,
+    pub map_command: Option<MapCommand>
+}
 
+/// A map tool run with `map <command>` instead of a game.
+///
+/// # Variants
+/// - `Import`: Converts an image into a map (`map import <image> [-palette <file>] [-output <file>]`).
+/// - `Validate`: Checks that a map can be played (`map validate <file>`).
+/// - `Analyse`: Measures the fairness of the spawn points of a map (`map analyse <file>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapCommand {
+    Import{
+        image: String,
+        palette: Option<String>,
+        output: Option<String>,
+    },
+    Validate{
+        file: String
+    },
+    Analyse{
+        file: String
+    }
 }
 impl Default for GameBuilder {
     fn default() -> Self {
@@ -29,8 +61,19 @@ impl Default for GameBuilder {
             render: true,
             player_1: PlayerController::wasd(),
             player_2: PlayerController::arrow(),
-            map: None,
-            delta_time: None
+            map: CurrentMap::None,
+            delta_time: None,
+            gym: None,
+            connection: ConnectionPolicy::default(),
+            action_policy: ActionPolicy::default(),
+            broadcast: None,
+            spectate: None,
+            join: None,
+            input_delay: None,
+            mouse_aim: None,
+            edit: None,
+            spawn_policy: None,
+            map_command: None
         }
     }
 }
@@ -42,6 +85,26 @@ enum ReaderState{
     Player2,
     Map,
     DeltaTime,
+    Gym,
+    Retries,
+    ConnectTimeout,
+    Timeout,
+    MaxFailures,
+    Strict,
+    ActionsPerTick,
+    Broadcast,
+    Spectate,
+    Join,
+    InputDelay,
+    MouseAim,
+    Edit,
+    MapCommand,
+    MapImport,
+    MapValidate,
+    MapAnalyse,
+    Spawns,
+    Palette,
+    Output,
     None
 }
 
@@ -83,6 +146,35 @@ fn read_args<'a, I: Iterator<Item = String>>(args: I) -> GameBuilder {
             
             ("-dt" | "-delta_time" | "-t" | "-time", ReaderState::None) => state = ReaderState::DeltaTime,
 
+            ("-gym", ReaderState::None) => state = ReaderState::Gym,
+
+            ("-retries", ReaderState::None) => state = ReaderState::Retries,
+            ("-connect_timeout", ReaderState::None) => state = ReaderState::ConnectTimeout,
+            ("-timeout", ReaderState::None) => state = ReaderState::Timeout,
+            ("-max_failures", ReaderState::None) => state = ReaderState::MaxFailures,
+            ("-strict", ReaderState::None) => state = ReaderState::Strict,
+
+            ("-actions_per_tick", ReaderState::None) => state = ReaderState::ActionsPerTick,
+
+            ("-broadcast", ReaderState::None) => state = ReaderState::Broadcast,
+            ("-spectate", ReaderState::None) => state = ReaderState::Spectate,
+
+            ("-join", ReaderState::None) => state = ReaderState::Join,
+            ("-input_delay", ReaderState::None) => state = ReaderState::InputDelay,
+
+            ("-mouse_aim", ReaderState::None) => state = ReaderState::MouseAim,
+
+            ("-edit", ReaderState::None) => state = ReaderState::Edit,
+
+            ("-spawns", ReaderState::None) => state = ReaderState::Spawns,
+
+            ("map", ReaderState::None) => state = ReaderState::MapCommand,
+            ("import", ReaderState::MapCommand) => state = ReaderState::MapImport,
+            ("validate", ReaderState::MapCommand) => state = ReaderState::MapValidate,
+            ("analyse" | "analyze", ReaderState::MapCommand) => state = ReaderState::MapAnalyse,
+            ("-palette", ReaderState::None) => state = ReaderState::Palette,
+            ("-o" | "-output", ReaderState::None) => state = ReaderState::Output,
+
             ("t" | "true", ReaderState::Render) => {
                 builder.render = true;
                 state = ReaderState::None;
@@ -100,6 +192,53 @@ fn read_args<'a, I: Iterator<Item = String>>(args: I) -> GameBuilder {
                 builder.player_1 = PlayerController::arrow();
                 state = ReaderState::None;
             },
+            (file, ReaderState::Player1) if file.starts_with("keys:") => {
+                builder.player_1 = KeyLayout::load(file.trim_start_matches("keys:"))
+                    .unwrap_or_else(|err| panic!("{err}"))
+                    .into();
+                state = ReaderState::None;
+            },
+            ("gamepad", ReaderState::Player1) => {
+                builder.player_1 = PlayerController::Gamepad{ id: 0 };
+                state = ReaderState::None;
+            },
+            (gamepad, ReaderState::Player1) if gamepad.starts_with("gamepad:") => {
+                builder.player_1 = PlayerController::Gamepad{
+                    id: gamepad.trim_start_matches("gamepad:").parse().unwrap()
+                };
+                state = ReaderState::None;
+            },
+            (bot, ReaderState::Player1) if bot.starts_with("builtin:") => {
+                builder.player_1 = builtin_controller(bot);
+                state = ReaderState::None;
+            },
+            (cmd, ReaderState::Player1) if cmd.starts_with("cmd:") => {
+                builder.player_1 = PlayerController::Process{
+                    cmd: cmd.trim_start_matches("cmd:").to_string(),
+                    game_id: id.clone()
+                };
+                state = ReaderState::None;
+            },
+            (url, ReaderState::Player1) if url.starts_with("grpc://") => {
+                builder.player_1 = PlayerController::Grpc{
+                    url: url.to_string(),
+                    game_id: id.clone()
+                };
+                state = ReaderState::None;
+            },
+            (url, ReaderState::Player1) if url.starts_with("ws://") => {
+                builder.player_1 = PlayerController::WebSocket{
+                    url: url.to_string(),
+                    game_id: id.clone()
+                };
+                state = ReaderState::None;
+            },
+            (addr, ReaderState::Player1) if addr.starts_with("net:") => {
+                builder.player_1 = PlayerController::Network{
+                    addr: addr.trim_start_matches("net:").to_string()
+                };
+                state = ReaderState::None;
+            },
             (ip, ReaderState::Player1) => {
                 let ip = ip.split(":").collect::<Vec<&str>>();
 
@@ -150,6 +289,53 @@ fn read_args<'a, I: Iterator<Item = String>>(args: I) -> GameBuilder {
                 builder.player_2 = PlayerController::arrow();
                 state = ReaderState::None;
             },
+            (file, ReaderState::Player2) if file.starts_with("keys:") => {
+                builder.player_2 = KeyLayout::load(file.trim_start_matches("keys:"))
+                    .unwrap_or_else(|err| panic!("{err}"))
+                    .into();
+                state = ReaderState::None;
+            },
+            ("gamepad", ReaderState::Player2) => {
+                builder.player_2 = PlayerController::Gamepad{ id: 0 };
+                state = ReaderState::None;
+            },
+            (gamepad, ReaderState::Player2) if gamepad.starts_with("gamepad:") => {
+                builder.player_2 = PlayerController::Gamepad{
+                    id: gamepad.trim_start_matches("gamepad:").parse().unwrap()
+                };
+                state = ReaderState::None;
+            },
+            (bot, ReaderState::Player2) if bot.starts_with("builtin:") => {
+                builder.player_2 = builtin_controller(bot);
+                state = ReaderState::None;
+            },
+            (cmd, ReaderState::Player2) if cmd.starts_with("cmd:") => {
+                builder.player_2 = PlayerController::Process{
+                    cmd: cmd.trim_start_matches("cmd:").to_string(),
+                    game_id: id.clone()
+                };
+                state = ReaderState::None;
+            },
+            (url, ReaderState::Player2) if url.starts_with("grpc://") => {
+                builder.player_2 = PlayerController::Grpc{
+                    url: url.to_string(),
+                    game_id: id.clone()
+                };
+                state = ReaderState::None;
+            },
+            (url, ReaderState::Player2) if url.starts_with("ws://") => {
+                builder.player_2 = PlayerController::WebSocket{
+                    url: url.to_string(),
+                    game_id: id.clone()
+                };
+                state = ReaderState::None;
+            },
+            (addr, ReaderState::Player2) if addr.starts_with("net:") => {
+                builder.player_2 = PlayerController::Network{
+                    addr: addr.trim_start_matches("net:").to_string()
+                };
+                state = ReaderState::None;
+            },
             (ip, ReaderState::Player2) => {
                 let ip = ip.split(":").collect::<Vec<&str>>();
 
@@ -194,11 +380,12 @@ fn read_args<'a, I: Iterator<Item = String>>(args: I) -> GameBuilder {
 
             
             ("random" | "None", ReaderState::Map) => {
-                builder.map = None;
+                builder.map = CurrentMap::None;
                 state = ReaderState::None;
             },
-            (asset_path, ReaderState::Map) => {
-                builder.map = Some(asset_path.to_string());
+            (map_name, ReaderState::Map) => {
+                builder.map = CurrentMap::from_name(Some(map_name.to_string()))
+                    .unwrap_or_else(|err| panic!("{err}"));
                 state = ReaderState::None;
             },
 
@@ -210,6 +397,121 @@ fn read_args<'a, I: Iterator<Item = String>>(args: I) -> GameBuilder {
                 builder.delta_time = Some(time.parse().unwrap());
                 state = ReaderState::None;
             },
+
+            (addr, ReaderState::Gym) => {
+                builder.gym = Some(addr.to_string());
+                state = ReaderState::None;
+            },
+
+            (retries, ReaderState::Retries) => {
+                builder.connection.connect_retries = retries.parse().unwrap();
+                state = ReaderState::None;
+            },
+            (time, ReaderState::ConnectTimeout) => {
+                builder.connection.connect_timeout = Duration::from_secs_f32(time.parse().unwrap());
+                state = ReaderState::None;
+            },
+            ("default", ReaderState::Timeout) => {
+                builder.connection.timeout = None;
+                state = ReaderState::None;
+            },
+            (time, ReaderState::Timeout) => {
+                builder.connection.timeout = Some(Duration::from_millis(time.parse().unwrap()));
+                state = ReaderState::None;
+            },
+            (max_failures, ReaderState::MaxFailures) => {
+                builder.connection.max_failures = max_failures.parse().unwrap();
+                state = ReaderState::None;
+            },
+            ("t" | "true", ReaderState::Strict) => {
+                builder.connection.strict = true;
+                state = ReaderState::None;
+            },
+            ("f" | "false", ReaderState::Strict) => {
+                builder.connection.strict = false;
+                state = ReaderState::None;
+            },
+
+            (actions, ReaderState::ActionsPerTick) => {
+                let actions = actions.parse().unwrap();
+
+                if !(1..=MAX_ACTIONS_PER_TICK).contains(&actions) {
+                    panic!("Invalid actions per tick ({actions}), expected 1 to {MAX_ACTIONS_PER_TICK}");
+                }
+
+                builder.action_policy.actions_per_tick = actions;
+                state = ReaderState::None;
+            },
+
+            (addr, ReaderState::Broadcast) => {
+                builder.broadcast = Some(addr.to_string());
+                state = ReaderState::None;
+            },
+            (addr, ReaderState::Spectate) => {
+                builder.spectate = Some(addr.to_string());
+                state = ReaderState::None;
+            },
+
+            (addr, ReaderState::Join) => {
+                builder.join = Some(addr.to_string());
+                state = ReaderState::None;
+            },
+            (ticks, ReaderState::InputDelay) => {
+                builder.input_delay = Some(InputDelay(ticks.parse().unwrap()));
+                state = ReaderState::None;
+            },
+
+            ("p1" | "player_1", ReaderState::MouseAim) => {
+                builder.mouse_aim = Some(MouseAim(0));
+                state = ReaderState::None;
+            },
+            ("p2" | "player_2", ReaderState::MouseAim) => {
+                builder.mouse_aim = Some(MouseAim(1));
+                state = ReaderState::None;
+            },
+
+            (map, ReaderState::Edit) => {
+                builder.edit = Some(map.to_string());
+                state = ReaderState::None;
+            },
+
+            ("farthest", ReaderState::Spawns) => {
+                builder.spawn_policy = Some(SpawnPolicy::Farthest);
+                state = ReaderState::None;
+            },
+            ("balanced", ReaderState::Spawns) => {
+                builder.spawn_policy = Some(SpawnPolicy::Balanced);
+                state = ReaderState::None;
+            },
+
+            (image, ReaderState::MapImport) => {
+                builder.map_command = Some(MapCommand::Import { image: image.to_string(), palette: None, output: None });
+                state = ReaderState::None;
+            },
+            (file, ReaderState::MapValidate) => {
+                builder.map_command = Some(MapCommand::Validate { file: file.to_string() });
+                state = ReaderState::None;
+            },
+            (file, ReaderState::MapAnalyse) => {
+                builder.map_command = Some(MapCommand::Analyse { file: file.to_string() });
+                state = ReaderState::None;
+            },
+            (file, ReaderState::Palette) => {
+                let Some(MapCommand::Import { palette, .. }) = &mut builder.map_command else {
+                    panic!("-palette is only used by map import");
+                };
+
+                *palette = Some(file.to_string());
+                state = ReaderState::None;
+            },
+            (file, ReaderState::Output) => {
+                let Some(MapCommand::Import { output, .. }) = &mut builder.map_command else {
+                    panic!("-output is only used by map import");
+                };
+
+                *output = Some(file.to_string());
+                state = ReaderState::None;
+            },
             
             state => {
                 panic!("{state:#?}")
@@ -220,9 +522,25 @@ fn read_args<'a, I: Iterator<Item = String>>(args: I) -> GameBuilder {
     builder
 }
 
+/// Parses a `builtin:<name>` argument into a `PlayerController::Builtin`.
+///
+/// # Panics
+/// Panics if `<name>` isn't one of the reference bots.
+fn builtin_controller<const P_FLAG: u32>(arg: &str) -> PlayerController<P_FLAG> {
+    let name = arg.trim_start_matches("builtin:");
+
+    if builtin::from_name(name).is_none() {
+        panic!("Unknown builtin bot ({name}), expected one of {BUILTIN_BOTS:?}");
+    }
+
+    PlayerController::Builtin(name.to_string())
+}
+
 #[cfg(test)]
 mod tests{
     use super::*;
+    use bevy::prelude::KeyCode;
+    use crate::engine::map::procgen::{Generator, MapGen};
 
     #[test]
     fn test_empty_vec(){
@@ -333,6 +651,195 @@ mod tests{
     //     );
     // }
 
+    #[test]
+    fn test_builtin(){
+        let game_builder = {
+            let mut tmp = GameBuilder::default();
+
+            tmp.player_1 = PlayerController::Builtin(format!("hunter"));
+            tmp.player_2 = PlayerController::Builtin(format!("level_1"));
+
+            tmp
+        };
+
+        assert_eq!(
+            game_builder,
+            read_args([format!("-p1"), format!("builtin:hunter"), format!("-p2"), format!("builtin:level_1")].into_iter())
+        );
+    }
+
+    #[test]
+    fn test_web_socket(){
+        let game_builder = read_args([format!("-p2"), format!("ws://127.0.0.1:5000")].into_iter());
+
+        match game_builder.player_2 {
+            PlayerController::WebSocket { url, .. } => assert_eq!(url, "ws://127.0.0.1:5000"),
+            controller => panic!("{controller:?}")
+        }
+    }
+
+    #[test]
+    fn test_process(){
+        let game_builder = read_args([format!("-p1"), format!("cmd:python bot.py")].into_iter());
+
+        match game_builder.player_1 {
+            PlayerController::Process { cmd, .. } => assert_eq!(cmd, "python bot.py"),
+            controller => panic!("{controller:?}")
+        }
+    }
+
+    #[test]
+    fn test_gamepad(){
+        let game_builder = read_args([format!("-p1"), format!("gamepad"), format!("-p2"), format!("gamepad:1")].into_iter());
+
+        assert_eq!(game_builder.player_1, PlayerController::Gamepad { id: 0 });
+        assert_eq!(game_builder.player_2, PlayerController::Gamepad { id: 1 });
+    }
+
+    #[test]
+    fn test_keys(){
+        let game_builder = read_args([format!("-p1"), format!("keys:assets/keys/esdf.ron")].into_iter());
+
+        assert_eq!(
+            game_builder.player_1,
+            PlayerController::Control {
+                move_forward: KeyCode::KeyE,
+                move_backward: KeyCode::KeyD,
+                rotate_left: KeyCode::KeyF,
+                rotate_right: KeyCode::KeyS,
+                spin_turret_left: KeyCode::KeyR,
+                spin_turret_right: KeyCode::KeyW,
+                shoot: KeyCode::KeyA,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "used by the rebinding screen")]
+    fn test_keys_reserved(){
+        let path = std::env::temp_dir().join("tank_game_reserved_keys.ron");
+        std::fs::write(&path, std::fs::read_to_string("assets/keys/esdf.ron").unwrap().replace("KeyA", "F1")).unwrap();
+
+        read_args([format!("-p1"), format!("keys:{}", path.display())].into_iter());
+    }
+
+    #[test]
+    fn test_mouse_aim(){
+        let game_builder = read_args([format!("-mouse_aim"), format!("p2")].into_iter());
+
+        assert_eq!(game_builder.mouse_aim, Some(MouseAim(1)));
+    }
+
+    #[test]
+    fn test_map_import(){
+        let game_builder = read_args([
+            format!("map"), format!("import"), format!("arena.png"),
+            format!("-palette"), format!("palette.ron")
+        ].into_iter());
+
+        assert_eq!(
+            game_builder.map_command,
+            Some(MapCommand::Import {
+                image: format!("arena.png"),
+                palette: Some(format!("palette.ron")),
+                output: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_generated_map(){
+        let game_builder = read_args([format!("-map"), format!("gen:caves:48x48")].into_iter());
+
+        assert_eq!(game_builder.map, CurrentMap::Generated(MapGen { generator: Generator::Caves, dim: (48, 48), seed: None }));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_generated_map_too_small(){
+        read_args([format!("-map"), format!("gen:rooms:8x8")].into_iter());
+    }
+
+    #[test]
+    fn test_map_validate(){
+        let game_builder = read_args([format!("map"), format!("validate"), format!("assets/maps/map_1.ron")].into_iter());
+
+        assert_eq!(game_builder.map_command, Some(MapCommand::Validate { file: format!("assets/maps/map_1.ron") }));
+    }
+
+    #[test]
+    fn test_edit(){
+        let game_builder = read_args([format!("-edit"), format!("maps/arena.ron")].into_iter());
+
+        assert_eq!(game_builder.edit, Some(format!("maps/arena.ron")));
+    }
+
+    #[test]
+    fn test_spawns(){
+        let game_builder = read_args([
+            format!("-spawns"), format!("balanced"),
+            format!("map"), format!("analyse"), format!("assets/maps/map_1.ron")
+        ].into_iter());
+
+        assert_eq!(game_builder.spawn_policy, Some(SpawnPolicy::Balanced));
+        assert_eq!(game_builder.map_command, Some(MapCommand::Analyse { file: format!("assets/maps/map_1.ron") }));
+    }
+
+    #[test]
+    fn test_network(){
+        let game_builder = read_args([
+            format!("-p2"), format!("net:0.0.0.0:6500"),
+            format!("-input_delay"), format!("5")
+        ].into_iter());
+
+        assert_eq!(game_builder.player_2, PlayerController::Network { addr: format!("0.0.0.0:6500") });
+        assert_eq!(game_builder.input_delay, Some(InputDelay(5)));
+    }
+
+    #[test]
+    fn test_connection_policy(){
+        let game_builder = read_args([
+            format!("-retries"), format!("5"),
+            format!("-connect_timeout"), format!("30"),
+            format!("-timeout"), format!("100"),
+            format!("-max_failures"), format!("2"),
+            format!("-strict"), format!("true")
+        ].into_iter());
+
+        assert_eq!(
+            game_builder.connection,
+            ConnectionPolicy {
+                connect_retries: 5,
+                connect_timeout: Duration::from_secs(30),
+                timeout: Some(Duration::from_millis(100)),
+                max_failures: 2,
+                strict: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_action_policy(){
+        let game_builder = read_args([format!("-actions_per_tick"), format!("1")].into_iter());
+
+        assert_eq!(game_builder.action_policy, ActionPolicy { actions_per_tick: 1 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bad_action_policy(){
+        read_args([format!("-actions_per_tick"), format!("0")].into_iter());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bad_builtin(){
+        read_args([
+            format!("-p1"), format!("builtin:skynet")
+        ].into_iter());
+    }
+
     #[test]
     #[should_panic]
     fn test_bad_args_1(){
@@ -0,0 +1,470 @@
+//! This module provides the map editor started with `-edit <file>`.
+//!
+//! The map is drawn on a grid of `WALL_SIZE` tiles, walls, spawn points and the
+//! other kinds of tiles are painted with the mouse and the map is saved back to its file as RON.
+//! Every stroke of the mouse can be undone and redone.
+
+use std::{fs, io, mem, path::{Path, PathBuf}};
+
+use bevy::{
+    app::{Plugin, Startup, Update},
+    color::{Color, LinearRgba},
+    input::{mouse::MouseButton, ButtonInput},
+    log::{info, warn},
+    math::{UVec2, Vec2, Vec3},
+    prelude::{
+        resource_changed, Camera, Commands, Component, DespawnRecursiveExt, Entity, EventReader, Gizmos, GlobalTransform,
+        DetectChangesMut, IntoSystemConfigs, KeyCode, OrthographicProjection, Query, Res, ResMut, Resource, TextBundle, Transform, With, Without
+    },
+    sprite::{Sprite, SpriteBundle},
+    text::{Text, TextStyle},
+    ui::{PositionType, Style, Val},
+    window::{PrimaryWindow, Window, WindowResized}
+};
+
+use crate::engine::map::{create_camera, Coord, Map, TileKind, WALL_SIZE};
+
+/// The dimensions of the maps created by the editor, like the maps made with `map_builder`.
+pub const NEW_MAP_DIM: (usize, usize) = (32, 32);
+
+/// What the left mouse button paints.
+///
+/// # Variants
+/// - `Wall`: Paints walls.
+/// - `SpawnPoint`: Paints the points where tanks may spawn.
+/// - `DestructibleWall`: Paints walls that can be shot through.
+/// - `LowWall`: Paints walls that bullets fly over.
+/// - `Water`: Paints water, slowing down tanks.
+/// - `Hole`: Paints holes, destroying tanks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    Wall,
+    SpawnPoint,
+    DestructibleWall,
+    LowWall,
+    Water,
+    Hole
+}
+
+impl EditorTool {
+    /// The kind of tile painted.
+    pub fn kind(self) -> TileKind {
+        match self {
+            EditorTool::Wall => TileKind::Wall,
+            EditorTool::SpawnPoint => TileKind::SpawnPoint,
+            EditorTool::DestructibleWall => TileKind::DestructibleWall,
+            EditorTool::LowWall => TileKind::LowWall,
+            EditorTool::Water => TileKind::Water,
+            EditorTool::Hole => TileKind::Hole,
+        }
+    }
+}
+
+/// The map being edited.
+///
+/// # Fields
+/// - `path`: Where the map is saved.
+/// - `map`: The map.
+/// - `tool`: What the left mouse button paints.
+/// - `saved`: Whether the map hasn't changed since it was last saved.
+/// - `undo`: The maps before each stroke, the latest last.
+/// - `redo`: The maps undone, the latest last.
+/// - `stroke`: Whether the mouse is painting.
+/// - `recorded`: Whether the current stroke has been saved for `undo`.
+#[derive(Debug, Resource)]
+pub struct MapEditor {
+    pub path: PathBuf,
+    pub map: Map,
+    pub tool: EditorTool,
+    pub saved: bool,
+    undo: Vec<Map>,
+    redo: Vec<Map>,
+    stroke: bool,
+    recorded: bool,
+}
+
+impl MapEditor {
+    /// Opens the map at the given path, or a new map surrounded by walls if there isn't one.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let map = match path.exists() {
+            true => ron::from_str(&fs::read_to_string(&path)?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            false => {
+                let (width, height) = NEW_MAP_DIM;
+                let walls = (0..height)
+                    .flat_map(|y| (0..width).map(move |x| (x, y)))
+                    .filter(|&(x, y)| x == 0 || y == 0 || x == width - 1 || y == height - 1)
+                    .collect();
+
+                Map { dim: NEW_MAP_DIM, walls, ..Default::default() }
+            },
+        };
+
+        Ok(MapEditor {
+            path,
+            map,
+            tool: EditorTool::Wall,
+            saved: true,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            stroke: false,
+            recorded: false,
+        })
+    }
+
+    /// Starts a stroke, everything painted until `end_stroke` is undone at once.
+    pub fn begin_stroke(&mut self) {
+        self.stroke = true;
+        self.recorded = false;
+    }
+
+    /// Ends the current stroke.
+    pub fn end_stroke(&mut self) {
+        self.stroke = false;
+    }
+
+    /// Paints the current tool on a tile, replacing what was there.
+    ///
+    /// # Returns
+    /// Whether the map changed.
+    pub fn paint(&mut self, tile: Coord) -> bool {
+        let kind = self.tool.kind();
+        if self.map.tile_kind(tile) == kind {
+            return false;
+        }
+
+        self.record();
+        self.clear(tile);
+        if let Some(tiles) = self.map.tiles_mut(kind) {
+            tiles.push(tile);
+        }
+
+        true
+    }
+
+    /// Removes whatever is on a tile.
+    ///
+    /// # Returns
+    /// Whether the map changed.
+    pub fn erase(&mut self, tile: Coord) -> bool {
+        if self.map.tile_kind(tile) == TileKind::Floor {
+            return false;
+        }
+
+        self.record();
+        self.clear(tile);
+
+        true
+    }
+
+    /// Reverts the last stroke.
+    ///
+    /// # Returns
+    /// Whether there was a stroke to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(map) = self.undo.pop() else {
+            return false;
+        };
+
+        self.redo.push(mem::replace(&mut self.map, map));
+        self.saved = false;
+        true
+    }
+
+    /// Applies the last stroke undone again.
+    ///
+    /// # Returns
+    /// Whether there was a stroke to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(map) = self.redo.pop() else {
+            return false;
+        };
+
+        self.undo.push(mem::replace(&mut self.map, map));
+        self.saved = false;
+        true
+    }
+
+    /// Writes the map to `path`.
+    pub fn save(&mut self) -> io::Result<()> {
+        if self.map.spawn_points.len() < 2 {
+            warn!("The map has less than 2 spawn points, it can't be played");
+        }
+
+        let ron = self.map.to_ron()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(&self.path, ron)?;
+
+        self.saved = true;
+        Ok(())
+    }
+
+    /// The tile at a position in the world, `None` outside the map.
+    pub fn tile_at(&self, pos: Vec2) -> Option<Coord> {
+        let tile = (pos / WALL_SIZE).round();
+
+        match tile.x >= 0. && tile.y >= 0. && (tile.x as usize) < self.map.dim.0 && (tile.y as usize) < self.map.dim.1 {
+            true => Some((tile.x as usize, tile.y as usize)),
+            false => None,
+        }
+    }
+
+    /// Removes a tile from every list of the map.
+    fn clear(&mut self, tile: Coord) {
+        for kind in TileKind::PLACED {
+            if let Some(tiles) = self.map.tiles_mut(kind) {
+                tiles.retain(|coord| *coord != tile);
+            }
+        }
+    }
+
+    /// Saves the map for `undo` before it changes, once per stroke.
+    fn record(&mut self) {
+        self.saved = false;
+        if self.stroke && self.recorded {
+            return;
+        }
+
+        self.undo.push(self.map.clone());
+        self.redo.clear();
+        self.recorded = self.stroke;
+    }
+}
+
+/// A tile drawn by the editor.
+#[derive(Component)]
+pub struct EditorTile;
+
+/// The help shown in the corner of the editor.
+#[derive(Component)]
+pub struct EditorHelp;
+
+/// Opens the editor on the map file at the given path, e.g. `assets/maps/map_1.ron`.
+///
+/// Needs the `DefaultPlugins`, the map is created when it is saved if it doesn't exist.
+///
+/// # Panics
+/// Panics if the map can't be read.
+pub struct EditorPlugin(pub String);
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        let editor = MapEditor::open(&self.0)
+            .unwrap_or_else(|err| panic!("Unable to open {} ({err})", self.0));
+
+        app.insert_resource(editor)
+            .add_systems(Startup, setup_editor)
+            .add_systems(Update, (
+                edit_map,
+                (draw_tiles, update_help).run_if(resource_changed::<MapEditor>),
+                draw_grid,
+                fit_camera
+            ).chain());
+    }
+}
+
+/// Spawns the camera and the help.
+pub fn setup_editor(mut commands: Commands, editor: Res<MapEditor>) {
+    create_camera(editor.map.dim, &mut commands);
+
+    commands.spawn((
+        EditorHelp,
+        TextBundle::from_section("", TextStyle { font_size: 18., ..Default::default() })
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                left: Val::Px(8.),
+                ..Default::default()
+            })
+            .with_background_color(Color::srgba(0., 0., 0., 0.8))
+    ));
+}
+
+/// Paints with the mouse and handles the shortcuts.
+///
+/// - Left click paints the current tool, right click erases.
+/// - `1` selects walls, `2` spawn points, `3` destructible walls, `4` low walls, `5` water and `6` holes.
+/// - `Ctrl+Z` undoes, `Ctrl+Y` or `Ctrl+Shift+Z` redoes, `Ctrl+S` saves.
+pub fn edit_map(
+    mut editor: ResMut<MapEditor>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if ctrl {
+        if keys.just_pressed(KeyCode::KeyZ) && !shift {
+            editor.undo();
+        }
+        else if keys.just_pressed(KeyCode::KeyY) || keys.just_pressed(KeyCode::KeyZ) {
+            editor.redo();
+        }
+
+        if keys.just_pressed(KeyCode::KeyS) {
+            match editor.save() {
+                Ok(()) => info!("Saved {}", editor.path.display()),
+                Err(err) => warn!("Unable to save {} ({err})", editor.path.display()),
+            }
+        }
+    }
+    else if keys.just_pressed(KeyCode::Digit1) {
+        editor.tool = EditorTool::Wall;
+    }
+    else if keys.just_pressed(KeyCode::Digit2) {
+        editor.tool = EditorTool::SpawnPoint;
+    }
+    else if keys.just_pressed(KeyCode::Digit3) {
+        editor.tool = EditorTool::DestructibleWall;
+    }
+    else if keys.just_pressed(KeyCode::Digit4) {
+        editor.tool = EditorTool::LowWall;
+    }
+    else if keys.just_pressed(KeyCode::Digit5) {
+        editor.tool = EditorTool::Water;
+    }
+    else if keys.just_pressed(KeyCode::Digit6) {
+        editor.tool = EditorTool::Hole;
+    }
+
+    // strokes and unchanged tiles don't need the tiles to be drawn again
+    if buttons.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        editor.bypass_change_detection().begin_stroke();
+    }
+    if buttons.any_just_released([MouseButton::Left, MouseButton::Right]) {
+        editor.bypass_change_detection().end_stroke();
+    }
+
+    let tile = window_query.get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .zip(camera_query.get_single().ok())
+        .and_then(|(cursor, (camera, camera_transform))| camera.viewport_to_world_2d(camera_transform, cursor))
+        .and_then(|pos| editor.tile_at(pos));
+
+    let Some(tile) = tile else {
+        return;
+    };
+
+    let changed = match (buttons.pressed(MouseButton::Left), buttons.pressed(MouseButton::Right)) {
+        (true, _) => editor.bypass_change_detection().paint(tile),
+        (_, true) => editor.bypass_change_detection().erase(tile),
+        _ => false,
+    };
+    if changed {
+        editor.set_changed();
+    }
+}
+
+/// Draws the walls in grey and the spawn points in red, like in the images of `map_builder`,
+/// and the other kinds of tiles in colours of their own.
+pub fn draw_tiles(
+    mut commands: Commands,
+    editor: Res<MapEditor>,
+    tile_query: Query<Entity, With<EditorTile>>,
+) {
+    for entity in &tile_query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let color = |kind| match kind {
+        TileKind::Wall => Color::srgb(0.6, 0.6, 0.6),
+        TileKind::SpawnPoint => Color::srgb(0.9, 0.1, 0.1),
+        TileKind::DestructibleWall => Color::srgb(0.6, 0.4, 0.2),
+        TileKind::LowWall => Color::srgb(0.8, 0.8, 0.5),
+        TileKind::Water => Color::srgb(0.2, 0.4, 0.8),
+        TileKind::Hole | TileKind::Floor => Color::srgb(0.15, 0.15, 0.15),
+    };
+    let tiles = TileKind::PLACED.into_iter()
+        .filter_map(|kind| Some((editor.map.tiles(kind)?, color(kind))))
+        .flat_map(|(tiles, color)| tiles.iter().map(move |tile| (tile, color)));
+
+    for (&(x, y), color) in tiles {
+        commands.spawn((
+            EditorTile,
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(WALL_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(x as f32 * WALL_SIZE, y as f32 * WALL_SIZE, 0.),
+                ..Default::default()
+            }
+        ));
+    }
+}
+
+/// Draws the lines between the tiles.
+pub fn draw_grid(mut gizmos: Gizmos, editor: Res<MapEditor>) {
+    let (width, height) = editor.map.dim;
+    let centre = Vec2::new(width as f32 - 1., height as f32 - 1.) / 2. * WALL_SIZE;
+
+    gizmos.grid_2d(
+        centre,
+        0.,
+        UVec2::new(width as u32, height as u32),
+        Vec2::splat(WALL_SIZE),
+        LinearRgba::new(0.3, 0.3, 0.3, 1.)
+    ).outer_edges();
+}
+
+/// Shows the current tool, whether the map is saved and the shortcuts.
+pub fn update_help(editor: Res<MapEditor>, mut help_query: Query<&mut Text, With<EditorHelp>>) {
+    let tool = match editor.tool {
+        EditorTool::Wall => "walls",
+        EditorTool::SpawnPoint => "spawn points",
+        EditorTool::DestructibleWall => "destructible walls",
+        EditorTool::LowWall => "low walls",
+        EditorTool::Water => "water",
+        EditorTool::Hole => "holes",
+    };
+    let saved = match editor.saved {
+        true => "saved",
+        false => "not saved",
+    };
+
+    let help = format!(
+        "{} ({saved}) - painting {tool}, {} spawn points\n\
+        Left click: paint, right click: erase, 1: walls, 2: spawn points\n\
+        3: destructible walls, 4: low walls, 5: water, 6: holes\n\
+        Ctrl+Z: undo, Ctrl+Y: redo, Ctrl+S: save",
+        editor.path.display(),
+        editor.map.spawn_points.len()
+    );
+
+    for mut text in &mut help_query {
+        text.sections[0].value = help.clone();
+    }
+}
+
+/// Fits the map in the window, like the camera of a rendered game.
+pub fn fit_camera(
+    mut resize_reader: EventReader<WindowResized>,
+    editor: Res<MapEditor>,
+
+    mut camera_query: Query<(&mut OrthographicProjection, &mut Transform), Without<EditorTile>>,
+) {
+    let Some(window_size) = resize_reader.read().last() else {
+        return;
+    };
+    let Ok((mut projection, mut transform)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let (width, height) = editor.map.dim;
+
+    projection.scale = match window_size.width > window_size.height {
+        false => ((1. + width as f32) * WALL_SIZE) / window_size.width,
+        true => ((1. + height as f32) * WALL_SIZE) / window_size.height,
+    };
+    transform.translation = Vec3 {
+        x: (width as f32 - 1.) / 2. * WALL_SIZE,
+        y: (height as f32 - 1.) / 2. * WALL_SIZE,
+        z: 1.
+    };
+}
@@ -1,6 +1,7 @@
 use bevy::{prelude::{Res, ResMut, Resource}, time::Time};
 
 
+/// The duration of the current tick in seconds, fixed in headless matches.
 #[derive(Debug, Resource)]
 pub struct DeltaTime(pub f32);
 
@@ -0,0 +1,229 @@
+//! This module measures how fair the spawn points of a map are, with `map analyse <file>` and `SpawnPolicy::Balanced`.
+//!
+//! Each spawn point is described by its cover, the walls of any kind around it, its exposure, how
+//! much of the map can see it over low walls, water and holes, and how far the centre of the map is. Two spawn points are balanced
+//! when these measures are close, a map is asymmetric when the default `SpawnPolicy::Farthest`
+//! can pick spawn points that aren't balanced.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::math::Vec2;
+
+use crate::engine::tank::vision::TURRET_RAY_MAX_DIST;
+
+use super::{farthest_spawn_point, Coord, Map, TileKind, WALL_SIZE};
+
+/// The distance in tiles around a spawn point in which walls count as cover.
+pub const COVER_RADIUS: f32 = 3.;
+/// The distance in tiles from which a spawn point can be seen, the reach of the turret's rays.
+pub const EXPOSURE_RANGE: f32 = TURRET_RAY_MAX_DIST / WALL_SIZE;
+/// The largest difference between two spawn points for them to be balanced.
+pub const FAIRNESS_TOLERANCE: f32 = 0.1;
+/// The number of points checked per tile along a line of sight.
+const SIGHT_SAMPLES_PER_TILE: f32 = 8.;
+
+/// The measures of a spawn point.
+///
+/// # Fields
+/// - `spawn`: The spawn point.
+/// - `cover`: The share of the tiles within `COVER_RADIUS` that are walls, from `0` to `1`.
+/// - `exposure`: The share of the tiles reachable within `EXPOSURE_RANGE` that can see the spawn point, from `0` to `1`.
+/// - `centre_distance`: The length of the shortest path to the centre of the map, in tiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnMetrics {
+    pub spawn: Coord,
+    pub cover: f32,
+    pub exposure: f32,
+    pub centre_distance: usize,
+}
+
+/// The measures of two spawn points facing each other.
+///
+/// # Fields
+/// - `spawns`: The spawn points.
+/// - `path_distance`: The length of the shortest path between them in tiles, `None` if they can't reach each other.
+/// - `imbalance`: The largest difference of cover, exposure and (relative) distance to the centre, from `0` to `1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairMetrics {
+    pub spawns: (Coord, Coord),
+    pub path_distance: Option<usize>,
+    pub imbalance: f32,
+}
+
+impl PairMetrics {
+    pub fn is_balanced(&self) -> bool {
+        self.path_distance.is_some() && self.imbalance <= FAIRNESS_TOLERANCE
+    }
+}
+
+/// The fairness of the spawn points of a map.
+///
+/// # Fields
+/// - `spawns`: The measures of every distinct spawn point.
+/// - `pairs`: The measures of every pair of distinct spawn points.
+/// - `farthest_pairs`: The pairs `SpawnPolicy::Farthest` may pick, one per spawn point of player 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fairness {
+    pub spawns: Vec<SpawnMetrics>,
+    pub pairs: Vec<PairMetrics>,
+    pub farthest_pairs: Vec<PairMetrics>,
+}
+
+impl Fairness {
+    /// Measures the spawn points of a map.
+    pub fn analyse(map: &Map) -> Self {
+        // destructible walls can be shot through, like in `Map::validate`
+        let blocked = map.tiles_where(|kind| kind.blocks_tanks() && kind != TileKind::DestructibleWall);
+        let spawn_points = map.spawn_points.iter()
+            .copied()
+            .fold(Vec::new(), |mut spawn_points, point| {
+                if !spawn_points.contains(&point) {
+                    spawn_points.push(point);
+                }
+                spawn_points
+            });
+
+        let distances: Vec<HashMap<Coord, usize>> = spawn_points.iter()
+            .map(|spawn| path_distances(map, *spawn, &blocked))
+            .collect();
+        let spawns: Vec<SpawnMetrics> = spawn_points.iter()
+            .zip(&distances)
+            .map(|(spawn, distances)| spawn_metrics(map, *spawn, distances))
+            .collect();
+
+        let pair = |i: usize, j: usize| PairMetrics {
+            spawns: (spawns[i].spawn, spawns[j].spawn),
+            path_distance: distances[i].get(&spawns[j].spawn).copied(),
+            imbalance: imbalance(&spawns[i], &spawns[j]),
+        };
+
+        let pairs = (0..spawns.len())
+            .flat_map(|i| (i + 1..spawns.len()).map(move |j| (i, j)))
+            .map(|(i, j)| pair(i, j))
+            .collect();
+
+        let farthest_pairs = (0..spawns.len())
+            .filter_map(|i| {
+                let farthest = farthest_spawn_point(map, spawns[i].spawn);
+                let j = spawn_points.iter().position(|spawn| *spawn == farthest)?;
+
+                (i != j).then(|| pair(i, j))
+            })
+            .collect();
+
+        Fairness { spawns, pairs, farthest_pairs }
+    }
+
+    /// Whether `SpawnPolicy::Farthest` may pick spawn points that aren't balanced.
+    pub fn is_asymmetric(&self) -> bool {
+        self.farthest_pairs.iter().any(|pair| !pair.is_balanced())
+    }
+
+    /// The pairs `SpawnPolicy::Balanced` picks from.
+    ///
+    /// Only pairs at least half as far apart as the farthest pair are considered. Among them, the
+    /// balanced ones, or the least imbalanced one if none is.
+    pub fn balanced_pairs(&self) -> Vec<&PairMetrics> {
+        let max_distance = self.pairs.iter()
+            .filter_map(|pair| pair.path_distance)
+            .max()
+            .unwrap_or_default();
+        let far: Vec<&PairMetrics> = self.pairs.iter()
+            .filter(|pair| pair.path_distance.is_some_and(|distance| 2 * distance >= max_distance))
+            .collect();
+
+        let balanced: Vec<&PairMetrics> = far.iter()
+            .copied()
+            .filter(|pair| pair.is_balanced())
+            .collect();
+
+        match balanced.is_empty() {
+            true => far.into_iter()
+                .min_by(|pair_1, pair_2| pair_1.imbalance.total_cmp(&pair_2.imbalance))
+                .into_iter()
+                .collect(),
+            false => balanced,
+        }
+    }
+}
+
+/// The length of the shortest path from `start` to every tile it can reach, moving through the sides of the tiles.
+fn path_distances(map: &Map, start: Coord, blocked: &HashSet<Coord>) -> HashMap<Coord, usize> {
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[&(x, y)];
+        let neighbours = [
+            x.checked_sub(1).map(|x| (x, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            Some((x + 1, y)),
+            Some((x, y + 1)),
+        ];
+
+        for tile in neighbours.into_iter().flatten() {
+            if tile.0 < map.dim.0 && tile.1 < map.dim.1 && !blocked.contains(&tile) && !distances.contains_key(&tile) {
+                distances.insert(tile, distance + 1);
+                queue.push_back(tile);
+            }
+        }
+    }
+
+    distances
+}
+
+fn spawn_metrics(map: &Map, spawn: Coord, distances: &HashMap<Coord, usize>) -> SpawnMetrics {
+    let walls = map.tiles_where(|kind| matches!(kind, TileKind::Wall | TileKind::DestructibleWall | TileKind::LowWall));
+    let opaque = map.tiles_where(TileKind::blocks_bullets);
+    let position = Vec2::new(spawn.0 as f32, spawn.1 as f32);
+    let tile_position = |&(x, y): &Coord| Vec2::new(x as f32, y as f32);
+
+    let around: Vec<Coord> = (0..map.dim.1)
+        .flat_map(|y| (0..map.dim.0).map(move |x| (x, y)))
+        .filter(|tile| *tile != spawn && tile_position(tile).distance(position) <= COVER_RADIUS)
+        .collect();
+    let cover = around.iter().filter(|tile| walls.contains(tile)).count() as f32 / around.len().max(1) as f32;
+
+    let in_range: Vec<&Coord> = distances.keys()
+        .filter(|tile| **tile != spawn && tile_position(tile).distance(position) <= EXPOSURE_RANGE)
+        .collect();
+    let exposure = in_range.iter()
+        .filter(|tile| in_sight(position, tile_position(tile), &opaque))
+        .count() as f32 / in_range.len().max(1) as f32;
+
+    // with an even width or height, several tiles are as close to the centre
+    let centre = Vec2::new(map.dim.0 as f32 - 1., map.dim.1 as f32 - 1.) / 2.;
+    let closest = distances.keys()
+        .map(|tile| tile_position(tile).distance(centre))
+        .min_by(f32::total_cmp)
+        .unwrap_or_default();
+    let centre_distance = distances.iter()
+        .filter(|(tile, _)| tile_position(tile).distance(centre) <= closest + 1e-3)
+        .map(|(_, distance)| *distance)
+        .min()
+        .unwrap_or_default();
+
+    SpawnMetrics { spawn, cover, exposure, centre_distance }
+}
+
+/// Whether the straight line between the centres of two tiles doesn't cross a tile blocking bullets.
+fn in_sight(from: Vec2, to: Vec2, walls: &HashSet<Coord>) -> bool {
+    let samples = (from.distance(to) * SIGHT_SAMPLES_PER_TILE).ceil() as usize;
+
+    (1..samples).all(|i| {
+        let point = from.lerp(to, i as f32 / samples as f32).round();
+        !walls.contains(&(point.x as usize, point.y as usize))
+    })
+}
+
+fn imbalance(spawn_1: &SpawnMetrics, spawn_2: &SpawnMetrics) -> f32 {
+    let (centre_1, centre_2) = (spawn_1.centre_distance as f32, spawn_2.centre_distance as f32);
+    let centre = match centre_1.max(centre_2) > 0. {
+        true => (centre_1 - centre_2).abs() / centre_1.max(centre_2),
+        false => 0.,
+    };
+
+    (spawn_1.cover - spawn_2.cover).abs()
+        .max((spawn_1.exposure - spawn_2.exposure).abs())
+        .max(centre)
+}
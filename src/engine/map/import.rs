@@ -0,0 +1,115 @@
+//! This module converts images into maps, replacing `map_builder/convert.py`.
+//!
+//! Every pixel is a tile, its colour is looked up in a `Palette`. Like
+//! `convert.py`, the first row of the image is the row `y = 0` of the map.
+
+use std::{cmp::Reverse, collections::HashMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+pub use super::TileKind;
+use super::{validate, Map};
+
+/// The colours of an image and the tiles they stand for.
+///
+/// # Fields
+/// - `tolerance`: How far a pixel's colour may be from a colour of the palette, as an euclidean distance in RGB.
+/// - `colours`: The colours with their tiles, a pixel takes the tile of the closest colour.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub tolerance: f32,
+    pub colours: Vec<([u8; 3], TileKind)>,
+}
+
+impl Default for Palette {
+    /// Black walls and red spawn points on white, like `convert.py`.
+    fn default() -> Self {
+        Palette {
+            tolerance: 10.,
+            colours: vec![
+                ([0, 0, 0], TileKind::Wall),
+                ([255, 0, 0], TileKind::SpawnPoint),
+                ([255, 255, 255], TileKind::Floor),
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// Reads a palette from a RON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let palette = fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read {} ({err})", path.display()))?;
+
+        ron::from_str(&palette).map_err(|err| format!("Invalid palette {} ({err})", path.display()))
+    }
+
+    /// The tile of the colour closest to `pixel`, `None` if none is within `tolerance`.
+    pub fn tile(&self, pixel: [u8; 3]) -> Option<TileKind> {
+        let dist = |colour: &[u8; 3]| colour.iter()
+            .zip(pixel)
+            .map(|(a, b)| (*a as f32 - b as f32).powi(2))
+            .sum::<f32>()
+            .sqrt();
+
+        self.colours.iter()
+            .map(|(colour, tile)| (dist(colour), *tile))
+            .filter(|(dist, _)| *dist <= self.tolerance)
+            .min_by(|(dist_1, _), (dist_2, _)| dist_1.total_cmp(dist_2))
+            .map(|(_, tile)| tile)
+    }
+}
+
+/// A map converted from an image.
+///
+/// # Fields
+/// - `map`: The map.
+/// - `unknown_colours`: The colours missing from the palette with their number of pixels, they are left empty.
+#[derive(Debug, Clone)]
+pub struct ImportedMap {
+    pub map: Map,
+    pub unknown_colours: Vec<([u8; 3], usize)>,
+}
+
+/// Converts a PNG or BMP image into a map.
+///
+/// # Parameters
+/// - `path`: The image.
+/// - `palette`: The tiles of the colours of the image, transparent pixels are empty.
+///
+/// # Returns
+/// The map, or an error if the image can't be read or the map can't be played.
+pub fn import_image(path: impl AsRef<Path>, palette: &Palette) -> Result<ImportedMap, String> {
+    let path = path.as_ref();
+    let image = image::open(path)
+        .map_err(|err| format!("Unable to read {} ({err})", path.display()))?
+        .into_rgba8();
+
+    let mut map = Map {
+        dim: (image.width() as usize, image.height() as usize),
+        ..Default::default()
+    };
+    let mut unknown_colours: HashMap<[u8; 3], usize> = HashMap::new();
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a == 0 {
+            continue;
+        }
+
+        let tile = (x as usize, y as usize);
+        match palette.tile([r, g, b]) {
+            Some(kind) => map.tiles_mut(kind).into_iter().for_each(|tiles| tiles.push(tile)),
+            None => *unknown_colours.entry([r, g, b]).or_default() += 1,
+        }
+    }
+
+    map.validate()
+        .map_err(|errors| validate::report(&path.display().to_string(), &errors))?;
+
+    let mut unknown_colours: Vec<_> = unknown_colours.into_iter().collect();
+    unknown_colours.sort_by_key(|(colour, pixels)| (Reverse(*pixels), *colour));
+
+    Ok(ImportedMap { map, unknown_colours })
+}
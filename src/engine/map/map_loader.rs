@@ -5,32 +5,29 @@ use std::{error::Error, fmt::{self, Display}, future::Future};
 use bevy::{asset::{AssetLoader, AsyncReadExt}, utils::ConditionalSendFuture};
 use ron::de::SpannedError;
 
-use super::Map;
+use super::{validate::report, Map};
+
+#[derive(Default)]
+pub struct MapLoader;
 
 /// An enumeration of errors that may occur during the loading of map assets.
 /// 
 /// # Variants
 /// - `ParsingError`: Indicates an error occurred while parsing the RON data.
 /// - `ReadingError`: Indicates an error occurred while reading the file.
-#[derive(Default)]
-pub struct MapLoader;
-
+/// - `InvalidMap`: The map can't be played, with the report of `validate::report`.
 #[derive(Debug)]
 pub enum MapLoaderError{
     ParsingError(SpannedError),
-    ReadingError(std::io::Error)
+    ReadingError(std::io::Error),
+    InvalidMap(String)
 }
 impl Display for MapLoaderError {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            MapLoaderError::ParsingError(_spanned_error) => {
-                todo!()
-                // write!(f, format!("{:#?}", spanned_error))
-            },
-            MapLoaderError::ReadingError(_io_error) => {
-                todo!()
-                // write!(f, format!("{:#?}", io_error))
-            },
+            MapLoaderError::ParsingError(spanned_error) => write!(f, "{spanned_error}"),
+            MapLoaderError::ReadingError(io_error) => write!(f, "{io_error}"),
+            MapLoaderError::InvalidMap(report) => write!(f, "invalid map\n{report}"),
         }
     }
 }
@@ -62,7 +59,7 @@ impl AssetLoader for MapLoader{
         &'a self,
         reader: &'a mut bevy::asset::io::Reader,
         _settings: &'a Self::Settings,
-        _load_context: &'a mut bevy::asset::LoadContext,
+        load_context: &'a mut bevy::asset::LoadContext,
     ) -> impl ConditionalSendFuture + Future<Output = Result<<Self as AssetLoader>::Asset, <Self as AssetLoader>::Error>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
@@ -70,6 +67,12 @@ impl AssetLoader for MapLoader{
 
             let custom_asset = ron::de::from_bytes::<Map>(&bytes)?;
 
+            if let Err(errors) = custom_asset.validate() {
+                let file = load_context.path().display().to_string();
+
+                return Err(MapLoaderError::InvalidMap(report(&file, &errors)));
+            }
+
             Ok(custom_asset)
         })
     }
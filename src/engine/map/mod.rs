@@ -1,47 +1,187 @@
 //! This module manages map loading and generation in a Bevy-based game,
 //! including wall creation and player spawn point selection for tank gameplay.
-use std::{fs, mem, path::Path};
+use std::{collections::HashSet, fs, mem, path::Path};
 
 use bevy::{
     app::{Plugin, Startup, Update
-    }, asset::{Asset, AssetApp, AssetServer, Assets, Handle}, color::LinearRgba, log::warn, math::Vec3, prelude::{
+    }, asset::{Asset, AssetApp, AssetId, AssetServer, Assets, Handle, LoadState}, color::LinearRgba, log::warn, math::Vec3, prelude::{
         in_state, AppExtStates, Camera2dBundle, Commands, Component, GlobalTransform, Image, InheritedVisibility, IntoSystemConfigs, Mesh, NextState, Res, ResMut, Resource, Transform, ViewVisibility, Visibility
     }, reflect::Reflect, sprite::Sprite
 };
 use bevy_rapier2d::prelude::Collider;
 use gen_state::Step;
 use map_loader::MapLoader;
+use procgen::MapGen;
+use terrain::{create_minimal_terrain, create_terrain, fall_into_holes, show_wall_damage, TankFell, DEFAULT_WALL_HIT_POINTS};
+use fairness::Fairness;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 
 use crate::{engine::tank::gen::{create_minimal_tank, create_tank}, player::PlayerID};
 
-use super::tank::material::TankMaterial;
+use super::{pickup::{create_minimal_pickups, create_pickups, PickupKind}, tank::material::TankMaterial, Simulation};
 
 pub mod map_loader;
 pub mod gen_state;
+pub mod import;
+pub mod procgen;
+pub mod fairness;
+pub mod terrain;
+pub mod validate;
 pub type Coord = (usize, usize);
 
 /// Represents a game map with dimensions, walls, and spawn points for tanks.
+///
+/// The other kinds of tiles are optional, maps without them stay loadable and are saved without them.
 /// 
 /// # Fields
 /// - `dim`: The dimensions of the map as a tuple of width and height.
 /// - `walls`: A vector of coordinates representing the positions of walls on the map.
 /// - `spawn_points`: A vector of coordinates representing possible spawn points for tanks.
-#[derive(Debug, Clone, Asset, Reflect, Deserialize, Serialize)]
+/// - `destructible_walls`: Walls that can be shot through.
+/// - `low_walls`: Walls blocking tanks that bullets and turret rays fly over.
+/// - `water`: Tiles slowing down the tanks driving through them.
+/// - `holes`: Tiles destroying the tanks driving into them, bullets fly over them.
+/// - `wall_hit_points`: How many bullets destroy a destructible wall, `DEFAULT_WALL_HIT_POINTS` if `None`.
+/// - `pickups`: The pickups lying on the map when the game starts, with their power-up.
+/// - `pickup_interval`: The seconds between two random pickups, none spawn if `None`.
+#[derive(Debug, Clone, Default, Asset, Reflect, Deserialize, Serialize)]
 pub struct Map{
     pub dim: (usize, usize),
     pub walls: Vec<Coord>,
-    pub spawn_points: Vec<Coord>
+    pub spawn_points: Vec<Coord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub destructible_walls: Vec<Coord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub low_walls: Vec<Coord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub water: Vec<Coord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub holes: Vec<Coord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_hit_points: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pickups: Vec<(Coord, PickupKind)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pickup_interval: Option<f32>,
+}
+
+/// The kind of a tile of a map.
+///
+/// # Variants
+/// - `Floor`: An empty tile.
+/// - `Wall`: A wall.
+/// - `DestructibleWall`: A wall that can be shot through.
+/// - `LowWall`: A wall blocking tanks but not bullets.
+/// - `Water`: Water slowing down tanks.
+/// - `Hole`: A hole destroying the tanks driving into it.
+/// - `SpawnPoint`: A point where a tank may spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TileKind {
+    Floor,
+    Wall,
+    DestructibleWall,
+    LowWall,
+    Water,
+    Hole,
+    SpawnPoint
+}
+
+impl TileKind {
+    /// Every kind of tile but `Floor`, in the order of the fields of `Map`.
+    pub const PLACED: [TileKind; 6] = [
+        TileKind::Wall,
+        TileKind::SpawnPoint,
+        TileKind::DestructibleWall,
+        TileKind::LowWall,
+        TileKind::Water,
+        TileKind::Hole,
+    ];
+
+    /// Whether tanks can't drive through the tile, or are destroyed when they do.
+    pub fn blocks_tanks(self) -> bool {
+        matches!(self, TileKind::Wall | TileKind::DestructibleWall | TileKind::LowWall | TileKind::Hole)
+    }
+
+    /// Whether bullets and turret rays stop on the tile.
+    pub fn blocks_bullets(self) -> bool {
+        matches!(self, TileKind::Wall | TileKind::DestructibleWall)
+    }
+}
+
+impl Map {
+    /// The tiles of a kind, `None` for `Floor`.
+    pub fn tiles(&self, kind: TileKind) -> Option<&Vec<Coord>> {
+        match kind {
+            TileKind::Floor => None,
+            TileKind::Wall => Some(&self.walls),
+            TileKind::DestructibleWall => Some(&self.destructible_walls),
+            TileKind::LowWall => Some(&self.low_walls),
+            TileKind::Water => Some(&self.water),
+            TileKind::Hole => Some(&self.holes),
+            TileKind::SpawnPoint => Some(&self.spawn_points),
+        }
+    }
+
+    /// The tiles of a kind, `None` for `Floor`.
+    pub fn tiles_mut(&mut self, kind: TileKind) -> Option<&mut Vec<Coord>> {
+        match kind {
+            TileKind::Floor => None,
+            TileKind::Wall => Some(&mut self.walls),
+            TileKind::DestructibleWall => Some(&mut self.destructible_walls),
+            TileKind::LowWall => Some(&mut self.low_walls),
+            TileKind::Water => Some(&mut self.water),
+            TileKind::Hole => Some(&mut self.holes),
+            TileKind::SpawnPoint => Some(&mut self.spawn_points),
+        }
+    }
+
+    /// How many bullets destroy a destructible wall.
+    pub fn wall_hit_points(&self) -> u32 {
+        self.wall_hit_points.unwrap_or(DEFAULT_WALL_HIT_POINTS)
+    }
+
+    /// The kind of a tile, `Floor` if it is in none of the lists.
+    pub fn tile_kind(&self, tile: Coord) -> TileKind {
+        TileKind::PLACED.into_iter()
+            .find(|kind| self.tiles(*kind).is_some_and(|tiles| tiles.contains(&tile)))
+            .unwrap_or(TileKind::Floor)
+    }
+
+    /// The tiles of the kinds matching `filter`.
+    pub fn tiles_where(&self, filter: impl Fn(TileKind) -> bool) -> HashSet<Coord> {
+        TileKind::PLACED.into_iter()
+            .filter(|kind| filter(*kind))
+            .filter_map(|kind| self.tiles(kind))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Serializes the map with its tiles sorted row by row, like the maps made with `map_builder`.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        let mut map = self.clone();
+        for kind in TileKind::PLACED {
+            if let Some(tiles) = map.tiles_mut(kind) {
+                tiles.sort_by_key(|&(x, y)| (y, x));
+            }
+        }
+        map.pickups.sort_by_key(|&((x, y), _)| (y, x));
+
+        ron::ser::to_string_pretty(&map, PrettyConfig::new().compact_arrays(true))
+    }
 }
 
 /// Holds the current map being used in the game, referenced by its asset handle.
 /// 
 /// # Fields
 /// - `0`: An optional handle to the current map asset.
-#[derive(Debug, Clone, Resource)]
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
 pub enum CurrentMap{
     None,
     AssetPath(String),
+    Generated(MapGen),
     Handle(Handle<Map>)
 }
 
@@ -51,6 +191,56 @@ impl Default for CurrentMap {
     }
 }
 
+impl CurrentMap {
+    /// Selects a map by name.
+    ///
+    /// # Parameters
+    /// - `map`: `None` for a random map, `gen:<generator>:<width>x<height>[:<seed>]` for a
+    ///   generated map (see `MapGen`), or the name of a map file in `assets/maps`.
+    ///
+    /// # Returns
+    /// An error if the description of a generated map is invalid.
+    pub fn from_name(map: Option<String>) -> Result<Self, String> {
+        match map {
+            None => Ok(CurrentMap::None),
+            Some(map_gen) if map_gen.starts_with("gen:") => map_gen.parse().map(CurrentMap::Generated),
+            Some(map_name) => Ok(CurrentMap::AssetPath(map_name)),
+        }
+    }
+}
+
+/// The random number generator used when picking maps and spawn points.
+///
+/// Insert a seeded `MapRng` before adding `MapPlugin` to make a game reproducible.
+#[derive(Resource)]
+pub struct MapRng(pub StdRng);
+
+impl MapRng {
+    pub fn seeded(seed: u64) -> Self {
+        MapRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for MapRng {
+    fn default() -> Self {
+        MapRng(StdRng::from_entropy())
+    }
+}
+
+/// How the spawn points of the two tanks are picked.
+///
+/// Insert it before adding `MapPlugin` to change it.
+///
+/// # Variants
+/// - `Farthest`: A random spawn point, and the farthest one from it for the other tank.
+/// - `Balanced`: A random pair of spawn points far apart with the same cover, exposure and distance to the centre (see `fairness`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub enum SpawnPolicy {
+    #[default]
+    Farthest,
+    Balanced
+}
+
 pub const WALL_SIZE: f32 = 32.;
 
 /// A component representing a wall in the game.
@@ -61,26 +251,32 @@ pub struct Wall;
 /// 
 /// # Parameters
 /// - `asset_server`: The asset server resource for loading map assets.
+/// - `maps`: The resource containing all loaded maps, generated maps are added to it.
 /// - `current_map`: The current map resource to store the loaded map.
+/// - `rng`: The random number generator used to pick a random map.
 /// - `next_state`: A mutable reference to the next state in the game state management.
 pub fn load_map(
     asset_server: Res<AssetServer>,
+    mut maps: ResMut<Assets<Map>>,
 
     mut current_map: ResMut<CurrentMap>,
+    mut rng: ResMut<MapRng>,
     mut next_state: ResMut<NextState<Step>>
 ){
     let current_map = current_map.as_mut();
     let mut selected_map = match &current_map {
         CurrentMap::None => {
-            let map_folder: Vec<String> = fs::read_dir("assets/maps")
+            let mut map_folder: Vec<String> = fs::read_dir("assets/maps")
                 .expect("Unable to load \"assets/maps\"")
                 .into_iter()
                 .filter(|file| file.is_ok())
                 .map(|file| file.unwrap())
                 .map(|file| format!("{}", file.file_name().to_str().unwrap()))
                 .collect();
+            // directory order isn't stable across platforms
+            map_folder.sort();
 
-            let i: usize = rand::random::<usize>();
+            let i: usize = rng.0.gen::<usize>();
 
             CurrentMap::Handle(
                 asset_server.load(
@@ -100,6 +296,9 @@ pub fn load_map(
                 )
             )
         },
+        CurrentMap::Generated(map_gen) => {
+            CurrentMap::Handle(maps.add(map_gen.generate(&mut rng.0)))
+        },
         CurrentMap::Handle(_) => panic!("Invalid state when loading a map"),
     };
     
@@ -108,18 +307,90 @@ pub fn load_map(
     next_state.set(Step::GenerateMap);
 }
 
+/// Stops the game if the map couldn't be loaded, e.g. because it is invalid, rather than waiting for it forever.
+///
+/// # Panics
+/// Panics with the loading error if loading the map failed.
+fn panic_if_failed(asset_server: &AssetServer, map_id: AssetId<Map>) {
+    if let LoadState::Failed(err) = asset_server.load_state(map_id) {
+        panic!("{err}");
+    }
+}
+
+/// Picks the spawn points of the two tanks.
+///
+/// # Parameters
+/// - `map`: The map, with at least 2 spawn points.
+/// - `rng`: The random number generator.
+/// - `policy`: How the spawn points are picked.
+///
+/// # Returns
+/// The spawn points of player 1 and player 2.
+///
+/// # Panics
+/// Panics with `SpawnPolicy::Balanced` if no spawn point can reach another one.
+pub fn pick_spawn_points(map: &Map, rng: &mut StdRng, policy: SpawnPolicy) -> (Coord, Coord) {
+    match policy {
+        SpawnPolicy::Farthest => {
+            let p1_spawn = map.spawn_points[rng.gen::<usize>() % map.spawn_points.len()];
+
+            (p1_spawn, farthest_spawn_point(map, p1_spawn))
+        },
+        SpawnPolicy::Balanced => {
+            let fairness = Fairness::analyse(map);
+            let pairs = fairness.balanced_pairs();
+            let pair = pairs.get(rng.gen::<usize>() % pairs.len().max(1))
+                .expect("A map needs 2 spawn points reaching each other");
+
+            if !pair.is_balanced() {
+                warn!("No balanced spawn points, using the closest ones ({:?} and {:?})", pair.spawns.0, pair.spawns.1);
+            }
+
+            match rng.gen::<bool>() {
+                true => pair.spawns,
+                false => (pair.spawns.1, pair.spawns.0),
+            }
+        },
+    }
+}
+
+/// The spawn point farthest from `spawn_point` in a straight line, `spawn_point` itself if there is no other one.
+pub fn farthest_spawn_point(map: &Map, spawn_point: Coord) -> Coord {
+    map.spawn_points
+        .iter()
+        .filter(|&&point| point != spawn_point)
+        .fold(
+            (spawn_point, 0isize),
+            |acc, next| {
+                let dist = (next.0 as isize - spawn_point.0 as isize).pow(2) + (next.1 as isize - spawn_point.1 as isize).pow(2);
+                
+                match dist > acc.1 {
+                    true => (*next, dist),
+                    false => acc
+                }
+            }
+        ).0
+}
+
 /// Generates a minimal map by spawning walls and two tanks at random spawn points.
 /// 
 /// # Parameters
 /// - `commands`: The command buffer for spawning entities.
 /// - `current_map`: The current map resource containing the loaded map.
 /// - `maps`: The resource containing all loaded maps.
+/// - `rng`: The random number generator used to pick spawn points.
+/// - `spawn_policy`: How the spawn points are picked.
+/// - `asset_server`: The asset server resource, to tell whether the map failed to load.
 /// - `next_state`: A mutable reference to the next state in the game state management.
 pub fn generate_minimal_map(
     mut commands: Commands,
     
     current_map: Res<CurrentMap>,
     maps: Res<Assets<Map>>,
+    mut rng: ResMut<MapRng>,
+    spawn_policy: Res<SpawnPolicy>,
+
+    asset_server: Res<AssetServer>,
     
     mut next_state: ResMut<NextState<Step>>
 ){
@@ -130,53 +401,18 @@ pub fn generate_minimal_map(
     let map_id = map_id.id();
 
     let Some(map) = maps.as_ref().get(map_id) else {
+        panic_if_failed(&asset_server, map_id);
         return;
     };
 
     #[cfg(feature = "debug")]
     println!("{map:?}");
     // generate walls & pick spawn points
-    type WallBundle = (Wall,Transform,GlobalTransform, Collider);
-    let walls: Vec<WallBundle> = map.walls.iter()
-        .map(|(x, y)| (*x as f32, *y as f32))
-        .map( |(x, y)| (
-            Wall,
-            Transform{
-                translation: Vec3{
-                    x: x * WALL_SIZE,
-                    y: y * WALL_SIZE,
-                    z: 0.,
-                },
-                ..Default::default()
-            },
-            Default::default(),
-            Collider::cuboid(WALL_SIZE/8., WALL_SIZE/8.)
-        ))
-        .collect();
-    commands.spawn_batch(
-        walls
-    );
-
-    let p1_spawn = {
-        let i1 = rand::random::<usize>() % map.spawn_points.len();
+    create_minimal_walls(&map.walls, &mut commands);
+    create_minimal_terrain(map, &mut commands);
+    create_minimal_pickups(&map.pickups, &mut commands);
 
-        &map.spawn_points[i1]
-    };
-
-    let p2_spawn = map.spawn_points
-        .iter()
-        .filter(|&&point| point != *p1_spawn)
-        .fold(
-            (*p1_spawn, 0isize),
-            |acc, next| {
-                let dist = (next.0 as isize - p1_spawn.0 as isize).pow(2) + (next.1 as isize - p1_spawn.1 as isize).pow(2);
-                
-                match dist > acc.1 {
-                    true => (*next, dist),
-                    false => acc
-                }
-            }
-        ).0;
+    let (p1_spawn, p2_spawn) = pick_spawn_points(map, &mut rng.0, *spawn_policy);
 
     {
         let p1 = create_minimal_tank(
@@ -209,13 +445,18 @@ pub fn generate_minimal_map(
 /// - `commands`: The command buffer for spawning entities.
 /// - `current_map`: The current map resource containing the loaded map.
 /// - `maps`: The resource containing all loaded maps.
-/// - `asset_server`: The asset server resource for loading textures.
+/// - `rng`: The random number generator used to pick spawn points.
+/// - `spawn_policy`: How the spawn points are picked.
+/// - `asset_server`: The asset server resource for loading textures, and to tell whether the map failed to load.
 /// - `next_state`: A mutable reference to the next state in the game state management.
+#[allow(clippy::too_many_arguments)]
 pub fn generate_map(
     mut commands: Commands,
     
     current_map: Res<CurrentMap>,
     maps: Res<Assets<Map>>,
+    mut rng: ResMut<MapRng>,
+    spawn_policy: Res<SpawnPolicy>,
 
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -230,11 +471,88 @@ pub fn generate_map(
     let map_id = map_id.id();
 
     let Some(map) = maps.as_ref().get(map_id) else {
+        panic_if_failed(&asset_server, map_id);
         return;
     };
 
     // println!("{map:?}");
     // generate walls & pick spawn points
+    create_walls(&map.walls, &mut commands, &asset_server);
+    create_terrain(map, &mut commands, &asset_server);
+    create_pickups(&map.pickups, &mut commands);
+    create_camera(map.dim, &mut commands);
+
+    
+    let (p1_spawn, p2_spawn) = pick_spawn_points(map, &mut rng.0, *spawn_policy);
+    {
+        let p1 = create_tank(
+            p1_spawn.0 as f32 * WALL_SIZE,
+            p1_spawn.1 as f32 * WALL_SIZE,
+            0,
+            LinearRgba::new(1., 0., 0., 1.),
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &asset_server
+        );
+        commands.entity(p1)
+            .insert(PlayerID::<0>);
+    }
+
+    {
+        let p2 = create_tank(
+            p2_spawn.0 as f32 * WALL_SIZE,
+            p2_spawn.1 as f32 * WALL_SIZE,
+            1,
+            LinearRgba::new(0., 0., 1., 1.),
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &asset_server
+        );
+        commands.entity(p2)
+            .insert(PlayerID::<1>);
+    }
+
+
+    next_state.set(Step::Finished);
+}
+
+/// Spawns the walls of a map without textures.
+///
+/// # Parameters
+/// - `walls`: The tile coordinates of the walls.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+pub fn create_minimal_walls(walls: &[Coord], commands: &mut Commands) {
+    type WallBundle = (Wall,Transform,GlobalTransform, Collider);
+    let walls: Vec<WallBundle> = walls.iter()
+        .map(|(x, y)| (*x as f32, *y as f32))
+        .map( |(x, y)| (
+            Wall,
+            Transform{
+                translation: Vec3{
+                    x: x * WALL_SIZE,
+                    y: y * WALL_SIZE,
+                    z: 0.,
+                },
+                ..Default::default()
+            },
+            Default::default(),
+            Collider::cuboid(WALL_SIZE/8., WALL_SIZE/8.)
+        ))
+        .collect();
+    commands.spawn_batch(
+        walls
+    );
+}
+
+/// Spawns the walls of a map with their textures.
+///
+/// # Parameters
+/// - `walls`: The tile coordinates of the walls.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+/// - `asset_server`: A resource reference to the `AssetServer` for loading the wall texture.
+pub fn create_walls(walls: &[Coord], commands: &mut Commands, asset_server: &Res<AssetServer>) {
     type WallBundle = (
         Wall,
         Transform,
@@ -248,7 +566,7 @@ pub fn generate_map(
         InheritedVisibility,
         ViewVisibility,
     );
-    let walls: Vec<WallBundle> = map.walls.iter()
+    let walls: Vec<WallBundle> = walls.iter()
         .map(|(x, y)| (*x as f32, *y as f32))
         .map( |(x, y)| (
             Wall,
@@ -274,13 +592,20 @@ pub fn generate_map(
     commands.spawn_batch(
         walls
     );
+}
 
+/// Spawns a camera looking at the centre of a map.
+///
+/// # Parameters
+/// - `dim`: The dimensions of the map in tiles.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+pub fn create_camera(dim: (usize, usize), commands: &mut Commands) {
     commands.spawn((
         Camera2dBundle{
             transform: Transform{
                 translation: Vec3 {
-                    x: map.dim.0 as f32 / 2. * WALL_SIZE,
-                    y: map.dim.1 as f32 / 2. * WALL_SIZE,
+                    x: dim.0 as f32 / 2. * WALL_SIZE,
+                    y: dim.1 as f32 / 2. * WALL_SIZE,
                     z: 1. 
                 },
                 ..Default::default()
@@ -288,89 +613,34 @@ pub fn generate_map(
             ..Default::default()
         },
     ));
-
-    
-    let p1_spawn = {
-        let i1 = rand::random::<usize>() % map.spawn_points.len();
-
-        &map.spawn_points[i1]
-    };
-
-    let p2_spawn = map.spawn_points
-        .iter()
-        .filter(|&&point| point != *p1_spawn)
-        .fold(
-            (*p1_spawn, 0isize),
-            |acc, next| {
-                let dist = (next.0 as isize - p1_spawn.0 as isize).pow(2) + (next.1 as isize - p1_spawn.1 as isize).pow(2);
-                
-                match dist > acc.1 {
-                    true => (*next, dist),
-                    false => acc
-                }
-            }
-        ).0;
-    {
-        let p1 = create_tank(
-            p1_spawn.0 as f32 * WALL_SIZE,
-            p1_spawn.1 as f32 * WALL_SIZE,
-            0,
-            LinearRgba::new(1., 0., 0., 1.),
-            &mut commands,
-            &mut meshes,
-            &mut materials,
-            &asset_server
-        );
-        commands.entity(p1)
-            .insert(PlayerID::<0>);
-    }
-
-    {
-        let p2 = create_tank(
-            p2_spawn.0 as f32 * WALL_SIZE,
-            p2_spawn.1 as f32 * WALL_SIZE,
-            1,
-            LinearRgba::new(0., 0., 1., 1.),
-            &mut commands,
-            &mut meshes,
-            &mut materials,
-            &asset_server
-        );
-        commands.entity(p2)
-            .insert(PlayerID::<1>);
-    }
-
-
-    next_state.set(Step::Finished);
 }
 
 /// A Bevy plugin for managing map loading and generation.
 /// 
 /// # Fields
 /// - `bool`: A flag indicating whether to generate a minimal map(headless) or a complete map.
-pub struct MapPlugin(pub bool, pub Option<String>);
+/// - `CurrentMap`: The map to play on, see `CurrentMap::from_name`.
+pub struct MapPlugin(pub bool, pub CurrentMap);
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app
             .init_asset::<Map>()
             .init_asset_loader::<MapLoader>()
+            .init_resource::<MapRng>()
+            .init_resource::<SpawnPolicy>()
+            .add_event::<TankFell>()
 
-
-            .init_state::<Step>();
-
-            match &self.1{
-                Some(selected_map) => {
-                    app.insert_resource(CurrentMap::AssetPath(selected_map.clone()));
-                },
-                None => {
-                    app.init_resource::<CurrentMap>();
-                },
-            };
+            .init_state::<Step>()
+            .insert_resource(self.1.clone());
 
             app.add_systems(
                     Startup,
-                    load_map.run_if(in_state(Step::LoadMap))
+                    load_map.run_if(in_state(Step::LoadMap)).in_set(Simulation)
+                )
+                .add_systems(
+                    Update,
+                    fall_into_holes.run_if(in_state(Step::Finished)).in_set(Simulation)
                 );
             
         match self.0 {
@@ -379,7 +649,7 @@ impl Plugin for MapPlugin {
                     Update,
                     (
                         generate_minimal_map.run_if(in_state(Step::GenerateMap)),
-                    )
+                    ).in_set(Simulation)
                 );
             },
             true => {
@@ -387,7 +657,8 @@ impl Plugin for MapPlugin {
                     Update,
                     (
                         generate_map.run_if(in_state(Step::GenerateMap)),
-                    )
+                        show_wall_damage,
+                    ).in_set(Simulation)
                 );
             }
         }
@@ -0,0 +1,443 @@
+//! This module generates maps at runtime, selected with `-map gen:<generator>:<width>x<height>[:<seed>]`.
+//!
+//! Every generated map is symmetric through its centre, the tile `(x, y)` is the same as
+//! `(width - 1 - x, height - 1 - y)`, and its two spawn points mirror each other so both
+//! players start from the same position. Regions cut off by walls are joined with corridors,
+//! so generated maps always pass `Map::validate`.
+
+use std::{cmp::Reverse, collections::VecDeque, fmt::{self, Display}, str::FromStr};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{Coord, Map};
+
+/// The smallest width and height of a generated map.
+pub const MIN_GEN_DIM: usize = 16;
+/// The largest width and height of a generated map.
+pub const MAX_GEN_DIM: usize = 256;
+
+/// The width of the corridors, in tiles, wide enough for a tank to turn.
+const CORRIDOR_WIDTH: usize = 2;
+/// The chance of a tile starting as a wall before the caves are smoothed.
+const CAVE_WALL_CHANCE: f64 = 0.45;
+/// The number of smoothing steps of the caves.
+const CAVE_ITERATIONS: usize = 5;
+/// The smallest and largest side of a room.
+const ROOM_SIZE: (usize, usize) = (4, 10);
+/// The largest side of a block of cover in an arena.
+const ARENA_BLOCK_SIZE: usize = 3;
+
+/// The algorithm laying out a generated map.
+///
+/// # Variants
+/// - `Rooms`: Rectangular rooms joined by corridors.
+/// - `Caves`: Caves grown from noise with a cellular automaton.
+/// - `Arena`: An open field with scattered blocks of cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    Rooms,
+    Caves,
+    Arena
+}
+
+/// The description of a generated map, parsed from `gen:<generator>:<width>x<height>[:<seed>]`.
+///
+/// # Fields
+/// - `generator`: The algorithm laying out the map.
+/// - `dim`: The dimensions of the map, between `MIN_GEN_DIM` and `MAX_GEN_DIM`.
+/// - `seed`: The seed of the map, taken from `MapRng` if `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapGen {
+    pub generator: Generator,
+    pub dim: (usize, usize),
+    pub seed: Option<u64>,
+}
+
+impl FromStr for MapGen {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid generated map {s}, expected gen:<rooms|caves|arena>:<width>x<height>[:<seed>]");
+        let mut parts = s.split(':');
+
+        if parts.next() != Some("gen") {
+            return Err(invalid());
+        }
+
+        let generator = match parts.next() {
+            Some("rooms") => Generator::Rooms,
+            Some("caves") => Generator::Caves,
+            Some("arena") => Generator::Arena,
+            _ => return Err(invalid()),
+        };
+
+        let (width, height) = parts.next()
+            .and_then(|dim| dim.split_once('x'))
+            .ok_or_else(invalid)?;
+        let dim = (
+            width.parse().map_err(|_| invalid())?,
+            height.parse().map_err(|_| invalid())?
+        );
+
+        let seed = parts.next()
+            .map(|seed| seed.parse().map_err(|_| invalid()))
+            .transpose()?;
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        let dims = MIN_GEN_DIM..=MAX_GEN_DIM;
+        if !dims.contains(&dim.0) || !dims.contains(&dim.1) {
+            return Err(format!("Invalid generated map {s}, the width and height must be between {MIN_GEN_DIM} and {MAX_GEN_DIM}"));
+        }
+
+        Ok(MapGen { generator, dim, seed })
+    }
+}
+
+impl Display for MapGen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let generator = match self.generator {
+            Generator::Rooms => "rooms",
+            Generator::Caves => "caves",
+            Generator::Arena => "arena",
+        };
+        write!(f, "gen:{generator}:{}x{}", self.dim.0, self.dim.1)?;
+
+        match self.seed {
+            Some(seed) => write!(f, ":{seed}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl MapGen {
+    /// Generates the map.
+    ///
+    /// # Parameters
+    /// - `rng`: The random number generator giving the seed when `seed` is `None`.
+    ///
+    /// # Returns
+    /// A symmetric map surrounded by walls, with 2 mirrored spawn points.
+    pub fn generate(&self, rng: &mut impl Rng) -> Map {
+        let mut rng = StdRng::seed_from_u64(self.seed.unwrap_or_else(|| rng.gen()));
+
+        let mut grid = match self.generator {
+            Generator::Rooms => rooms(self.dim, &mut rng),
+            Generator::Caves => caves(self.dim, &mut rng),
+            Generator::Arena => arena(self.dim, &mut rng),
+        };
+
+        grid.close_border();
+        if !grid.tiles().any(|tile| grid.is_open(tile) && tile != grid.mirror(tile)) {
+            let y = self.dim.1 / 2;
+            for x in 1..self.dim.0 - 1 {
+                grid.carve((x, y));
+            }
+        }
+        grid.connect();
+
+        let spawn_points = grid.spawn_points(&mut rng);
+
+        Map {
+            dim: self.dim,
+            walls: grid.tiles().filter(|tile| !grid.is_open(*tile)).collect(),
+            spawn_points: spawn_points.to_vec(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Rooms placed at random without overlapping, each joined to the previous one by a corridor.
+fn rooms(dim: (usize, usize), rng: &mut StdRng) -> Grid {
+    let mut grid = Grid::new(dim, false);
+    let mut rooms: Vec<(Coord, Coord)> = Vec::new();
+
+    for _ in 0..dim.0 * dim.1 / 64 {
+        let size = (
+            rng.gen_range(ROOM_SIZE.0..=ROOM_SIZE.1).min(dim.0 - 2),
+            rng.gen_range(ROOM_SIZE.0..=ROOM_SIZE.1).min(dim.1 - 2)
+        );
+        let pos = (
+            rng.gen_range(1..=dim.0 - 1 - size.0),
+            rng.gen_range(1..=dim.1 - 1 - size.1)
+        );
+
+        // rooms keep a wall between them
+        let overlaps = rooms.iter().any(|&(other_pos, other_size)| {
+            pos.0 <= other_pos.0 + other_size.0 && other_pos.0 <= pos.0 + size.0 &&
+            pos.1 <= other_pos.1 + other_size.1 && other_pos.1 <= pos.1 + size.1
+        });
+        if !overlaps {
+            rooms.push((pos, size));
+        }
+    }
+
+    for &((x, y), (width, height)) in &rooms {
+        for tile in (y..y + height).flat_map(|y| (x..x + width).map(move |x| (x, y))) {
+            grid.set_mirrored(tile, true);
+        }
+    }
+
+    let centre = |&((x, y), (width, height)): &(Coord, Coord)| (x + width / 2, y + height / 2);
+    for pair in rooms.windows(2) {
+        let (from, to) = (centre(&pair[0]), centre(&pair[1]));
+        let corner = match rng.gen_bool(0.5) {
+            true => (to.0, from.1),
+            false => (from.0, to.1),
+        };
+
+        for tile in line(from, corner).into_iter().chain(line(corner, to)) {
+            grid.carve(tile);
+        }
+    }
+
+    grid
+}
+
+/// Random noise smoothed into caves, a tile becomes a wall when most of its neighbours are walls.
+fn caves(dim: (usize, usize), rng: &mut StdRng) -> Grid {
+    let mut grid = Grid::new(dim, true);
+
+    for tile in grid.tiles().collect::<Vec<_>>() {
+        if grid.index(tile) <= grid.index(grid.mirror(tile)) {
+            grid.set_mirrored(tile, !rng.gen_bool(CAVE_WALL_CHANCE));
+        }
+    }
+
+    // the rule is the same from both sides, so the caves stay symmetric
+    for _ in 0..CAVE_ITERATIONS {
+        grid.open = grid.tiles()
+            .map(|(x, y)| {
+                let walls = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (x as isize + dx, y as isize + dy)))
+                    .filter(|&(x, y)| {
+                        x < 0 || y < 0 || x >= dim.0 as isize || y >= dim.1 as isize ||
+                        !grid.is_open((x as usize, y as usize))
+                    })
+                    .count();
+
+                walls < 5
+            })
+            .collect();
+    }
+
+    grid
+}
+
+/// An open field with blocks of cover, leaving a lane along the outer walls.
+fn arena(dim: (usize, usize), rng: &mut StdRng) -> Grid {
+    let mut grid = Grid::new(dim, true);
+
+    for _ in 0..dim.0 * dim.1 / 48 {
+        let size = (rng.gen_range(1..=ARENA_BLOCK_SIZE), rng.gen_range(1..=ARENA_BLOCK_SIZE));
+        let (x, y) = (
+            rng.gen_range(2..=dim.0 - 3 - size.0),
+            rng.gen_range(2..=dim.1 - 3 - size.1)
+        );
+
+        for tile in (y..y + size.1).flat_map(|y| (x..x + size.0).map(move |x| (x, y))) {
+            grid.set_mirrored(tile, false);
+        }
+    }
+
+    grid
+}
+
+/// The tiles of a horizontal or vertical line, ends included.
+fn line(from: Coord, to: Coord) -> Vec<Coord> {
+    match from.1 == to.1 {
+        true => (from.0.min(to.0)..=from.0.max(to.0)).map(|x| (x, from.1)).collect(),
+        false => (from.1.min(to.1)..=from.1.max(to.1)).map(|y| (from.0, y)).collect(),
+    }
+}
+
+/// The tiles of a map being generated.
+///
+/// # Fields
+/// - `dim`: The dimensions of the map.
+/// - `open`: Whether each tile is empty, row by row.
+struct Grid {
+    dim: (usize, usize),
+    open: Vec<bool>,
+}
+
+impl Grid {
+    fn new(dim: (usize, usize), open: bool) -> Self {
+        Grid { dim, open: vec![open; dim.0 * dim.1] }
+    }
+
+    fn index(&self, (x, y): Coord) -> usize {
+        y * self.dim.0 + x
+    }
+
+    fn is_open(&self, tile: Coord) -> bool {
+        self.open[self.index(tile)]
+    }
+
+    fn is_border(&self, (x, y): Coord) -> bool {
+        x == 0 || y == 0 || x == self.dim.0 - 1 || y == self.dim.1 - 1
+    }
+
+    /// The tile on the other side of the centre of the map.
+    fn mirror(&self, (x, y): Coord) -> Coord {
+        (self.dim.0 - 1 - x, self.dim.1 - 1 - y)
+    }
+
+    /// Every tile, row by row.
+    fn tiles(&self) -> impl Iterator<Item = Coord> {
+        let (width, height) = self.dim;
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    /// The tiles sharing a side with `tile`.
+    fn neighbours(&self, (x, y): Coord) -> impl Iterator<Item = Coord> {
+        let dim = self.dim;
+        [
+            x.checked_sub(1).map(|x| (x, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            Some((x + 1, y)),
+            Some((x, y + 1)),
+        ]
+            .into_iter()
+            .flatten()
+            .filter(move |&(x, y)| x < dim.0 && y < dim.1)
+    }
+
+    /// Sets a tile and its mirror.
+    fn set_mirrored(&mut self, tile: Coord, open: bool) {
+        let (index, mirror) = (self.index(tile), self.index(self.mirror(tile)));
+        self.open[index] = open;
+        self.open[mirror] = open;
+    }
+
+    /// Empties a `CORRIDOR_WIDTH` wide square from `tile` and its mirror, without touching the border.
+    fn carve(&mut self, (x, y): Coord) {
+        for dy in 0..CORRIDOR_WIDTH {
+            for dx in 0..CORRIDOR_WIDTH {
+                let tile = ((x + dx).clamp(1, self.dim.0 - 2), (y + dy).clamp(1, self.dim.1 - 2));
+                self.set_mirrored(tile, true);
+            }
+        }
+    }
+
+    fn close_border(&mut self) {
+        for tile in self.tiles().filter(|tile| self.is_border(*tile)).collect::<Vec<_>>() {
+            self.set_mirrored(tile, false);
+        }
+    }
+
+    /// The groups of empty tiles joined by their sides, largest first.
+    fn regions(&self) -> Vec<Vec<Coord>> {
+        let mut visited = vec![false; self.open.len()];
+        let mut regions = Vec::new();
+
+        for start in self.tiles() {
+            if !self.is_open(start) || visited[self.index(start)] {
+                continue;
+            }
+
+            visited[self.index(start)] = true;
+            let mut region = vec![start];
+            let mut queue = VecDeque::from([start]);
+
+            while let Some(tile) = queue.pop_front() {
+                for next in self.neighbours(tile) {
+                    if self.is_open(next) && !visited[self.index(next)] {
+                        visited[self.index(next)] = true;
+                        region.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            regions.push(region);
+        }
+
+        regions.sort_by_key(|region| Reverse(region.len()));
+        regions
+    }
+
+    /// Digs corridors until every empty tile can be reached from the others.
+    ///
+    /// Each corridor follows the shortest path from the largest region to the closest other one.
+    fn connect(&mut self) {
+        loop {
+            let regions = self.regions();
+            if regions.len() <= 1 {
+                return;
+            }
+
+            let mut region_of = vec![None; self.open.len()];
+            for (i, region) in regions.iter().enumerate() {
+                for tile in region {
+                    region_of[self.index(*tile)] = Some(i);
+                }
+            }
+
+            let mut previous: Vec<Option<Coord>> = vec![None; self.open.len()];
+            let mut visited = vec![false; self.open.len()];
+            let mut queue: VecDeque<Coord> = regions[0].iter().copied().collect();
+            for tile in &regions[0] {
+                visited[self.index(*tile)] = true;
+            }
+
+            let mut path = Vec::new();
+            'search: while let Some(tile) = queue.pop_front() {
+                for next in self.neighbours(tile) {
+                    if visited[self.index(next)] || self.is_border(next) {
+                        continue;
+                    }
+                    visited[self.index(next)] = true;
+                    previous[self.index(next)] = Some(tile);
+
+                    if region_of[self.index(next)].is_some() {
+                        let mut step = Some(tile);
+                        while let Some(tile) = step.filter(|tile| region_of[self.index(*tile)] != Some(0)) {
+                            path.push(tile);
+                            step = previous[self.index(tile)];
+                        }
+                        break 'search;
+                    }
+
+                    queue.push_back(next);
+                }
+            }
+
+            for tile in path {
+                self.carve(tile);
+            }
+        }
+    }
+
+    /// Picks an empty tile far from its mirror, preferring tiles with empty surroundings.
+    ///
+    /// # Returns
+    /// The tile and its mirror.
+    fn spawn_points(&self, rng: &mut StdRng) -> [Coord; 2] {
+        let dist = |tile: &Coord| {
+            let mirror = self.mirror(*tile);
+            (tile.0 as isize - mirror.0 as isize).pow(2) + (tile.1 as isize - mirror.1 as isize).pow(2)
+        };
+        let roomy = |&(x, y): &Coord| {
+            (y - 1..=y + 1).all(|y| (x - 1..=x + 1).all(|x| self.is_open((x, y))))
+        };
+
+        let open: Vec<Coord> = self.tiles()
+            .filter(|tile| self.is_open(*tile) && self.index(*tile) < self.index(self.mirror(*tile)))
+            .collect();
+        let roomy: Vec<Coord> = open.iter().copied().filter(roomy).collect();
+        let candidates = match roomy.is_empty() {
+            true => open,
+            false => roomy,
+        };
+
+        // at least half as far from its mirror as the farthest candidate
+        let max_dist = candidates.iter().map(dist).max().expect("A generated map has empty tiles");
+        let far: Vec<Coord> = candidates.into_iter().filter(|tile| 4 * dist(tile) >= max_dist).collect();
+
+        let spawn_point = far[rng.gen_range(0..far.len())];
+        [spawn_point, self.mirror(spawn_point)]
+    }
+}
@@ -0,0 +1,173 @@
+//! This module spawns the tiles of a map other than the walls, and applies their effects on the tanks.
+//!
+//! - Destructible walls are `Wall`s marked `Destructible`, removed once their `WallHealth` runs out.
+//! - Low walls block the tanks, bullets and turret rays fly over them.
+//! - Water and holes are sensors the tanks drive into, water slows them down and holes destroy them.
+
+use bevy::{
+    asset::{AssetServer, Handle},
+    color::{Alpha, Color},
+    math::Vec2,
+    prelude::{Changed, Commands, Component, Entity, Event, EventWriter, GlobalTransform, Query, Res, Transform, With},
+    sprite::{Sprite, SpriteBundle}
+};
+use bevy_rapier2d::{plugin::RapierContext, prelude::{Collider, QueryFilter, Sensor}};
+
+use crate::engine::tank::gen::Tank;
+
+use super::{Coord, Map, Wall, WALL_SIZE};
+
+/// How fast tanks move in water, as a share of `TANK_MOVE_SPEED`.
+pub const WATER_SPEED_FACTOR: f32 = 0.5;
+/// How many bullets destroy a destructible wall, unless the map sets `wall_hit_points`.
+pub const DEFAULT_WALL_HIT_POINTS: u32 = 3;
+
+/// A `Wall` that can be shot through.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Destructible;
+
+/// The hit points of a destructible wall, it is despawned when they reach `0`.
+///
+/// # Fields
+/// - `hit_points`: The bullets the wall can still take.
+/// - `max`: The hit points of an intact wall.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct WallHealth {
+    pub hit_points: u32,
+    pub max: u32,
+}
+
+impl WallHealth {
+    pub fn new(max: u32) -> Self {
+        WallHealth { hit_points: max, max }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.hit_points == 0
+    }
+}
+
+/// A wall blocking tanks that bullets and turret rays fly over.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct LowWall;
+
+/// A tile of water slowing down the tanks in it.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Water;
+
+/// A hole destroying the tanks driving into it.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Hole;
+
+/// Sent when a tank drives into a hole.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TankFell(pub Entity);
+
+fn tile_transform(&(x, y): &Coord, z: f32) -> Transform {
+    Transform::from_xyz(x as f32 * WALL_SIZE, y as f32 * WALL_SIZE, z)
+}
+
+/// Spawns the tiles of a map other than the walls and spawn points, without textures.
+///
+/// Destructible and low walls have the colliders of `create_minimal_walls`, water and holes cover their tile.
+///
+/// # Parameters
+/// - `map`: The map.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+pub fn create_minimal_terrain(map: &Map, commands: &mut Commands) {
+    let solid = || Collider::cuboid(WALL_SIZE/8., WALL_SIZE/8.);
+    let sensor = || (Collider::cuboid(WALL_SIZE/2., WALL_SIZE/2.), Sensor);
+    let at = |tile: &Coord| (tile_transform(tile, 0.), GlobalTransform::default());
+
+    let health = WallHealth::new(map.wall_hit_points());
+
+    commands.spawn_batch(map.destructible_walls.iter().map(|tile| (Wall, Destructible, health, at(tile), solid())).collect::<Vec<_>>());
+    commands.spawn_batch(map.low_walls.iter().map(|tile| (LowWall, at(tile), solid())).collect::<Vec<_>>());
+    commands.spawn_batch(map.water.iter().map(|tile| (Water, at(tile), sensor())).collect::<Vec<_>>());
+    commands.spawn_batch(map.holes.iter().map(|tile| (Hole, at(tile), sensor())).collect::<Vec<_>>());
+}
+
+/// Spawns the tiles of a map other than the walls and spawn points, with their sprites.
+///
+/// # Parameters
+/// - `map`: The map.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+/// - `asset_server`: A resource reference to the `AssetServer` for loading the wall texture.
+pub fn create_terrain(map: &Map, commands: &mut Commands, asset_server: &Res<AssetServer>) {
+    let solid = || Collider::cuboid(WALL_SIZE/2., WALL_SIZE/2.);
+    let sensor = || (Collider::cuboid(WALL_SIZE/2., WALL_SIZE/2.), Sensor);
+    let wall_texture: Handle<bevy::prelude::Image> = asset_server.load("textures\\map\\wall.png");
+    let health = WallHealth::new(map.wall_hit_points());
+
+    // water and holes are drawn below the tanks, the default texture is plain white
+    let sprite = |tile: &Coord, color: Color, size: f32, z: f32, texture: Handle<bevy::prelude::Image>| SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::splat(size)),
+            ..Default::default()
+        },
+        transform: tile_transform(tile, z),
+        texture,
+        ..Default::default()
+    };
+
+    commands.spawn_batch(
+        map.destructible_walls.iter()
+            .map(|tile| (Wall, Destructible, health, solid(), sprite(tile, Color::srgb(0.8, 0.55, 0.35), WALL_SIZE, 0., wall_texture.clone())))
+            .collect::<Vec<_>>()
+    );
+    commands.spawn_batch(
+        map.low_walls.iter()
+            .map(|tile| (LowWall, solid(), sprite(tile, Color::srgb(0.7, 0.7, 0.7), WALL_SIZE * 0.75, 0., wall_texture.clone())))
+            .collect::<Vec<_>>()
+    );
+    commands.spawn_batch(
+        map.water.iter()
+            .map(|tile| (Water, sensor(), sprite(tile, Color::srgb(0.2, 0.4, 0.8), WALL_SIZE, -0.5, Handle::default())))
+            .collect::<Vec<_>>()
+    );
+    commands.spawn_batch(
+        map.holes.iter()
+            .map(|tile| (Hole, sensor(), sprite(tile, Color::srgb(0.05, 0.05, 0.05), WALL_SIZE, -0.5, Handle::default())))
+            .collect::<Vec<_>>()
+    );
+}
+
+/// Whether a point is inside a collider matching `filter`, e.g. `Water`.
+pub fn is_inside(rapier_context: &RapierContext, point: Vec2, filter: impl Fn(Entity) -> bool) -> bool {
+    let mut inside = false;
+    rapier_context.intersections_with_point(point, QueryFilter::default(), |entity| {
+        inside = filter(entity);
+        !inside
+    });
+
+    inside
+}
+
+/// Destroys the tanks whose centre is over a hole, with their turret.
+pub fn fall_into_holes(
+    mut commands: Commands,
+
+    tank_query: Query<(&Transform, &Tank, Entity)>,
+    hole_query: Query<(), With<Hole>>,
+
+    mut tank_fell: EventWriter<TankFell>,
+
+    rapier_context: Res<RapierContext>,
+) {
+    for (transform, tank, tank_entity) in &tank_query {
+        if is_inside(&rapier_context, transform.translation.truncate(), |entity| hole_query.contains(entity)) {
+            tank_fell.send(TankFell(tank_entity));
+
+            commands.entity(tank.turret).despawn();
+            commands.entity(tank_entity).despawn();
+        }
+    }
+}
+
+/// Fades the destructible walls as they lose hit points.
+pub fn show_wall_damage(mut wall_query: Query<(&WallHealth, &mut Sprite), Changed<WallHealth>>) {
+    for (health, mut sprite) in &mut wall_query {
+        sprite.color.set_alpha(0.25 + 0.75 * health.hit_points as f32 / health.max.max(1) as f32);
+    }
+}
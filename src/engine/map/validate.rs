@@ -0,0 +1,195 @@
+//! This module checks that a `Map` can be played.
+//!
+//! Maps are validated when `MapLoader` parses them and with `map validate <file>`.
+
+use std::{collections::{HashSet, VecDeque}, fmt::{self, Display}, fs, path::Path};
+
+use super::{Coord, Map, TileKind};
+
+/// A reason a map can't be played.
+///
+/// # Variants
+/// - `WallOutOfBounds`: A wall is outside `dim`.
+/// - `SpawnPointOutOfBounds`: A spawn point is outside `dim`.
+/// - `TooFewSpawnPoints`: The map has less than 2 distinct spawn points.
+/// - `SpawnPointOnWall`: A spawn point is on a wall, destructible or low wall.
+/// - `Unreachable`: A spawn point can't be reached from the first one.
+/// - `TileOutOfBounds`: A destructible wall, low wall, water or hole is outside `dim`.
+/// - `OverlappingTiles`: A tile is of several kinds, e.g. both a wall and water.
+/// - `SpawnPointOnHole`: A spawn point is on a hole.
+/// - `NoWallHitPoints`: `wall_hit_points` is `0`, destructible walls couldn't be hit.
+/// - `MisplacedPickup`: A pickup is outside `dim` or on a tile tanks can't drive over.
+/// - `NoPickupInterval`: `pickup_interval` isn't a positive number of seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapError {
+    WallOutOfBounds(Coord),
+    SpawnPointOutOfBounds(Coord),
+    TooFewSpawnPoints(usize),
+    SpawnPointOnWall(Coord),
+    Unreachable{
+        from: Coord,
+        to: Coord
+    },
+    TileOutOfBounds(Coord),
+    OverlappingTiles(Coord),
+    SpawnPointOnHole(Coord),
+    NoWallHitPoints,
+    MisplacedPickup(Coord),
+    NoPickupInterval
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::WallOutOfBounds((x, y)) => write!(f, "wall ({x}, {y}) is outside the map"),
+            MapError::SpawnPointOutOfBounds((x, y)) => write!(f, "spawn point ({x}, {y}) is outside the map"),
+            MapError::TooFewSpawnPoints(count) => write!(f, "{count} distinct spawn points, a map needs at least 2"),
+            MapError::SpawnPointOnWall((x, y)) => write!(f, "spawn point ({x}, {y}) is on a wall"),
+            MapError::Unreachable { from: (x_1, y_1), to: (x_2, y_2) } => {
+                write!(f, "spawn point ({x_2}, {y_2}) can't be reached from spawn point ({x_1}, {y_1})")
+            },
+            MapError::TileOutOfBounds((x, y)) => write!(f, "tile ({x}, {y}) is outside the map"),
+            MapError::OverlappingTiles((x, y)) => write!(f, "tile ({x}, {y}) is of several kinds"),
+            MapError::SpawnPointOnHole((x, y)) => write!(f, "spawn point ({x}, {y}) is on a hole"),
+            MapError::NoWallHitPoints => write!(f, "destructible walls need at least 1 hit point"),
+            MapError::MisplacedPickup((x, y)) => write!(f, "pickup ({x}, {y}) can't be reached by tanks"),
+            MapError::NoPickupInterval => write!(f, "random pickups need a positive interval"),
+        }
+    }
+}
+
+impl Map {
+    /// Checks that the map can be played.
+    ///
+    /// Tanks move from tile to tile through the 4 sides, so spawn points only joined by a
+    /// diagonal between two walls are unreachable. Tanks can't cross walls or holes, but can
+    /// shoot their way through destructible walls.
+    ///
+    /// # Returns
+    /// Every problem found, or `Ok` if there is none.
+    pub fn validate(&self) -> Result<(), Vec<MapError>> {
+        let in_bounds = |&(x, y): &Coord| x < self.dim.0 && y < self.dim.1;
+        let walls = self.tiles_where(|kind| kind.blocks_tanks() && kind != TileKind::Hole);
+        let holes: HashSet<Coord> = self.holes.iter().copied().collect();
+        let terrain = [TileKind::DestructibleWall, TileKind::LowWall, TileKind::Water, TileKind::Hole];
+        let spawn_points: Vec<Coord> = self.spawn_points.iter()
+            .copied()
+            .filter(in_bounds)
+            .fold(Vec::new(), |mut spawn_points, point| {
+                if !spawn_points.contains(&point) {
+                    spawn_points.push(point);
+                }
+                spawn_points
+            });
+
+        let mut errors: Vec<MapError> = self.walls.iter()
+            .filter(|wall| !in_bounds(wall))
+            .map(|wall| MapError::WallOutOfBounds(*wall))
+            .collect();
+        errors.extend(
+            self.spawn_points.iter()
+                .filter(|point| !in_bounds(point))
+                .map(|point| MapError::SpawnPointOutOfBounds(*point))
+        );
+        errors.extend(
+            terrain.into_iter()
+                .filter_map(|kind| self.tiles(kind))
+                .flatten()
+                .filter(|tile| !in_bounds(tile))
+                .map(|tile| MapError::TileOutOfBounds(*tile))
+        );
+
+        let mut seen = HashSet::new();
+        let mut overlapping = HashSet::new();
+        for tile in [TileKind::Wall].into_iter().chain(terrain).filter_map(|kind| self.tiles(kind)).flatten() {
+            if !seen.insert(*tile) && overlapping.insert(*tile) {
+                errors.push(MapError::OverlappingTiles(*tile));
+            }
+        }
+
+        if self.wall_hit_points == Some(0) {
+            errors.push(MapError::NoWallHitPoints);
+        }
+
+        let blocked = self.tiles_where(TileKind::blocks_tanks);
+        errors.extend(
+            self.pickups.iter()
+                .filter(|(tile, _)| !in_bounds(tile) || blocked.contains(tile))
+                .map(|(tile, _)| MapError::MisplacedPickup(*tile))
+        );
+        if self.pickup_interval.is_some_and(|interval| interval.is_nan() || interval <= 0.) {
+            errors.push(MapError::NoPickupInterval);
+        }
+
+        if spawn_points.len() < 2 {
+            errors.push(MapError::TooFewSpawnPoints(spawn_points.len()));
+        }
+
+        let (on_walls, open): (Vec<Coord>, Vec<Coord>) = spawn_points.iter().partition(|point| walls.contains(point));
+        errors.extend(on_walls.into_iter().map(MapError::SpawnPointOnWall));
+        let (on_holes, open): (Vec<Coord>, Vec<Coord>) = open.iter().partition(|point| holes.contains(point));
+        errors.extend(on_holes.into_iter().map(MapError::SpawnPointOnHole));
+
+        if let Some(&from) = open.first() {
+            let reachable = self.reachable(from, &self.tiles_where(|kind| kind.blocks_tanks() && kind != TileKind::DestructibleWall));
+
+            errors.extend(
+                open.iter()
+                    .filter(|point| !reachable.contains(point))
+                    .map(|&to| MapError::Unreachable { from, to })
+            );
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// The tiles inside the map reachable from `start` without crossing `walls`, e.g. the tiles blocking tanks.
+    pub fn reachable(&self, start: Coord, walls: &HashSet<Coord>) -> HashSet<Coord> {
+        let mut reachable = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some((x, y)) = queue.pop_front() {
+            let neighbours = [
+                x.checked_sub(1).map(|x| (x, y)),
+                y.checked_sub(1).map(|y| (x, y)),
+                Some((x + 1, y)),
+                Some((x, y + 1)),
+            ];
+
+            for tile in neighbours.into_iter().flatten() {
+                if tile.0 < self.dim.0 && tile.1 < self.dim.1 && !walls.contains(&tile) && reachable.insert(tile) {
+                    queue.push_back(tile);
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+/// Formats the problems of a map, one per line prefixed with the map's file.
+pub fn report(file: &str, errors: &[MapError]) -> String {
+    errors.iter()
+        .map(|err| format!("{file}: {err}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads a map from a RON file and checks that it can be played.
+///
+/// # Returns
+/// The map, or an error if the file can't be read or parsed, or the report of its problems.
+pub fn validate_file(path: impl AsRef<Path>) -> Result<Map, String> {
+    let path = path.as_ref();
+    let map = fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {} ({err})", path.display()))?;
+    let map: Map = ron::from_str(&map)
+        .map_err(|err| format!("Invalid map {} ({err})", path.display()))?;
+
+    map.validate().map_err(|errors| report(&path.display().to_string(), &errors))?;
+
+    Ok(map)
+}
@@ -1,5 +1,5 @@
 use bevy::{
-    a11y::AccessibilityPlugin, app::{PanicHandlerPlugin, Plugin, PreUpdate, Update}, asset::AssetPlugin, diagnostic::DiagnosticsPlugin, log::LogPlugin, prelude::{HierarchyPlugin, TransformPlugin}, state::app::StatesPlugin, DefaultPlugins, MinimalPlugins
+    a11y::AccessibilityPlugin, app::{PanicHandlerPlugin, Plugin, PostUpdate, PreUpdate, Startup, Update}, asset::AssetPlugin, diagnostic::DiagnosticsPlugin, log::LogPlugin, prelude::{resource_equals, HierarchyPlugin, IntoSystemSetConfigs, Resource, SystemSet, TransformPlugin}, state::app::StatesPlugin, utils::tracing::dispatcher, DefaultPlugins, MinimalPlugins
 };
 use bevy_rapier2d::plugin::{NoUserData, RapierPhysicsPlugin};
 
@@ -13,17 +13,37 @@ use camera::update_camera_pos;
 use camera::cinematic_camera_scale;
 
 use game_time::{update_delta_time, DeltaTime};
-use map::MapPlugin;
+use map::{CurrentMap, MapPlugin};
+use pickup::PickupPlugin;
 use tank::TankPlugin;
 
 
 pub mod map;
 pub mod tank;
+pub mod pickup;
 mod camera;
-mod game_time;
+pub mod game_time;
 
 
-pub struct EnginePlugin(pub bool, pub Option<String>, pub Option<f32>);
+/// Whether this engine plays the game or shows a game played by another engine.
+///
+/// `spectator::SpectatorPlugin` switches it to `Spectator`.
+///
+/// # Variants
+/// - `Host`: The engine loads the map and runs the simulation.
+/// - `Spectator`: The engine only renders, the entities are updated from outside (see `spectator::SpectatorPlugin`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub enum Authority {
+    #[default]
+    Host,
+    Spectator
+}
+
+/// The systems that load the map and advance the game, they only run on the `Host`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct Simulation;
+
+pub struct EnginePlugin(pub bool, pub CurrentMap, pub Option<f32>);
 
 impl Plugin for EnginePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
@@ -49,9 +69,14 @@ impl Plugin for EnginePlugin {
             },
             false => {
                 app.add_plugins(MinimalPlugins)
-                    .add_plugins(PanicHandlerPlugin)
-                    .add_plugins(LogPlugin::default())
-                    .add_plugins(TransformPlugin)
+                    .add_plugins(PanicHandlerPlugin);
+
+                // embedded matches create several apps in one process, only the first can own the logger
+                if !dispatcher::has_been_set() {
+                    app.add_plugins(LogPlugin::default());
+                }
+
+                app.add_plugins(TransformPlugin)
                     .add_plugins(HierarchyPlugin)
                     .add_plugins(DiagnosticsPlugin)
                     .add_plugins(AccessibilityPlugin)
@@ -69,9 +94,15 @@ impl Plugin for EnginePlugin {
                     .add_systems(PreUpdate, update_delta_time);
             },
         }
+        app.init_resource::<Authority>()
+            .configure_sets(Startup, Simulation.run_if(resource_equals(Authority::Host)))
+            .configure_sets(Update, Simulation.run_if(resource_equals(Authority::Host)))
+            .configure_sets(PostUpdate, Simulation.run_if(resource_equals(Authority::Host)));
+
         app
             .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0))
             .add_plugins(MapPlugin(self.0, self.1.clone()))
-            .add_plugins(TankPlugin(self.0));
+            .add_plugins(TankPlugin(self.0))
+            .add_plugins(PickupPlugin(self.0));
     }
 }
\ No newline at end of file
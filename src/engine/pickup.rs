@@ -0,0 +1,393 @@
+//! This module spawns the pickups of a map and gives their power-ups to the tanks driving over them.
+//!
+//! Pickups come from the map's `pickups`, and on a random free tile the tanks can reach every
+//! `pickup_interval` seconds if the map sets it. They are sensors: tanks and bullets go through them, the
+//! turret's rays see over them and the hull's rays see them as `VisionHit::Pickup`.
+
+use std::collections::HashSet;
+
+use bevy::{
+    app::{Plugin, Update},
+    asset::Assets,
+    color::Color,
+    math::Vec2,
+    prelude::{in_state, Bundle, Commands, Component, Entity, Event, EventWriter, GlobalTransform, IntoSystemConfigs, OnEnter, Query, Res, ResMut, Resource, Transform, With},
+    reflect::Reflect,
+    sprite::{Sprite, SpriteBundle}
+};
+use bevy_rapier2d::{plugin::RapierContext, prelude::{Collider, QueryFilter, Sensor}};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    game_time::DeltaTime,
+    map::{gen_state::Step, Coord, CurrentMap, Map, MapRng, TileKind, WALL_SIZE},
+    tank::{gen::{Tank, RELOAD_TIME, TANK_SIZE}, instruction::get_rotation_z},
+    Simulation
+};
+
+/// How long the timed power-ups last, in seconds.
+pub const PICKUP_DURATION: f32 = 10.;
+/// How fast a boosted tank moves, as a share of `TANK_MOVE_SPEED`.
+pub const SPEED_BOOST_FACTOR: f32 = 1.5;
+/// How long reloading takes with `FastReload`, as a share of `RELOAD_TIME`.
+pub const FAST_RELOAD_FACTOR: f32 = 0.5;
+/// The shots `ExtraAmmo` fires without reloading.
+pub const EXTRA_AMMO: u32 = 3;
+/// Random pickups stop spawning while this many of them are on the map, the map's own pickups aren't counted.
+pub const MAX_RANDOM_PICKUPS: usize = 3;
+
+const PICKUP_RADIUS: f32 = WALL_SIZE / 4.;
+const PICKUP_HEIGHT: f32 = -0.25;
+
+/// The power-up given by a pickup.
+///
+/// # Variants
+/// - `Repair`: The tank survives one more hit.
+/// - `FastReload`: The gun reloads faster for `PICKUP_DURATION`.
+/// - `SpeedBoost`: The tank moves faster for `PICKUP_DURATION`.
+/// - `Shield`: Bullets don't hurt the tank for `PICKUP_DURATION`.
+/// - `ExtraAmmo`: The next `EXTRA_AMMO` shots don't reload the gun.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum PickupKind {
+    Repair,
+    FastReload,
+    SpeedBoost,
+    Shield,
+    ExtraAmmo
+}
+
+impl PickupKind {
+    pub const ALL: [PickupKind; 5] = [
+        PickupKind::Repair,
+        PickupKind::FastReload,
+        PickupKind::SpeedBoost,
+        PickupKind::Shield,
+        PickupKind::ExtraAmmo,
+    ];
+
+    /// The colour the pickup is drawn with.
+    pub fn colour(self) -> Color {
+        match self {
+            PickupKind::Repair => Color::srgb(0.2, 0.8, 0.2),
+            PickupKind::FastReload => Color::srgb(0.9, 0.5, 0.1),
+            PickupKind::SpeedBoost => Color::srgb(0.1, 0.8, 0.9),
+            PickupKind::Shield => Color::srgb(0.6, 0.3, 0.9),
+            PickupKind::ExtraAmmo => Color::srgb(0.9, 0.9, 0.2),
+        }
+    }
+}
+
+/// A pickup lying on the map.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Pickup(pub PickupKind);
+
+/// The power-ups of a tank, timed ones are active while their time left is above `0`.
+///
+/// # Fields
+/// - `armour`: The hits the tank survives, from `Repair`.
+/// - `fast_reload`: The seconds left of `FastReload`.
+/// - `speed_boost`: The seconds left of `SpeedBoost`.
+/// - `shield`: The seconds left of `Shield`.
+/// - `extra_ammo`: The shots left that don't reload the gun.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Component)]
+pub struct PowerUps {
+    pub armour: u32,
+    pub fast_reload: f32,
+    pub speed_boost: f32,
+    pub shield: f32,
+    pub extra_ammo: u32,
+}
+
+impl PowerUps {
+    /// Gives the power-up of a pickup, timed power-ups are restarted rather than extended.
+    pub fn apply(&mut self, kind: PickupKind) {
+        match kind {
+            PickupKind::Repair => self.armour += 1,
+            PickupKind::FastReload => self.fast_reload = PICKUP_DURATION,
+            PickupKind::SpeedBoost => self.speed_boost = PICKUP_DURATION,
+            PickupKind::Shield => self.shield = PICKUP_DURATION,
+            PickupKind::ExtraAmmo => self.extra_ammo += EXTRA_AMMO,
+        }
+    }
+
+    /// The share of `TANK_MOVE_SPEED` the tank moves at.
+    pub fn speed_factor(&self) -> f32 {
+        match self.speed_boost > 0. {
+            true => SPEED_BOOST_FACTOR,
+            false => 1.,
+        }
+    }
+
+    /// How long the gun takes to reload after a shot, in seconds.
+    pub fn reload_time(&self) -> f32 {
+        match self.fast_reload > 0. {
+            true => RELOAD_TIME * FAST_RELOAD_FACTOR,
+            false => RELOAD_TIME,
+        }
+    }
+
+    /// Uses up a shot of extra ammo.
+    ///
+    /// # Returns
+    /// Whether the gun stays loaded.
+    pub fn use_ammo(&mut self) -> bool {
+        let loaded = self.extra_ammo > 0;
+        self.extra_ammo = self.extra_ammo.saturating_sub(1);
+
+        loaded
+    }
+
+    /// Whether the tank survives a hit.
+    pub fn is_protected(&self) -> bool {
+        self.shield > 0. || self.armour > 0
+    }
+
+    /// Takes a hit with the shield, or else with the armour.
+    pub fn absorb_hit(&mut self) {
+        if self.shield <= 0. {
+            self.armour = self.armour.saturating_sub(1);
+        }
+    }
+
+    fn tick(&mut self, dt: f32) {
+        for time_left in [&mut self.fast_reload, &mut self.speed_boost, &mut self.shield] {
+            *time_left = (*time_left - dt).max(0.);
+        }
+    }
+}
+
+/// Marks the pickups spawned by `spawn_random_pickups`, only they count against `MAX_RANDOM_PICKUPS`.
+#[derive(Component, Debug)]
+pub struct RandomPickup;
+
+/// The seconds since the last random pickup, reset when a map is generated.
+#[derive(Resource, Debug, Default)]
+pub struct RandomPickupTimer(pub f32);
+
+/// Sent when a tank drives over a pickup.
+///
+/// # Fields
+/// - `tank`: The tank that got the power-up.
+/// - `kind`: The power-up.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PickupCollected {
+    pub tank: Entity,
+    pub kind: PickupKind
+}
+
+/// Spawns and collects the pickups.
+///
+/// # Fields
+/// - `0`: Whether the pickups are rendered, must match the `EnginePlugin`.
+pub struct PickupPlugin(pub bool);
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<PickupCollected>()
+            .init_resource::<RandomPickupTimer>()
+            .add_systems(OnEnter(Step::Finished), reset_random_pickup_timer)
+            .add_systems(Update, (tick_power_ups, collect_pickups).chain().run_if(in_state(Step::Finished)).in_set(Simulation));
+
+        match self.0 {
+            true => {
+                app.add_systems(Update, spawn_random_pickups::<true>.run_if(in_state(Step::Finished)).in_set(Simulation));
+            },
+            false => {
+                app.add_systems(Update, spawn_random_pickups::<false>.run_if(in_state(Step::Finished)).in_set(Simulation));
+            }
+        }
+    }
+}
+
+fn pickup_transform(&(x, y): &Coord) -> Transform {
+    Transform::from_xyz(x as f32 * WALL_SIZE, y as f32 * WALL_SIZE, PICKUP_HEIGHT)
+}
+
+/// Spawns pickups without textures.
+///
+/// # Parameters
+/// - `pickups`: The tiles of the pickups and their power-ups.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+pub fn create_minimal_pickups(pickups: &[(Coord, PickupKind)], commands: &mut Commands) {
+    commands.spawn_batch(
+        pickups.iter()
+            .map(|(tile, kind)| minimal_pickup_bundle(tile, *kind))
+            .collect::<Vec<_>>()
+    );
+}
+
+fn minimal_pickup_bundle(tile: &Coord, kind: PickupKind) -> impl Bundle {
+    (Pickup(kind), pickup_transform(tile), GlobalTransform::default(), Collider::ball(PICKUP_RADIUS), Sensor)
+}
+
+/// Spawns pickups drawn as squares of the colour of their power-up.
+///
+/// # Parameters
+/// - `pickups`: The tiles of the pickups and their power-ups.
+/// - `commands`: A mutable reference to the `Commands` struct used to spawn entities.
+pub fn create_pickups(pickups: &[(Coord, PickupKind)], commands: &mut Commands) {
+    commands.spawn_batch(
+        pickups.iter()
+            .map(|(tile, kind)| pickup_bundle(tile, *kind))
+            .collect::<Vec<_>>()
+    );
+}
+
+fn pickup_bundle(tile: &Coord, kind: PickupKind) -> impl Bundle {
+    (
+        Pickup(kind),
+        Collider::ball(PICKUP_RADIUS),
+        Sensor,
+        SpriteBundle {
+            sprite: Sprite {
+                color: kind.colour(),
+                custom_size: Some(Vec2::splat(PICKUP_RADIUS * 2.)),
+                ..Default::default()
+            },
+            transform: pickup_transform(tile),
+            ..Default::default()
+        }
+    )
+}
+
+/// Picks the tile of a random pickup, a floor tile that isn't `taken` and that a tank can reach
+/// from a spawn point (destructible walls may be shot through).
+///
+/// # Parameters
+/// - `map`: The map.
+/// - `taken`: The tiles with a pickup or a tank on them.
+/// - `rng`: The random number generator.
+///
+/// # Returns
+/// The tile, or `None` if the map has no free floor tile in reach.
+pub fn random_pickup_tile(map: &Map, taken: &HashSet<Coord>, rng: &mut impl Rng) -> Option<Coord> {
+    let placed = map.tiles_where(|kind| kind != TileKind::Floor);
+    let walls = map.tiles_where(|kind| kind.blocks_tanks() && kind != TileKind::DestructibleWall);
+    let reachable: HashSet<Coord> = map.spawn_points.iter()
+        .filter(|spawn| !walls.contains(spawn))
+        .flat_map(|&spawn| map.reachable(spawn, &walls))
+        .collect();
+
+    let mut free: Vec<Coord> = reachable.into_iter()
+        .filter(|tile| !placed.contains(tile) && !taken.contains(tile))
+        .collect();
+    // the draw only depends on the seed, not on the order of the set
+    free.sort_unstable();
+
+    match free.is_empty() {
+        true => None,
+        false => Some(free[rng.gen_range(0..free.len())]),
+    }
+}
+
+fn tile_of(transform: &Transform) -> Coord {
+    let tile = (transform.translation.truncate() / WALL_SIZE).round().max(Vec2::ZERO);
+
+    (tile.x as usize, tile.y as usize)
+}
+
+/// Spawns a pickup of a random kind every `pickup_interval` seconds, unless `MAX_RANDOM_PICKUPS` random pickups are on the map.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_random_pickups<const RENDER: bool>(
+    mut commands: Commands,
+    mut elapsed: ResMut<RandomPickupTimer>,
+
+    current_map: Res<CurrentMap>,
+    maps: Res<Assets<Map>>,
+    mut rng: ResMut<MapRng>,
+
+    pickup_query: Query<&Transform, With<Pickup>>,
+    random_pickup_query: Query<(), With<RandomPickup>>,
+    tank_query: Query<&Transform, With<Tank>>,
+
+    time: Res<DeltaTime>,
+) {
+    let CurrentMap::Handle(handle) = current_map.as_ref() else {
+        return;
+    };
+    let Some(interval) = maps.get(handle).and_then(|map| map.pickup_interval) else {
+        return;
+    };
+
+    elapsed.0 += time.0;
+    if elapsed.0 < interval {
+        return;
+    }
+    elapsed.0 -= interval;
+
+    if random_pickup_query.iter().count() >= MAX_RANDOM_PICKUPS {
+        return;
+    }
+
+    let Some(map) = maps.get(handle) else {
+        return;
+    };
+    let taken: HashSet<Coord> = pickup_query.iter().chain(&tank_query).map(tile_of).collect();
+    let Some(tile) = random_pickup_tile(map, &taken, &mut rng.0) else {
+        return;
+    };
+    let kind = PickupKind::ALL[rng.0.gen_range(0..PickupKind::ALL.len())];
+
+    match RENDER {
+        true => commands.spawn((pickup_bundle(&tile, kind), RandomPickup)),
+        false => commands.spawn((minimal_pickup_bundle(&tile, kind), RandomPickup)),
+    };
+}
+
+/// Restarts the countdown to the first random pickup of a new map.
+fn reset_random_pickup_timer(mut elapsed: ResMut<RandomPickupTimer>) {
+    elapsed.0 = 0.;
+}
+
+/// Counts down the timed power-ups.
+pub fn tick_power_ups(mut power_up_query: Query<&mut PowerUps>, time: Res<DeltaTime>) {
+    for mut power_ups in &mut power_up_query {
+        power_ups.tick(time.0);
+    }
+}
+
+/// Gives the power-up of the pickups the tanks drive over, and removes the pickups.
+pub fn collect_pickups(
+    mut commands: Commands,
+
+    mut tank_query: Query<(&Transform, &mut PowerUps, Entity), With<Tank>>,
+    pickup_query: Query<&Pickup>,
+
+    mut pickup_collected: EventWriter<PickupCollected>,
+
+    rapier_context: Res<RapierContext>,
+) {
+    let shape = Collider::cuboid(TANK_SIZE/2., TANK_SIZE/2.);
+    // the colliders of the pickups despawned this tick are still there, and two tanks may reach one together
+    let mut collected = Vec::new();
+
+    for (transform, mut power_ups, tank_entity) in &mut tank_query {
+        let mut touching = Vec::new();
+        rapier_context.intersections_with_shape(
+            transform.translation.truncate(),
+            get_rotation_z(transform.up().truncate()),
+            &shape,
+            QueryFilter::default().exclude_solids().predicate(&|entity| pickup_query.contains(entity)),
+            |entity| {
+                touching.push(entity);
+                true
+            }
+        );
+
+        for entity in touching {
+            if collected.contains(&entity) {
+                continue;
+            }
+            let Ok(Pickup(kind)) = pickup_query.get(entity) else {
+                continue;
+            };
+
+            power_ups.apply(*kind);
+            pickup_collected.send(PickupCollected { tank: tank_entity, kind: *kind });
+
+            commands.entity(entity).despawn();
+            collected.push(entity);
+        }
+    }
+}
@@ -1,17 +1,17 @@
 use std::time::Duration;
 
 use bevy::{
-    asset::AssetServer, math::{Quat, Vec2, Vec3}, prelude::{Commands, Component, Entity, Event, EventReader, GlobalTransform, Query, Res, Transform, With}, sprite::SpriteBundle
+    asset::AssetServer, math::{Quat, Vec2, Vec3}, prelude::{Commands, Component, Entity, Event, EventReader, EventWriter, GlobalTransform, Or, Query, Res, Transform, With}, sprite::SpriteBundle
 };
 use bevy_rapier2d::{plugin::RapierContext, prelude::{Collider, QueryFilter, ShapeCastOptions}};
 
-use crate::engine::{game_time::DeltaTime, map::Wall};
+use crate::engine::{game_time::DeltaTime, map::{terrain::WallHealth, Coord, Wall, WALL_SIZE}, pickup::PowerUps};
 
 use super::gen::{GunState, Tank, Turret};
 
 
-const BULLET_HEIGHT: f32 = 0.;
-const BULLET_SPEED: f32 = 400.;
+pub const BULLET_HEIGHT: f32 = 0.;
+pub const BULLET_SPEED: f32 = 400.;
 
 #[derive(Event, Clone, Copy)]
 pub struct NewBullet{
@@ -23,6 +23,28 @@ pub struct NewBullet{
 #[derive(Component, Clone, Copy)]
 pub struct Bullet(pub Entity);
 
+/// Sent when a bullet hits a tank.
+///
+/// # Fields
+/// - `source`: The tank that fired the bullet.
+/// - `target`: The tank that was hit.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TankHit{
+    pub source: Entity,
+    pub target: Entity
+}
+
+/// Sent when a bullet destroys a destructible wall.
+///
+/// # Fields
+/// - `source`: The tank that fired the bullet.
+/// - `tile`: Where the wall was.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WallDestroyed{
+    pub source: Entity,
+    pub tile: Coord
+}
+
 pub fn update_bullet_pos(
     mut bullet_query: Query<&mut Transform, With<Bullet>>,
     time: Res<DeltaTime>
@@ -75,15 +97,28 @@ pub fn create_bullet(mut commands: Commands, mut event_reader: EventReader<NewBu
     }
 }
 
+/// The components of a wall a bullet can damage, for use in queries.
+type WallHit = (Option<&'static mut WallHealth>, &'static Transform);
+/// What bullets stop on.
+type Solid = Or<(With<Wall>, With<Tank>)>;
+
+#[allow(clippy::too_many_arguments)]
 pub fn bullet_collision(
     mut commands: Commands,
 
     bullet_query: Query<(&Bullet, &Transform, Entity)>,
-    tank_query: Query<&Tank>,
-    wall_query: Query<(), With<Wall>>,
+    mut tank_query: Query<(&Tank, &mut PowerUps)>,
+    mut wall_query: Query<WallHit, With<Wall>>,
+    solid_query: Query<(), Solid>,
+
+    mut tank_hit: EventWriter<TankHit>,
+    mut wall_destroyed: EventWriter<WallDestroyed>,
 
     rapier_context: Res<RapierContext>,
 ) {
+    // bullets fly over low walls, water and holes, and through the walls despawned before their collider is removed
+    let is_solid = |entity| solid_query.contains(entity);
+
     // todo!() Replace bullet_query with a parallel iter
     for (bullet, transform, bullet_entity) in &bullet_query{
         let pos = transform.translation;
@@ -97,6 +132,7 @@ pub fn bullet_collision(
                 let mut filter = QueryFilter::default();
 
                 filter.exclude_collider = Some(bullet.0);
+                filter.predicate = Some(&is_solid);
 
                 filter
             };
@@ -118,9 +154,32 @@ pub fn bullet_collision(
         };
 
         if let Some((hit_entity, _hit)) = cast_shape {
-            match (wall_query.get(hit_entity), tank_query.get(hit_entity)) {
-                (Ok(_), _) => {},//do nothing
-                (_, Ok(tank)) => {
+            match (wall_query.get_mut(hit_entity), tank_query.get_mut(hit_entity)) {
+                (Ok((Some(mut health), wall_transform)), _) => {
+                    // a wall hit by several bullets in the same tick is only destroyed once
+                    match health.hit_points {
+                        0 => {},
+                        1 => {
+                            let tile = (wall_transform.translation.truncate() / WALL_SIZE).round();
+                            wall_destroyed.send(WallDestroyed{
+                                source: bullet.0,
+                                tile: (tile.x as usize, tile.y as usize)
+                            });
+
+                            health.hit_points = 0;
+                            commands.entity(hit_entity).despawn();
+                        },
+                        _ => health.hit_points -= 1,
+                    }
+                },
+                (Ok((None, _)), _) => {},//do nothing
+                (_, Ok((_, mut power_ups))) if power_ups.is_protected() => power_ups.absorb_hit(),
+                (_, Ok((tank, _))) => {
+                    tank_hit.send(TankHit{
+                        source: bullet.0,
+                        target: hit_entity
+                    });
+
                     commands.entity(tank.turret).despawn();
                     commands.entity(hit_entity).despawn();
                     //change win state
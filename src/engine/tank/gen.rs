@@ -3,6 +3,8 @@ use std::{f32::consts::PI, time::Duration};
 use bevy::{asset::{AssetServer, Assets}, color::LinearRgba, math::Vec3, prelude::{default, BuildChildren, Commands, Component, Entity, GlobalTransform, Mesh, Rectangle, Res, ResMut, Transform}, sprite::MaterialMesh2dBundle, time::Timer};
 use bevy_rapier2d::prelude::Collider;
 
+use crate::engine::pickup::PowerUps;
+
 use super::{material::TankMaterial, vision::{VisionRay, HULL_RAY_MAX_DIST, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY, TURRET_RAY_MAX_DIST, TURRET_VISION_ANGLE}};
 
 
@@ -24,10 +26,18 @@ pub enum GunState{
     Reload(Timer)
 }
 
+/// The time it takes to reload the gun after shooting, in seconds.
+pub const RELOAD_TIME: f32 = 1.;
+
 impl GunState {
     pub fn reload() -> Self {
+        GunState::reload_in(RELOAD_TIME)
+    }
+
+    /// A gun ready again after `time` seconds, e.g. with `FastReload`.
+    pub fn reload_in(time: f32) -> Self {
         GunState::Reload(
-            Timer::new(Duration::from_secs(1), bevy::time::TimerMode::Once)
+            Timer::new(Duration::from_secs_f32(time), bevy::time::TimerMode::Once)
         )
     }
 }
@@ -89,6 +99,7 @@ pub fn create_minimal_tank(x: f32, y: f32, team_id: u8, commands: &mut Commands)
         },
         GlobalTransform::default(),
         Collider::cuboid(TANK_SIZE/2., TANK_SIZE/2.),
+        PowerUps::default(),
         VisionRay::<NUM_OF_HULL_RAY, Tank>::new(
             HULL_RAY_MAX_DIST,
             2. * PI / NUM_OF_HULL_RAY as f32,
@@ -178,6 +189,7 @@ pub fn create_tank(
             ..default()
         },
         Collider::cuboid(TANK_SIZE/2., TANK_SIZE/2.),
+        PowerUps::default(),
         VisionRay::<NUM_OF_HULL_RAY, Tank>::new(
             HULL_RAY_MAX_DIST,
             2. * PI / NUM_OF_HULL_RAY as f32,
@@ -1,9 +1,10 @@
-use std::f32::consts::PI;
+use std::{f32::consts::PI, str::FromStr};
 
-use bevy::{math::{Vec2, Vec3}, prelude::{Entity, Event, EventReader, EventWriter, GlobalTransform, Query, Res, Transform, With, Without}};
-use bevy_rapier2d::{plugin::RapierContext, prelude::{Collider, QueryFilter, ShapeCastOptions}};
+use bevy::{ecs::schedule::SystemSet, math::{Vec2, Vec3}, prelude::{Entity, Event, EventReader, EventWriter, GlobalTransform, Query, Res, Resource, Transform, With, Without}};
+use bevy_rapier2d::{plugin::RapierContext, prelude::{Collider, QueryFilter, QueryFilterFlags, ShapeCastOptions}};
+use serde::{Deserialize, Serialize};
 
-use crate::{engine::game_time::DeltaTime, player::PlayerID};
+use crate::{engine::{game_time::DeltaTime, map::terrain::{is_inside, Water, WATER_SPEED_FACTOR}, pickup::PowerUps}, player::PlayerID};
 
 use super::{bullet::NewBullet, gen::{GunState, Tank, Turret, TANK_SIZE}};
 
@@ -20,7 +21,7 @@ use super::{bullet::NewBullet, gen::{GunState, Tank, Turret, TANK_SIZE}};
 /// - `SpinTurretLeft`: Instructs the turret to spin left.
 /// - `SpinTurretRight`: Instructs the turret to spin right.
 /// - `Shoot`: Instructs the tank to shoot.
-#[derive(Debug, Clone, Copy, Event)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
 pub enum Instruction<const P_FLAG: u32> {
     MoveForward,
     MoveBackward,
@@ -32,18 +33,106 @@ pub enum Instruction<const P_FLAG: u32> {
     Shoot
 }
 
-const TANK_ROTATION_SPEED: f32 = PI / 2.;
-const TURRET_ROTATION_SPEED: f32 = 3. * PI / 2.;
-const TANK_MOVE_SPEED: f32 = 100.;
+/// A player agnostic version of `Instruction`, matching the action names used by the AI API.
+///
+/// # Variants
+/// - `Shoot`: Fires the turret.
+/// - `MoveForward`: Moves the tank forward.
+/// - `MoveBackward`: Moves the tank backward.
+/// - `RotateLeft`: Rotates the tank to the left.
+/// - `RotateRight`: Rotates the tank to the right.
+/// - `SpinLeft`: Spins the turret to the left.
+/// - `SpinRight`: Spins the turret to the right.
+/// - `Wait`: Takes no action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Shoot,
+    MoveForward,
+    MoveBackward,
+    RotateLeft,
+    RotateRight,
+    SpinLeft,
+    SpinRight,
+    Wait
+}
+
+impl Action {
+    /// Converts the action into an instruction for the given player.
+    ///
+    /// Returns `None` for `Action::Wait` since it doesn't produce an instruction.
+    pub fn instruction<const P_FLAG: u32>(self) -> Option<Instruction<P_FLAG>> {
+        match self {
+            Action::Shoot => Some(Instruction::Shoot),
+            Action::MoveForward => Some(Instruction::MoveForward),
+            Action::MoveBackward => Some(Instruction::MoveBackward),
+            Action::RotateLeft => Some(Instruction::RotateLeft),
+            Action::RotateRight => Some(Instruction::RotateRight),
+            Action::SpinLeft => Some(Instruction::SpinTurretLeft),
+            Action::SpinRight => Some(Instruction::SpinTurretRight),
+            Action::Wait => None,
+        }
+    }
+}
+
+/// The names of the actions, as sent by AI players.
+pub const ACTION_NAMES: [&str; 8] = ["shoot", "move_forward", "move_backward", "rotate_left", "rotate_right", "spin_left", "spin_right", "wait"];
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shoot" => Ok(Action::Shoot),
+            "move_forward" => Ok(Action::MoveForward),
+            "move_backward" => Ok(Action::MoveBackward),
+            "rotate_left" => Ok(Action::RotateLeft),
+            "rotate_right" => Ok(Action::RotateRight),
+            "spin_left" => Ok(Action::SpinLeft),
+            "spin_right" => Ok(Action::SpinRight),
+            "wait" => Ok(Action::Wait),
+            invalid => Err(format!("Unknown action ({invalid}), expected one of {ACTION_NAMES:?}"))
+        }
+    }
+}
+
+/// The most actions a tank can take in one tick: one movement, one turret rotation and one shot.
+pub const MAX_ACTIONS_PER_TICK: usize = 3;
+
+/// How many actions each player may take per tick, applied to every controller alike.
+///
+/// Keyboard players can act on every tick while AIs are limited by their round trips,
+/// lower `actions_per_tick` to make human-vs-AI and AI-vs-AI matches comparable.
+/// Instructions over the budget are dropped in the order they were sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct ActionPolicy {
+    pub actions_per_tick: usize,
+}
+
+impl Default for ActionPolicy {
+    fn default() -> Self {
+        ActionPolicy { actions_per_tick: MAX_ACTIONS_PER_TICK }
+    }
+}
+
+/// The systems applying instructions, controllers send theirs before it so every player's are applied on the same tick.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct ProcessInstructions;
+
+pub const TANK_ROTATION_SPEED: f32 = PI / 2.;
+pub const TURRET_ROTATION_SPEED: f32 = 3. * PI / 2.;
+pub const TANK_MOVE_SPEED: f32 = 100.;
 
 /// Processes tank instructions for movement and turret control.
 ///
 /// # Parameters
 /// - `P_FLAG`: A constant representing the player ID flag.
-/// - `tank_query`: A query for the tank's transform and tank components, filtered by the player ID.
+/// - `tank_query`: A query for the tank's transform, tank and power-up components, filtered by the player ID.
 /// - `turret_query`: A query for the turret's transform component, filtered by turrets that don't match the player ID.
+/// - `water_query`: A query for the tiles of water, slowing down the tanks in them.
 /// - `instruction_events`: A reader for processing instruction events.
 /// - `time`: A resource providing delta time for smooth frame-based calculations.
+/// - `policy`: How many actions the tank may take this tick.
 ///
 /// The function handles different `Instruction` variants:
 /// - **RotateLeft**: Rotates the tank left.
@@ -53,14 +142,17 @@ const TANK_MOVE_SPEED: f32 = 100.;
 /// - **SpinTurretLeft**: Rotates the turret left.
 /// - **SpinTurretRight**: Rotates the turret right.
 /// - **Shoot**: (Not implemented yet).
+#[allow(clippy::too_many_arguments)]
 pub fn process_tank_instruction<const P_FLAG: u32>(
-    mut tank_query: Query<(&mut Transform, &Tank, Entity), With<PlayerID<P_FLAG>>>,
+    mut tank_query: Query<(&mut Transform, &Tank, &mut PowerUps, Entity), With<PlayerID<P_FLAG>>>,
     mut turret_query: Query<(&mut Transform, &GlobalTransform, &mut Turret), (Without<PlayerID<P_FLAG>>, With<Turret>)>,
+    water_query: Query<(), With<Water>>,
     mut instruction_events: EventReader<Instruction<P_FLAG>>,
 
     mut new_bullet: EventWriter<NewBullet>,
 
     time: Res<DeltaTime>,
+    policy: Res<ActionPolicy>,
 
     rapier_context: Res<RapierContext>,
 
@@ -76,10 +168,17 @@ pub fn process_tank_instruction<const P_FLAG: u32>(
             tank_query.iter_mut()
                 .zip(&mut tank_actions)
                 .for_each(|(
-                    (mut transform, tank, player_entity),
+                    (mut transform, tank, mut power_ups, player_entity),
                     viable_actions
                 )|{
+                    if viable_actions.iter().filter(|taken| **taken).count() >= policy.actions_per_tick {
+                        return;
+                    }
                     let transform = transform.as_mut();
+                    let speed = match is_inside(&rapier_context, transform.translation.truncate(), |entity| water_query.contains(entity)) {
+                        true => TANK_MOVE_SPEED * WATER_SPEED_FACTOR,
+                        false => TANK_MOVE_SPEED,
+                    } * power_ups.speed_factor();
 
                     match (inst, &viable_actions) {
                         // movement
@@ -113,6 +212,7 @@ pub fn process_tank_instruction<const P_FLAG: u32>(
                             transform.translation = new_move_pos::<false>(
                                 transform.translation,
                                 transform.up().as_vec3(),
+                                speed,
                                 time.0,
 
                                 &rapier_context,
@@ -127,6 +227,7 @@ pub fn process_tank_instruction<const P_FLAG: u32>(
                             transform.translation = new_move_pos::<false>(
                                 transform.translation,
                                 transform.down().as_vec3(),
+                                speed,
                                 time.0,
 
                                 &rapier_context,
@@ -176,7 +277,9 @@ pub fn process_tank_instruction<const P_FLAG: u32>(
                                 });
                                 viable_actions[2] = true;
 
-                                turret.0 = GunState::reload();
+                                if !power_ups.use_ammo() {
+                                    turret.0 = GunState::reload_in(power_ups.reload_time());
+                                }
                             }
                         },
                         _=> {}
@@ -214,6 +317,8 @@ fn update_rotation<const DEBUG: bool>(
             let mut filter = QueryFilter::default();
 
             filter.exclude_collider = Some(player);
+            // water and holes don't stop the tanks
+            filter.flags = QueryFilterFlags::EXCLUDE_SENSORS;
 
             filter
         };
@@ -244,6 +349,7 @@ fn update_rotation<const DEBUG: bool>(
 fn new_move_pos<const DEBUG: bool>(
     start_pos: Vec3,
     dir: Vec3,
+    speed: f32,
     delta_time: f32,
 
     rapier_context: &Res<RapierContext>,
@@ -252,7 +358,7 @@ fn new_move_pos<const DEBUG: bool>(
     // gizmos: &mut Gizmos
 ) -> Vec3{
     
-    let new_pos = start_pos + TANK_MOVE_SPEED * dir * delta_time;
+    let new_pos = start_pos + speed * dir * delta_time;
     let rot = get_rotation_z(Vec2::new(dir.x, dir.y));
     if DEBUG {
         // gizmos.rect_2d(Vec2::new(new_pos.x, new_pos.y), rot, Vec2::splat(TANK_SIZE), GREEN);
@@ -267,11 +373,13 @@ fn new_move_pos<const DEBUG: bool>(
             let mut filter = QueryFilter::default();
 
             filter.exclude_collider = Some(player);
+            // water and holes don't stop the tanks
+            filter.flags = QueryFilterFlags::EXCLUDE_SENSORS;
 
             filter
         };
         let options = ShapeCastOptions {
-            max_time_of_impact: TANK_MOVE_SPEED * delta_time,
+            max_time_of_impact: speed * delta_time,
             target_distance: 0.0,
             stop_at_penetration: true,
             compute_impact_geometry_on_penetration: true,
@@ -292,7 +400,7 @@ fn new_move_pos<const DEBUG: bool>(
     }
 
 
-    start_pos + TANK_MOVE_SPEED * dir * delta_time
+    start_pos + speed * dir * delta_time
 }
 
 pub fn get_rotation_z(dir: Vec2) -> f32 {
@@ -1,16 +1,16 @@
 //! This module handles the gameplay mechanics for tanks in a Bevy-based game,
 //! including their creation, movement, turret control, and associated instructions.
 
-use bevy::{app::{AppExit, Plugin, PostUpdate, Update}, prelude::{in_state, EventWriter, IntoSystemConfigs, Query, With}, sprite::Material2dPlugin};
-use bullet::{bullet_collision, create_bullet, create_bullet_minimal, reload_gun, update_bullet_pos, NewBullet};
+use bevy::{app::{AppExit, Plugin, PostUpdate, Update}, prelude::{in_state, Event, EventReader, EventWriter, IntoSystemConfigs, IntoSystemSetConfigs, Query, With}, sprite::Material2dPlugin};
+use bullet::{bullet_collision, create_bullet, create_bullet_minimal, reload_gun, update_bullet_pos, NewBullet, TankHit, WallDestroyed};
 use gen::Tank;
-use instruction::{process_tank_instruction, Instruction};
+use instruction::{process_tank_instruction, ActionPolicy, Instruction, ProcessInstructions};
 use material::TankMaterial;
 use vision::{update_tank_vision_ray, update_turret_vision_ray, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY};
 
 use crate::player::PlayerID;
 
-use super::map::gen_state::Step;
+use super::{map::gen_state::Step, Simulation};
 
 pub mod instruction;
 pub mod gen;
@@ -18,11 +18,28 @@ pub mod vision;
 pub mod bullet;
 pub mod material;
 
+/// Sent when a player's tank is removed because its controller gave up, e.g. an AI that kept timing out.
+///
+/// # Fields
+/// - `player`: The id of the player.
+/// - `reason`: What went wrong, e.g. `kept timing out`.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct TankForfeited {
+    pub player: u32,
+    pub reason: String,
+}
+
+/// Prints the id of the winner once a side has no tank left, `-1` for a draw, and exits.
+///
+/// The result is always the last line, a forfeit is printed on the line before it,
+/// e.g. `Player 0 forfeits, its controller kept timing out`.
 fn end_game<const P_FLAG_1: u32, const P_FLAG_2: u32,>(
     tanks: Query<(Option<&PlayerID<P_FLAG_1>>, Option<&PlayerID<P_FLAG_2>>), With<Tank>>,
+    mut forfeits: EventReader<TankForfeited>,
 
     mut app_exit_events: EventWriter<AppExit>,
 ) {
+
     let tank_count = tanks.iter()
         .map(|(tank_1, tank_2)| (
             tank_1.map(|_| 1u16).unwrap_or_else(|| 0u16),
@@ -33,21 +50,28 @@ fn end_game<const P_FLAG_1: u32, const P_FLAG_2: u32,>(
             |acc, next| (acc.0 + next.0, acc.1 + next.1)
         );
 
-    match &tank_count {
+    let result = match &tank_count {
         (0, 0) => {
-            println!("-1")
+            "-1".to_string()
         },
         (0, _) => {
-            println!("{P_FLAG_2}")
+            P_FLAG_2.to_string()
         },
         (_, 0) => {
-            println!("{P_FLAG_1}")
+            P_FLAG_1.to_string()
         },
         (_, _) => {
+            // the forfeits only matter in the tick that ends the game
+            forfeits.clear();
             return;
         }
     };
 
+    for TankForfeited { player, reason } in forfeits.read() {
+        println!("Player {player} forfeits, its controller {reason}");
+    }
+    println!("{result}");
+
     app_exit_events.send(AppExit::Success);
 }
 
@@ -59,24 +83,34 @@ impl Plugin for TankPlugin{
             .add_event::<Instruction<0>>()
             .add_event::<Instruction<1>>()
             .add_event::<NewBullet>()
-            .add_systems(Update, process_tank_instruction::<0>)
-            .add_systems(Update, process_tank_instruction::<1>)
-            .add_systems(Update, update_tank_vision_ray::<NUM_OF_HULL_RAY>)
-            .add_systems(Update, update_turret_vision_ray::<NUM_OF_TURRET_RAY>)
-            .add_systems(Update, update_bullet_pos)
-            .add_systems(Update, bullet_collision)
-            .add_systems(Update, reload_gun)
+            .add_event::<TankHit>()
+            .add_event::<WallDestroyed>()
+            .add_event::<TankForfeited>()
+            .init_resource::<ActionPolicy>()
+            .configure_sets(Update, ProcessInstructions.in_set(Simulation))
+            .add_systems(Update, process_tank_instruction::<0>.in_set(ProcessInstructions))
+            .add_systems(Update, process_tank_instruction::<1>.in_set(ProcessInstructions))
+            .add_systems(Update, (
+                update_tank_vision_ray::<NUM_OF_HULL_RAY>,
+                update_turret_vision_ray::<NUM_OF_TURRET_RAY>,
+                update_bullet_pos,
+                // the rays see through the walls destroyed during the tick
+                bullet_collision
+                    .before(update_tank_vision_ray::<NUM_OF_HULL_RAY>)
+                    .before(update_turret_vision_ray::<NUM_OF_TURRET_RAY>),
+                reload_gun
+            ).in_set(Simulation))
             .add_systems(PostUpdate, end_game::<0, 1>.run_if(
                 in_state(Step::Finished)
-            ));
+            ).in_set(Simulation));
         
         match self.0 {
             true => {
                 app.add_plugins(Material2dPlugin::<TankMaterial>::default());
-                app.add_systems(Update, create_bullet);
+                app.add_systems(Update, create_bullet.in_set(Simulation));
             },
             false => {
-                app.add_systems(Update, create_bullet_minimal);
+                app.add_systems(Update, create_bullet_minimal.in_set(Simulation));
             }
         }
     }
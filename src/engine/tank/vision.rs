@@ -1,6 +1,6 @@
 use std::{f32::consts::PI, marker::PhantomData};
 
-use bevy::{math::Vec2, prelude::{Component, Entity, GlobalTransform, Query, Res, With}};
+use bevy::{ecs::{query::Has, system::SystemParam}, math::Vec2, prelude::{Component, Entity, GlobalTransform, Query, Res, With}};
 use bevy_rapier2d::{na::{Matrix2, Vector2}, plugin::RapierContext, prelude::QueryFilter};
 use serde::Serialize;
 
@@ -10,7 +10,7 @@ use bevy::color::palettes::css::{RED, BLUE, GREEN};
 #[cfg(feature = "debug")]
 use bevy::prelude::Gizmos;
 
-use crate::engine::map::Wall;
+use crate::engine::{map::{terrain::{Destructible, Hole, LowWall, WallHealth, Water}, Wall}, pickup::Pickup};
 
 
 
@@ -18,16 +18,92 @@ use super::gen::{Tank, Turret, TANK_SIZE};
 
 
 pub const NUM_OF_HULL_RAY: usize = 8;
-pub(super) const HULL_RAY_MAX_DIST: f32 = TANK_SIZE * 4.;
+pub const HULL_RAY_MAX_DIST: f32 = TANK_SIZE * 4.;
 
-pub(super) const TURRET_VISION_ANGLE: f32 = PI / 12.;
+pub const TURRET_VISION_ANGLE: f32 = PI / 12.;
 pub const NUM_OF_TURRET_RAY: usize = 5;
-pub(super) const TURRET_RAY_MAX_DIST: f32 = TANK_SIZE * 32.;
+pub const TURRET_RAY_MAX_DIST: f32 = TANK_SIZE * 32.;
 
+/// What a vision ray hit, with the distance to it.
+///
+/// # Variants
+/// - `Wall`: A wall.
+/// - `Enemy`: A tank.
+/// - `DestructibleWall`: A wall that can be shot through.
+/// - `LowWall`: A wall bullets fly over, only seen by the hull's rays.
+/// - `Water`: Water slowing down tanks, only seen by the hull's rays.
+/// - `Hole`: A hole destroying tanks, only seen by the hull's rays.
+/// - `Pickup`: A pickup giving a power-up to the tank driving over it, only seen by the hull's rays.
 #[derive(Debug, Clone, Copy, Serialize)]
 pub enum VisionHit {
     Wall(f32),
-    Enemy(f32)
+    Enemy(f32),
+    DestructibleWall(f32),
+    LowWall(f32),
+    Water(f32),
+    Hole(f32),
+    Pickup(f32)
+}
+
+impl VisionHit {
+    /// The distance to what the ray hit.
+    pub fn dist(&self) -> f32 {
+        match *self {
+            VisionHit::Wall(dist)
+            | VisionHit::Enemy(dist)
+            | VisionHit::DestructibleWall(dist)
+            | VisionHit::LowWall(dist)
+            | VisionHit::Water(dist)
+            | VisionHit::Hole(dist)
+            | VisionHit::Pickup(dist) => dist
+        }
+    }
+}
+
+/// What vision rays can hit, to tell them apart.
+#[derive(SystemParam)]
+pub struct VisionTargets<'w, 's> {
+    tanks: Query<'w, 's, (), With<Tank>>,
+    walls: Query<'w, 's, (Has<Destructible>, Option<&'static WallHealth>), With<Wall>>,
+    low_walls: Query<'w, 's, (), With<LowWall>>,
+    water: Query<'w, 's, (), With<Water>>,
+    holes: Query<'w, 's, (), With<Hole>>,
+    pickups: Query<'w, 's, (), With<Pickup>>,
+}
+
+impl VisionTargets<'_, '_> {
+    /// What a ray hitting `entity` after `toi` saw.
+    ///
+    /// # Panics
+    /// Panics if the entity is none of the targets.
+    pub fn classify(&self, entity: Entity, toi: f32) -> VisionHit {
+        if self.tanks.contains(entity) {
+            return VisionHit::Enemy(toi);
+        }
+
+        match self.walls.get(entity) {
+            Ok((true, _)) => VisionHit::DestructibleWall(toi),
+            Ok((false, _)) => VisionHit::Wall(toi),
+            Err(_) if self.low_walls.contains(entity) => VisionHit::LowWall(toi),
+            Err(_) if self.water.contains(entity) => VisionHit::Water(toi),
+            Err(_) if self.holes.contains(entity) => VisionHit::Hole(toi),
+            Err(_) if self.pickups.contains(entity) => VisionHit::Pickup(toi),
+            Err(_) => panic!("This should never happen")
+        }
+    }
+
+    /// Whether the turret's rays see over `entity`, like bullets fly over it.
+    pub fn is_low(&self, entity: Entity) -> bool {
+        self.low_walls.contains(entity) || self.water.contains(entity) || self.holes.contains(entity) || self.pickups.contains(entity)
+    }
+
+    /// Whether `entity` is a destroyed wall or was despawned, its collider is only removed at the end of the tick.
+    pub fn is_gone(&self, entity: Entity) -> bool {
+        match self.walls.get(entity) {
+            Ok((_, health)) => health.is_some_and(WallHealth::is_destroyed),
+            Err(_) => !self.tanks.contains(entity) && !self.is_low(entity),
+        }
+    }
 }
 
 #[derive(Component, Debug, Clone, Copy)]
@@ -68,8 +144,7 @@ impl<const RAY_COUNT: usize, S> VisionRay<RAY_COUNT, S> {
 /// 
 /// * `rays`: A query containing mutable references to `VisionRay` components, 
 ///    global transformations, and associated entity.
-/// * `tanks`: A query that filters entities with the `Tank` component.
-/// * `walls`: A query that filters entities with the `Wall` component.
+/// * `targets`: The tanks, walls and other tiles the rays can hit.
 /// * `rapier_context`: A reference to the physics context (`RapierContext`) 
 ///    used for ray casting.
 /// * `gizmos`: A mutable reference to `Gizmos` for visual debugging.
@@ -78,8 +153,9 @@ impl<const RAY_COUNT: usize, S> VisionRay<RAY_COUNT, S> {
 /// 
 /// For each ray:
 /// - Casts a ray in the direction specified by the tank's orientation.
-/// - Checks for collisions with either walls or tanks.
-/// - Marks the ray with the type of object hit (`Wall` or `Enemy`).
+/// - Checks for collisions with tanks, walls, water, holes and pickups, ignoring the
+///   water, hole or pickup the tank is in.
+/// - Marks the ray with the type of object hit (e.g. `Wall` or `Enemy`).
 /// - Optionally displays debugging information such as the ray's path and 
 ///   hit points.
 ///
@@ -95,8 +171,7 @@ impl<const RAY_COUNT: usize, S> VisionRay<RAY_COUNT, S> {
 pub fn update_tank_vision_ray<const RAY_COUNT: usize>(
     mut rays: Query<(&mut VisionRay<RAY_COUNT, Tank>, &GlobalTransform, Entity)>,
 
-    tanks: Query<(), With<Tank>>,
-    walls: Query<(), With<Wall>>,
+    targets: VisionTargets,
 
     rapier_context: Res<RapierContext>,
 
@@ -116,10 +191,18 @@ pub fn update_tank_vision_ray<const RAY_COUNT: usize>(
 
         let max_toi = *max_dist;
         let solid = true;
+        // the water or hole the tank is in would hide everything else
+        let mut inside = Vec::new();
+        rapier_context.intersections_with_point(ray_pos, QueryFilter::default().exclude_solids(), |entity| {
+            inside.push(entity);
+            true
+        });
+        let outside = |entity| !inside.contains(&entity) && !targets.is_gone(entity);
         let filter = {
             let mut filter = QueryFilter::default();
 
             filter.exclude_collider = Some(player_entity);
+            filter.predicate = Some(&outside);
 
             filter
         };
@@ -146,32 +229,21 @@ pub fn update_tank_vision_ray<const RAY_COUNT: usize>(
                     filter
                 );
 
-                *hit_marker = match ray_cast {
-                    Some((entity, toi)) => {
-                        match (tanks.contains(entity), walls.contains(entity)) {
-                            (true, false) => {
-                                #[cfg(feature = "debug")]
-                                {
-                                    let hit_point = ray_pos + Vec2::new(ray_dir[0], ray_dir[1]) * toi;
-                                    gizmos.circle_2d(hit_point, 5., RED);
-                                }
-                                
-                                Some(VisionHit::Enemy(toi))
-                            },
-                            (false, true) => {
-                                #[cfg(feature = "debug")]
-                                {
-                                    let hit_point = ray_pos + Vec2::new(ray_dir[0], ray_dir[1]) * toi;
-                                    gizmos.circle_2d(hit_point, 5., BLUE);
-                                }
-                                
-                                Some(VisionHit::Wall(toi))
-                            },
-                            _ => panic!("This should never happen")
-                        }
-                    },
-                    None => None
-                };
+                *hit_marker = ray_cast.map(|(entity, toi)| {
+                    let hit = targets.classify(entity, toi);
+
+                    #[cfg(feature = "debug")]
+                    {
+                        let hit_point = ray_pos + Vec2::new(ray_dir[0], ray_dir[1]) * toi;
+                        let color = match hit {
+                            VisionHit::Enemy(_) => RED,
+                            _ => BLUE
+                        };
+                        gizmos.circle_2d(hit_point, 5., color);
+                    }
+
+                    hit
+                });
 
                 ray_dir = *rotation_matrix * ray_dir;
             });
@@ -190,8 +262,7 @@ pub fn update_tank_vision_ray<const RAY_COUNT: usize>(
 ///    the associated tank, and entity.
 /// * `turrets`: A query that filters entities with the `Turret` component to get
 ///    the global transform of the turret.
-/// * `tanks`: A query that filters entities with the `Tank` component.
-/// * `walls`: A query that filters entities with the `Wall` component.
+/// * `targets`: The tanks, walls and other tiles the rays can hit.
 /// * `rapier_context`: A reference to the physics context (`RapierContext`)
 ///    used for ray casting.
 /// * `gizmos`: A mutable reference to `Gizmos` for visual debugging.
@@ -200,8 +271,9 @@ pub fn update_tank_vision_ray<const RAY_COUNT: usize>(
 ///
 /// For each ray:
 /// - Casts a ray in the direction specified by the turret's orientation.
-/// - Checks for collisions with either walls or tanks.
-/// - Marks the ray with the type of object hit (`Wall` or `Enemy`).
+/// - Checks for collisions with either walls or tanks, seeing over low walls,
+///   water and holes like bullets.
+/// - Marks the ray with the type of object hit (e.g. `Wall` or `Enemy`).
 /// - Optionally displays debugging information such as the ray's path and
 ///   hit points.
 ///
@@ -218,8 +290,7 @@ pub fn update_turret_vision_ray<const RAY_COUNT: usize>(
     mut rays: Query<(&mut VisionRay<RAY_COUNT, Turret>, &Tank, Entity)>,
 
     turrets: Query<&GlobalTransform, With<Turret>>,
-    tanks: Query<(), With<Tank>>,
-    walls: Query<(), With<Wall>>,
+    targets: VisionTargets,
 
     rapier_context: Res<RapierContext>,
     
@@ -241,10 +312,12 @@ pub fn update_turret_vision_ray<const RAY_COUNT: usize>(
 
         let max_toi = *max_dist;
         let solid = true;
+        let high = |entity| !targets.is_low(entity) && !targets.is_gone(entity);
         let filter = {
             let mut filter = QueryFilter::default();
 
             filter.exclude_collider = Some(player_entity);
+            filter.predicate = Some(&high);
 
             filter
         };
@@ -272,32 +345,21 @@ pub fn update_turret_vision_ray<const RAY_COUNT: usize>(
                     filter
                 );
 
-                *hit_marker = match ray_cast {
-                    Some((entity, toi)) => {
-                        match (tanks.contains(entity), walls.contains(entity)) {
-                            (true, false) => {
-                                #[cfg(feature = "debug")]
-                                {
-                                    let hit_point = ray_pos + Vec2::new(ray_dir[0], ray_dir[1]) * toi;
-                                    gizmos.circle_2d(hit_point, 5., RED);
-                                }
-                                
-                                Some(VisionHit::Enemy(toi))
-                            },
-                            (false, true) => {
-                                #[cfg(feature = "debug")]
-                                {
-                                    let hit_point = ray_pos + Vec2::new(ray_dir[0], ray_dir[1]) * toi;
-                                    gizmos.circle_2d(hit_point, 5., BLUE);
-                                }
-                                
-                                Some(VisionHit::Wall(toi))
-                            },
-                            _ => panic!("This should never happen")
-                        }
-                    },
-                    None => None
-                };
+                *hit_marker = ray_cast.map(|(entity, toi)| {
+                    let hit = targets.classify(entity, toi);
+
+                    #[cfg(feature = "debug")]
+                    {
+                        let hit_point = ray_pos + Vec2::new(ray_dir[0], ray_dir[1]) * toi;
+                        let color = match hit {
+                            VisionHit::Enemy(_) => RED,
+                            _ => BLUE
+                        };
+                        gizmos.circle_2d(hit_point, 5., color);
+                    }
+
+                    hit
+                });
 
                 ray_dir = *rotation_matrix * ray_dir;
             });
@@ -0,0 +1,222 @@
+//! This module provides `Match`, a handle for embedding a headless game in
+//! other Rust projects and tests. The simulation is advanced manually with
+//! `step`, players are read with `observe` and controlled with `act`.
+
+use std::{thread, time::{Duration, Instant}};
+
+use bevy::{app::{App, PluginsState}, prelude::{Entity, GlobalTransform, State, With}};
+use serde::Serialize;
+
+use crate::{
+    engine::{map::{gen_state::Step, CurrentMap, MapRng}, tank::{gen::{Tank, Turret}, instruction::Action, vision::{VisionRay, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY}}, EnginePlugin},
+    player::{fault::{Fault, PlayerForfeit}, observation::PlayerData, telemetry::{PlayerTelemetry, TelemetrySummary}, PlayerID}
+};
+
+/// The delta time used by `Match` when none is given, in seconds.
+pub const DEFAULT_MATCH_DELTA_TIME: f32 = 1. / 60.;
+
+const MAP_LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The outcome of a finished match.
+///
+/// # Variants
+/// - `Winner`: The id of the last player standing.
+/// - `Draw`: Both tanks were destroyed.
+/// - `Forfeit`: The id of the winner, the other player's AI failed and its tank was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MatchOutcome {
+    Winner(u32),
+    Draw,
+    Forfeit { winner: u32, fault: Fault }
+}
+
+/// A headless game between player `0` and player `1` that is driven by the caller.
+///
+/// No `PlayerController` is attached, every instruction comes from `act`.
+pub struct Match {
+    app: App,
+}
+
+impl Match {
+    /// Creates a headless match and blocks until the map has been generated.
+    ///
+    /// # Parameters
+    /// - `map`: The name of the map file in `assets/maps`, a generated map (`gen:...`) or `None` for a
+    ///   random map, see `CurrentMap::from_name`.
+    /// - `delta_time`: The simulated time between steps in seconds.
+    ///
+    /// # Panics
+    /// Panics if the map can't be loaded or a generated map is invalid.
+    pub fn new(map: Option<String>, delta_time: f32) -> Self {
+        Self::from_app(App::new(), map, delta_time)
+    }
+
+    /// Creates a reproducible headless match, the seed decides the map (if `map` is `None`) and the spawn points.
+    pub fn with_seed(map: Option<String>, delta_time: f32, seed: u64) -> Self {
+        let mut app = App::new();
+        app.insert_resource(MapRng::seeded(seed));
+
+        Self::from_app(app, map, delta_time)
+    }
+
+    /// Creates a headless match on top of an existing `App`.
+    ///
+    /// Resources inserted into `app` before this call take priority over the engine defaults.
+    pub fn from_app(mut app: App, map: Option<String>, delta_time: f32) -> Self {
+        let map = CurrentMap::from_name(map).unwrap_or_else(|err| panic!("{err}"));
+        app.add_plugins(EnginePlugin(false, map, Some(delta_time)));
+
+        while app.plugins_state() == PluginsState::Adding {
+            thread::yield_now();
+        }
+        app.finish();
+        app.cleanup();
+
+        let mut game_match = Match { app };
+
+        let start = Instant::now();
+        while *game_match.app.world().resource::<State<Step>>().get() != Step::Finished {
+            if start.elapsed() > MAP_LOAD_TIMEOUT {
+                panic!("Map failed to load");
+            }
+
+            game_match.app.update();
+            thread::yield_now();
+        }
+        // run once more so vision rays see the freshly spawned entities
+        game_match.app.update();
+
+        game_match
+    }
+
+    /// Advances the simulation by one tick.
+    ///
+    /// Does nothing once the match has finished.
+    pub fn step(&mut self) {
+        if self.outcome().is_some() {
+            return;
+        }
+
+        self.app.update();
+    }
+
+    /// Returns the sensor data of a player's tank, or `None` if it has been destroyed.
+    ///
+    /// # Parameters
+    /// - `player`: The player id (`0` or `1`).
+    pub fn observe(&mut self, player: u32) -> Option<PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY>> {
+        match player {
+            0 => self.observe_player::<0>(),
+            1 => self.observe_player::<1>(),
+            _ => None
+        }
+    }
+
+    /// Queues an action for a player, to be applied on the next `step`.
+    ///
+    /// # Parameters
+    /// - `player`: The player id (`0` or `1`).
+    /// - `action`: The action the player's tank should take.
+    pub fn act(&mut self, player: u32, action: Action) {
+        let world = self.app.world_mut();
+
+        match player {
+            0 => {
+                if let Some(inst) = action.instruction::<0>() {
+                    world.send_event(inst);
+                }
+            },
+            1 => {
+                if let Some(inst) = action.instruction::<1>() {
+                    world.send_event(inst);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Returns the outcome of the match, or `None` while both tanks are alive.
+    pub fn outcome(&mut self) -> Option<MatchOutcome> {
+        match (self.is_alive::<0>(), self.is_alive::<1>()) {
+            (true, true) => None,
+            (true, false) => Some(self.win::<0, 1>()),
+            (false, true) => Some(self.win::<1, 0>()),
+            (false, false) => Some(MatchOutcome::Draw),
+        }
+    }
+
+    /// Returns the entity of a player's tank, or `None` if it has been destroyed.
+    ///
+    /// # Parameters
+    /// - `player`: The player id (`0` or `1`).
+    pub fn tank(&mut self, player: u32) -> Option<Entity> {
+        match player {
+            0 => self.tank_entity::<0>(),
+            1 => self.tank_entity::<1>(),
+            _ => None
+        }
+    }
+
+    /// Returns what was measured about a player's AI, or `None` if the player isn't controlled by an AI.
+    ///
+    /// # Parameters
+    /// - `player`: The player id (`0` or `1`).
+    pub fn telemetry(&self, player: u32) -> Option<TelemetrySummary> {
+        let world = self.app.world();
+
+        match player {
+            0 => world.get_resource::<PlayerTelemetry<0>>().map(PlayerTelemetry::summary),
+            1 => world.get_resource::<PlayerTelemetry<1>>().map(PlayerTelemetry::summary),
+            _ => None
+        }
+    }
+
+    /// Gives direct access to the underlying Bevy `App`.
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    fn win<const WINNER: u32, const LOSER: u32>(&self) -> MatchOutcome {
+        let fault = self.app.world()
+            .get_resource::<PlayerForfeit<LOSER>>()
+            .and_then(|forfeit| forfeit.0.get());
+
+        match fault {
+            Some(fault) => MatchOutcome::Forfeit { winner: WINNER, fault },
+            None => MatchOutcome::Winner(WINNER),
+        }
+    }
+
+    fn is_alive<const P_FLAG: u32>(&mut self) -> bool {
+        self.tank_entity::<P_FLAG>().is_some()
+    }
+
+    fn tank_entity<const P_FLAG: u32>(&mut self) -> Option<Entity> {
+        let world = self.app.world_mut();
+
+        world.query_filtered::<Entity, (With<Tank>, With<PlayerID<P_FLAG>>)>()
+            .iter(world)
+            .next()
+    }
+
+    fn observe_player<const P_FLAG: u32>(&mut self) -> Option<PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY>> {
+        let world = self.app.world_mut();
+
+        let (transform, tank, tank_vision, turret_vision) = world.query_filtered::<(
+                &GlobalTransform,
+                &Tank,
+                &VisionRay<NUM_OF_HULL_RAY, Tank>,
+                &VisionRay<NUM_OF_TURRET_RAY, Turret>
+            ), With<PlayerID<P_FLAG>>>()
+            .iter(world)
+            .next()?;
+        let turret_transform = world.get::<GlobalTransform>(tank.turret)?;
+
+        Some(PlayerData::new(
+            transform,
+            turret_transform,
+            turret_vision.rays,
+            tank_vision.rays
+        ))
+    }
+}
@@ -0,0 +1,210 @@
+//! This module provides a step/reset reinforcement learning environment over
+//! the headless engine. Unlike the `/brain` API the simulation only advances
+//! when `step` is called, so training runs are fast and reproducible.
+
+use bevy::{ecs::event::{Events, ManualEventReader}, prelude::Entity};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    engine::{map::terrain::TankFell, tank::{bullet::TankHit, instruction::Action}},
+    game_match::{Match, MatchOutcome, DEFAULT_MATCH_DELTA_TIME},
+    player::observation::PlayerDataSerialized
+};
+
+pub mod server;
+
+/// The number of players in a match.
+pub const NUM_OF_PLAYERS: usize = 2;
+
+/// The default number of steps before an episode is truncated.
+pub const DEFAULT_MAX_STEPS: usize = 60 * 60 * 2;
+
+/// The observation of a single player, `None` once its tank has been destroyed.
+pub type Observation = Option<PlayerDataSerialized>;
+
+/// The weights used to compute each player's reward for a step.
+///
+/// # Fields
+/// - `hit`: Reward for each bullet that hits the opponent.
+/// - `damage`: Reward for each bullet that hits the player and for falling into a hole (usually negative).
+/// - `survival`: Reward for each step the player's tank is alive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewardConfig {
+    pub hit: f32,
+    pub damage: f32,
+    pub survival: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            hit: 1.,
+            damage: -1.,
+            survival: 0.,
+        }
+    }
+}
+
+/// Extra information about a step that isn't part of the reward.
+///
+/// # Fields
+/// - `step`: The number of steps taken since the last reset.
+/// - `hits`: The number of hits each player landed during this step.
+/// - `outcome`: The outcome of the match once it has finished.
+/// - `truncated`: Whether the episode ended because it reached `max_steps`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepInfo {
+    pub step: usize,
+    pub hits: [u32; NUM_OF_PLAYERS],
+    pub outcome: Option<MatchOutcome>,
+    pub truncated: bool,
+}
+
+/// The result of `GymEnv::step`, indexed by player id.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub obs: [Observation; NUM_OF_PLAYERS],
+    pub reward: [f32; NUM_OF_PLAYERS],
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+/// A reinforcement learning environment where both players are controlled by the caller.
+pub struct GymEnv {
+    pub reward: RewardConfig,
+    pub max_steps: usize,
+    pub delta_time: f32,
+
+    game_id: String,
+    game_match: Option<Match>,
+    tanks: [Option<Entity>; NUM_OF_PLAYERS],
+    hit_reader: ManualEventReader<TankHit>,
+    fell_reader: ManualEventReader<TankFell>,
+    steps: usize,
+}
+
+impl Default for GymEnv {
+    fn default() -> Self {
+        Self::new(RewardConfig::default(), DEFAULT_MAX_STEPS, DEFAULT_MATCH_DELTA_TIME)
+    }
+}
+
+impl GymEnv {
+    /// Creates an environment, no match is running until `reset` is called.
+    ///
+    /// # Parameters
+    /// - `reward`: The reward shaping weights.
+    /// - `max_steps`: The number of steps before an episode is truncated.
+    /// - `delta_time`: The simulated time of each step in seconds.
+    pub fn new(reward: RewardConfig, max_steps: usize, delta_time: f32) -> Self {
+        Self {
+            reward,
+            max_steps,
+            delta_time,
+
+            game_id: String::new(),
+            game_match: None,
+            tanks: [None; NUM_OF_PLAYERS],
+            hit_reader: Default::default(),
+            fell_reader: Default::default(),
+            steps: 0,
+        }
+    }
+
+    /// Starts a new episode and returns the first observation of each player.
+    ///
+    /// # Parameters
+    /// - `seed`: The seed used to pick the map (if `map` is `None`) and the spawn points.
+    /// - `map`: The name of the map file in `assets/maps`.
+    pub fn reset(&mut self, seed: u64, map: Option<String>) -> [Observation; NUM_OF_PLAYERS] {
+        let mut game_match = Match::with_seed(map, self.delta_time, seed);
+
+        self.game_id = Uuid::new_v4().to_string();
+        self.tanks = [game_match.tank(0), game_match.tank(1)];
+        self.hit_reader = Default::default();
+        self.fell_reader = Default::default();
+        self.steps = 0;
+        self.game_match = Some(game_match);
+
+        self.observe()
+    }
+
+    /// Applies one action per player and advances the simulation by one tick.
+    ///
+    /// # Panics
+    /// Panics if `reset` hasn't been called.
+    pub fn step(&mut self, actions: [Action; NUM_OF_PLAYERS]) -> StepResult {
+        let game_match = self.game_match.as_mut()
+            .expect("`reset` must be called before `step`");
+
+        for (player, action) in actions.into_iter().enumerate() {
+            game_match.act(player as u32, action);
+        }
+        game_match.step();
+        self.steps += 1;
+
+        let mut hits = [0u32; NUM_OF_PLAYERS];
+        let mut hits_taken = [0u32; NUM_OF_PLAYERS];
+        {
+            let events = game_match.app_mut().world().resource::<Events<TankHit>>();
+
+            for hit in self.hit_reader.read(events) {
+                if let Some(player) = self.tanks.iter().position(|tank| *tank == Some(hit.source)) {
+                    hits[player] += 1;
+                }
+                if let Some(player) = self.tanks.iter().position(|tank| *tank == Some(hit.target)) {
+                    hits_taken[player] += 1;
+                }
+            }
+
+            // a tank lost to a hole is penalised like a hit
+            let events = game_match.app_mut().world().resource::<Events<TankFell>>();
+
+            for TankFell(tank) in self.fell_reader.read(events) {
+                if let Some(player) = self.tanks.iter().position(|player_tank| *player_tank == Some(*tank)) {
+                    hits_taken[player] += 1;
+                }
+            }
+        }
+
+        let outcome = game_match.outcome();
+        let obs = self.observe();
+
+        let reward: [f32; NUM_OF_PLAYERS] = std::array::from_fn(|player| {
+            hits[player] as f32 * self.reward.hit
+                + hits_taken[player] as f32 * self.reward.damage
+                + obs[player].as_ref().map(|_| self.reward.survival).unwrap_or(0.)
+        });
+
+        let truncated = outcome.is_none() && self.steps >= self.max_steps;
+
+        StepResult {
+            obs,
+            reward,
+            done: outcome.is_some() || truncated,
+            info: StepInfo {
+                step: self.steps,
+                hits,
+                outcome,
+                truncated,
+            }
+        }
+    }
+
+    /// Returns `true` once `reset` has been called.
+    pub fn is_running(&self) -> bool {
+        self.game_match.is_some()
+    }
+
+    fn observe(&mut self) -> [Observation; NUM_OF_PLAYERS] {
+        let Some(game_match) = self.game_match.as_mut() else {
+            return [None, None];
+        };
+
+        [0, 1].map(|player| game_match.observe(player)
+            .map(|data| (self.game_id.clone(), data).into())
+        )
+    }
+}
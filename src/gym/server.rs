@@ -0,0 +1,120 @@
+//! This module serves a `GymEnv` over a local TCP socket so environments can
+//! be driven from other languages (see `ai/gym`).
+//!
+//! Messages are newline delimited JSON. Each request gets exactly one response.
+//! - `{"cmd": "reset", "seed": 0, "map": "test_1.ron", "reward": {...}, "max_steps": 7200}`
+//!   responds with `{"obs": [...]}`.
+//! - `{"cmd": "step", "actions": ["shoot", "wait"]}` responds with
+//!   `{"obs": [...], "reward": [...], "done": false, "info": {...}}`.
+//! - `{"cmd": "close"}` closes the connection.
+//!
+//! Errors are reported as `{"error": "..."}`.
+
+use std::{io::{self, BufRead, BufReader, Write}, net::{TcpListener, TcpStream, ToSocketAddrs}};
+
+use bevy::log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{map::CurrentMap, tank::instruction::Action};
+
+use super::{GymEnv, Observation, RewardConfig, NUM_OF_PLAYERS};
+
+/// A request sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum GymRequest {
+    Reset {
+        #[serde(default)]
+        seed: u64,
+        #[serde(default)]
+        map: Option<String>,
+        #[serde(default)]
+        reward: Option<RewardConfig>,
+        #[serde(default)]
+        max_steps: Option<usize>,
+    },
+    Step {
+        actions: [Action; NUM_OF_PLAYERS]
+    },
+    Close
+}
+
+#[derive(Debug, Serialize)]
+struct ResetResponse {
+    obs: [Observation; NUM_OF_PLAYERS]
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String
+}
+
+/// Listens on `addr` and serves one environment per connection, one connection at a time.
+///
+/// # Parameters
+/// - `addr`: The address to listen on, e.g. `127.0.0.1:6000`.
+pub fn serve(addr: impl ToSocketAddrs) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+
+    println!("Gym server listening on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = handle_client(stream) {
+                    warn!("Gym client disconnected - {err}");
+                }
+            },
+            Err(err) => warn!("Failed to accept gym client - {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_client(stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    let mut env = GymEnv::default();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<GymRequest>(&line) {
+            Ok(GymRequest::Reset { seed, map, reward, max_steps }) => match CurrentMap::from_name(map.clone()) {
+                // an invalid generated map would stop the server
+                Err(error) => serde_json::to_string(&ErrorResponse{ error }),
+                Ok(_) => {
+                    env.reward = reward.unwrap_or_default();
+                    if let Some(max_steps) = max_steps {
+                        env.max_steps = max_steps;
+                    }
+
+                    serde_json::to_string(&ResetResponse{ obs: env.reset(seed, map) })
+                },
+            },
+            Ok(GymRequest::Step { .. }) if !env.is_running() => {
+                serde_json::to_string(&ErrorResponse{ error: "`reset` must be called before `step`".to_string() })
+            },
+            Ok(GymRequest::Step { actions }) => {
+                serde_json::to_string(&env.step(actions))
+            },
+            Ok(GymRequest::Close) => {
+                return Ok(());
+            },
+            Err(err) => {
+                serde_json::to_string(&ErrorResponse{ error: err.to_string() })
+            }
+        }?;
+
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
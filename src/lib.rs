@@ -0,0 +1,20 @@
+//! T[AI]NK game engine.
+//!
+//! The engine can be run through the `game` binary or embedded directly in
+//! other Rust projects, either as Bevy plugins (`EnginePlugin`,
+//! `PlayerControllerPlugin`), through the `Match` handle or as a
+//! reinforcement learning environment (`gym`). Matches can be streamed to
+//! spectators with `spectator::BroadcastPlugin`. Maps are made with the
+//! `editor`.
+
+pub mod args;
+pub mod player;
+pub mod engine;
+pub mod game_match;
+pub mod gym;
+pub mod spectator;
+pub mod editor;
+
+pub use engine::{map::Map, tank::instruction::{Action, Instruction}, EnginePlugin};
+pub use game_match::{Match, MatchOutcome};
+pub use player::{PlayerController, PlayerControllerPlugin};
@@ -1,18 +1,55 @@
+use std::{fs, path::Path, process};
+
 use bevy::prelude::*;
-use player::PlayerControllerPlugin;
+use game::{args::{self, MapCommand}, editor::EditorPlugin, engine::{self, map::{import::{import_image, Palette}, fairness::Fairness, validate::validate_file, CurrentMap}}, gym, player::network::client::JoinPlugin, spectator::{client::SpectatorPlugin, BroadcastPlugin}, PlayerControllerPlugin};
 
 #[cfg(feature = "debug")]
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
 
-pub mod args;
-pub mod player;
-pub mod engine;
-
 fn main() {
     let game_builder = args::get_args();
 
+    if let Some(command) = game_builder.map_command {
+        if let Err(err) = run_map_command(command) {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(addr) = &game_builder.gym {
+        gym::server::serve(addr.as_str()).expect("Gym server failed");
+        return;
+    }
+
     let mut app = App::new();
 
+    if let Some(map) = game_builder.edit {
+        app.add_plugins(DefaultPlugins)
+            .add_plugins(EditorPlugin(map))
+            .run();
+        return;
+    }
+
+    if let Some(addr) = game_builder.spectate {
+        app.insert_resource(game_builder.connection)
+            .add_plugins(engine::EnginePlugin(true, CurrentMap::None, None))
+            .add_plugins(SpectatorPlugin(addr, true))
+            .run();
+        return;
+    }
+
+    if let Some(addr) = game_builder.join {
+        app.insert_resource(game_builder.connection)
+            .add_plugins(engine::EnginePlugin(true, CurrentMap::None, None))
+            .add_plugins(JoinPlugin(addr, game_builder.player_1))
+            .run();
+        return;
+    }
+
+    if let Some(spawn_policy) = game_builder.spawn_policy {
+        app.insert_resource(spawn_policy);
+    }
     app.add_plugins(
         engine::EnginePlugin(
             game_builder.render,
@@ -21,6 +58,19 @@ fn main() {
         )
     );
 
+    app.insert_resource(game_builder.connection)
+        .insert_resource(game_builder.action_policy);
+    if let Some(input_delay) = game_builder.input_delay {
+        app.insert_resource(input_delay);
+    }
+    if let Some(mouse_aim) = game_builder.mouse_aim {
+        app.insert_resource(mouse_aim);
+    }
+
+    // before the players, a `net:` player reuses the broadcast
+    if let Some(addr) = game_builder.broadcast {
+        app.add_plugins(BroadcastPlugin(addr));
+    }
     app.add_plugins(PlayerControllerPlugin(game_builder.player_1, game_builder.player_2));
     
 
@@ -31,4 +81,68 @@ fn main() {
     }
 
     app.run();
-}
\ No newline at end of file
+}
+
+/// Runs a `map` command, printing what it did.
+fn run_map_command(command: MapCommand) -> Result<(), String> {
+    match command {
+        MapCommand::Import { image, palette, output } => {
+            let palette = match palette {
+                Some(path) => Palette::load(path)?,
+                None => Palette::default(),
+            };
+            let output = output.unwrap_or_else(|| {
+                let name = Path::new(&image).file_stem().unwrap_or_default().to_string_lossy();
+
+                format!("assets/maps/{name}.ron")
+            });
+
+            let imported = import_image(&image, &palette)?;
+            for ([r, g, b], pixels) in &imported.unknown_colours {
+                println!("Warning: #{r:02x}{g:02x}{b:02x} isn't in the palette, its {pixels} pixels were left empty");
+            }
+
+            let ron = imported.map.to_ron().map_err(|err| err.to_string())?;
+            fs::write(&output, ron).map_err(|err| format!("Unable to write {output} ({err})"))?;
+
+            let map = &imported.map;
+            println!(
+                "Imported {image} to {output} ({}x{}, {} walls, {} spawn points)",
+                map.dim.0, map.dim.1, map.walls.len(), map.spawn_points.len()
+            );
+        },
+        MapCommand::Validate { file } => {
+            validate_file(&file)?;
+            println!("{file} is valid");
+        },
+        MapCommand::Analyse { file } => {
+            let fairness = Fairness::analyse(&validate_file(&file)?);
+
+            println!("{:<12} {:>6} {:>9} {:>7}", "spawn point", "cover", "exposure", "centre");
+            for spawn in &fairness.spawns {
+                println!(
+                    "{:<12} {:>6.2} {:>9.2} {:>7}",
+                    format!("({}, {})", spawn.spawn.0, spawn.spawn.1), spawn.cover, spawn.exposure, spawn.centre_distance
+                );
+            }
+
+            println!("\nPicked by -spawns farthest:");
+            for pair in &fairness.farthest_pairs {
+                let (spawn_1, spawn_2) = pair.spawns;
+                println!(
+                    "{spawn_1:?} against {spawn_2:?}: {} tiles apart, imbalance {:.2}{}",
+                    pair.path_distance.unwrap_or_default(),
+                    pair.imbalance,
+                    if pair.is_balanced() { "" } else { " (unbalanced)" }
+                );
+            }
+
+            if fairness.is_asymmetric() {
+                return Err(format!("{file} is asymmetric, -spawns balanced only picks balanced spawn points"));
+            }
+            println!("\n{file} is fair");
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! Reference bots, ported from the Python AIs in `ai/testing`.
+
+use std::f32::consts::PI;
+
+use bevy::math::Vec2;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    engine::tank::{instruction::Action, vision::{VisionHit, HULL_RAY_MAX_DIST, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY}},
+    player::observation::PlayerData
+};
+
+use super::TankBrain;
+
+type Data = PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY>;
+
+const STUCK_LIMIT: u32 = 30;
+
+/// Stays in place and spins its turret (`ai/testing/level_1`).
+pub struct Idle;
+
+impl TankBrain for Idle {
+    fn brain(&mut self, _data: &Data) -> Action {
+        Action::SpinLeft
+    }
+}
+
+/// Picks a uniformly random action every tick.
+pub struct RandomBot(StdRng);
+
+impl Default for RandomBot {
+    fn default() -> Self {
+        RandomBot(StdRng::from_entropy())
+    }
+}
+
+impl RandomBot {
+    pub fn seeded(seed: u64) -> Self {
+        RandomBot(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl TankBrain for RandomBot {
+    fn brain(&mut self, _data: &Data) -> Action {
+        const ACTIONS: [Action; 8] = [
+            Action::Shoot,
+            Action::MoveForward,
+            Action::MoveBackward,
+            Action::RotateLeft,
+            Action::RotateRight,
+            Action::SpinLeft,
+            Action::SpinRight,
+            Action::Wait
+        ];
+
+        ACTIONS[self.0.gen_range(0..ACTIONS.len())]
+    }
+}
+
+/// Stays in place, tracks the enemy with its turret and shoots when it's centred (`ai/testing/level_2`).
+pub struct AimAndShoot;
+
+impl TankBrain for AimAndShoot {
+    fn brain(&mut self, data: &Data) -> Action {
+        aim(data)
+    }
+}
+
+/// Drives forward and turns away from walls (`ai/testing/level_3`).
+#[derive(Default)]
+pub struct WallFollower {
+    turning: Option<Turn>,
+    counting: u32,
+    old_pos: Vec2,
+    old_rot: f32,
+}
+
+impl TankBrain for WallFollower {
+    fn brain(&mut self, data: &Data) -> Action {
+        self.drive(data)
+    }
+}
+
+/// Alternates between `AimAndShoot` and `WallFollower` every tick (`ai/testing/level_4`).
+#[derive(Default)]
+pub struct Hunter {
+    driver: WallFollower,
+    turret_turn: bool,
+}
+
+impl TankBrain for Hunter {
+    fn brain(&mut self, data: &Data) -> Action {
+        self.turret_turn = !self.turret_turn;
+
+        match self.turret_turn {
+            true => aim(data),
+            false => self.driver.drive(data),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Left,
+    Right
+}
+
+impl Turn {
+    fn flip(self) -> Self {
+        match self {
+            Turn::Left => Turn::Right,
+            Turn::Right => Turn::Left,
+        }
+    }
+
+    /// The action turning the tank this way on screen, `RotateLeft` turns clockwise (see `key_board::KeyLayout`).
+    fn action(self) -> Action {
+        match self {
+            Turn::Left => Action::RotateRight,
+            Turn::Right => Action::RotateLeft,
+        }
+    }
+}
+
+fn is_enemy(hit: &Option<VisionHit>) -> bool {
+    matches!(hit, Some(VisionHit::Enemy(_)))
+}
+
+/// The distance reported by a sensor, or `-1` if it didn't hit anything.
+fn sensor_dist(hit: &Option<VisionHit>) -> f32 {
+    hit.map_or(-1., |hit| hit.dist())
+}
+
+/// Whether a sensor sees something the tank can't drive through, water only slows it down and pickups are driven over.
+fn is_blocked(hit: &Option<VisionHit>, min_dist: f32) -> bool {
+    hit.is_some_and(|hit| !matches!(hit, VisionHit::Water(_) | VisionHit::Pickup(_))) && sensor_dist(hit) < min_dist
+}
+
+fn angle_distance(angle_1: f32, angle_2: f32) -> f32 {
+    let diff = (angle_1.rem_euclid(2. * PI) - angle_2.rem_euclid(2. * PI)).abs();
+
+    diff.min(2. * PI - diff)
+}
+
+fn aim(data: &Data) -> Action {
+    let center = NUM_OF_TURRET_RAY / 2;
+    let vision = &data.turret_vision;
+
+    if is_enemy(&vision[center]) {
+        Action::Shoot
+    } else if vision[..center].iter().any(is_enemy) {
+        Action::SpinLeft
+    } else if vision[center + 1..].iter().any(is_enemy) {
+        Action::SpinRight
+    } else {
+        Action::SpinLeft
+    }
+}
+
+impl WallFollower {
+    fn pick_turn(&self, data: &Data) -> Turn {
+        let [_n, nw, w, _sw, _s, _se, e, ne] = &data.hull_vision;
+        // a side where the rays hit nothing is the most open one
+        let free_dist = |hit: &Option<VisionHit>| hit.map_or(HULL_RAY_MAX_DIST, |hit| hit.dist());
+
+        let left_dist = free_dist(w).max(free_dist(nw));
+        let right_dist = free_dist(e).max(free_dist(ne));
+
+        match left_dist < right_dist {
+            true => Turn::Right,
+            false => Turn::Left,
+        }
+    }
+
+    fn drive(&mut self, data: &Data) -> Action {
+        let [n, nw, _w, _sw, _s, _se, _e, ne] = &data.hull_vision;
+
+        match self.turning {
+            None => {
+                let forward_blocked = is_blocked(n, 45.) || is_blocked(ne, 40.) || is_blocked(nw, 40.);
+
+                if forward_blocked {
+                    self.turning = Some(self.pick_turn(data));
+                    self.old_pos = data.pos;
+
+                    return Action::MoveBackward;
+                }
+
+                if self.old_pos.distance_squared(data.pos) <= 0.000001 {
+                    self.counting = 1 + self.counting % STUCK_LIMIT;
+
+                    if self.counting == STUCK_LIMIT - 1 {
+                        self.turning = Some(self.pick_turn(data));
+                        self.old_pos = data.pos;
+
+                        return Action::MoveBackward;
+                    }
+                } else {
+                    self.counting = 0;
+                }
+
+                self.old_pos = data.pos;
+                Action::MoveForward
+            },
+            Some(turn) => {
+                let forward_blocked = is_blocked(n, 70.) || is_blocked(ne, 40.) || is_blocked(nw, 40.);
+
+                let facing_good_dir = (0..8)
+                    .map(|i| i as f32 * PI / 4.)
+                    .any(|good_rot| angle_distance(good_rot, data.rot) < 0.1);
+
+                if angle_distance(data.rot, self.old_rot) < 0.00001 {
+                    self.counting = 1 + self.counting % STUCK_LIMIT;
+
+                    if self.counting == STUCK_LIMIT - 1 {
+                        self.turning = Some(turn.flip());
+                    }
+
+                    if self.counting == 10 {
+                        self.old_pos = data.pos;
+
+                        return Action::MoveBackward;
+                    }
+                }
+
+                if facing_good_dir && !forward_blocked {
+                    self.turning = None;
+                    self.old_pos = data.pos;
+
+                    return Action::MoveForward;
+                }
+
+                self.old_rot = data.rot;
+                self.turning.unwrap_or(turn).action()
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! This module provides in-process AI players. A `TankBrain` receives the
+//! same sensor data as the `/brain` route every tick and answers with an
+//! `Action`, without needing an external server.
+
+use bevy::prelude::{EventWriter, GlobalTransform, Query, ResMut, Resource, With};
+
+use crate::engine::tank::{gen::Turret, instruction::{Action, Instruction}, vision::{NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY}};
+
+use super::{observation::{PlayerData, TankSensors}, PlayerID};
+
+pub mod bots;
+
+/// The names accepted by `PlayerController::Builtin`.
+pub const BUILTIN_BOTS: [&str; 5] = ["idle", "random", "aim_and_shoot", "wall_follower", "hunter"];
+
+/// An AI that runs inside the engine.
+pub trait TankBrain: Send + Sync {
+    /// Called once before the first tick of a game.
+    fn start_game(&mut self) {}
+
+    /// Picks the next action from the tank's sensor data.
+    fn brain(&mut self, data: &PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY>) -> Action;
+}
+
+/// Creates one of the reference bots by name.
+///
+/// The `level_1` to `level_4` aliases match the Python bots in `ai/testing`.
+///
+/// # Returns
+/// `None` if no bot has the given name.
+pub fn from_name(name: &str) -> Option<Box<dyn TankBrain>> {
+    match name {
+        "idle" | "level_1" => Some(Box::new(bots::Idle)),
+        "random" => Some(Box::new(bots::RandomBot::default())),
+        "aim_and_shoot" | "level_2" => Some(Box::new(bots::AimAndShoot)),
+        "wall_follower" | "level_3" => Some(Box::new(bots::WallFollower::default())),
+        "hunter" | "level_4" => Some(Box::new(bots::Hunter::default())),
+        _ => None
+    }
+}
+
+/// The brain controlling a player.
+#[derive(Resource)]
+pub struct BuiltinBrain<const P_FLAG: u32>{
+    brain: Box<dyn TankBrain>,
+    started: bool
+}
+
+impl<const P_FLAG: u32> BuiltinBrain<P_FLAG> {
+    pub fn new(brain: Box<dyn TankBrain>) -> Self {
+        Self {
+            brain,
+            started: false
+        }
+    }
+}
+
+/// Runs the player's brain on its tank's sensor data and sends the resulting instruction.
+pub fn builtin_input<const P_FLAG: u32>(
+    mut brain: ResMut<BuiltinBrain<P_FLAG>>,
+
+    tank_query: Query<TankSensors, With<PlayerID<P_FLAG>>>,
+    turret_query: Query<&GlobalTransform, With<Turret>>,
+
+    mut event_writer: EventWriter<Instruction<P_FLAG>>
+) {
+    let Ok((transform, tank, tank_vision, turret_vision)) = tank_query.get_single() else {
+        return;
+    };
+    let turret_transform = turret_query.get(tank.turret).expect("Tank lost ref to turret entity");
+
+    let brain = brain.as_mut();
+    if !brain.started {
+        brain.brain.start_game();
+        brain.started = true;
+    }
+
+    let data = PlayerData::new(
+        transform,
+        turret_transform,
+        turret_vision.rays,
+        tank_vision.rays
+    );
+
+    if let Some(inst) = brain.brain.brain(&data).instruction() {
+        event_writer.send(inst);
+    }
+}
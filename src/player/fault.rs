@@ -0,0 +1,182 @@
+//! This module decides what happens when an AI misbehaves: how often connecting
+//! is retried, how long a request may take and how many failed requests in a row
+//! are tolerated before the player forfeits.
+//!
+//! The connection threads raise a `ForfeitFlag`, the game then removes the
+//! player's tank so the match ends through the usual win condition, and sends
+//! `TankForfeited` so the result can say why.
+
+use std::{fmt, io, sync::{Arc, RwLock}, thread, time::Duration};
+
+use bevy::{log::warn, prelude::{Commands, Entity, EventWriter, Query, Res, Resource, With}};
+use serde::Serialize;
+
+use crate::engine::tank::{gen::Tank, TankForfeited};
+
+use super::PlayerID;
+
+/// How many times connecting to an AI is retried by default.
+pub const DEFAULT_CONNECT_RETRIES: u32 = 3;
+
+/// How long to wait between two connection attempts by default.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// How long a `net:` player waits for the remote player to join by default.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many failed requests in a row make a player forfeit by default.
+pub const DEFAULT_MAX_FAILURES: u32 = 10;
+
+/// How the engine deals with unreliable AIs.
+///
+/// Insert it before adding `PlayerControllerPlugin` to override the defaults.
+///
+/// # Fields
+/// - `connect_retries`: How many times connecting is retried before giving up.
+/// - `retry_delay`: How long to wait between two connection attempts.
+/// - `connect_timeout`: How long to wait for a remote player to join.
+/// - `timeout`: How long the AI has to answer a request, `None` uses the transport's default.
+/// - `max_failures`: How many failed requests in a row make the player forfeit.
+/// - `strict`: Whether a single invalid answer (e.g. an unknown action) makes the player forfeit.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct ConnectionPolicy {
+    pub connect_retries: u32,
+    pub retry_delay: Duration,
+    pub connect_timeout: Duration,
+    pub timeout: Option<Duration>,
+    pub max_failures: u32,
+    pub strict: bool,
+}
+
+impl Default for ConnectionPolicy {
+    fn default() -> Self {
+        ConnectionPolicy {
+            connect_retries: DEFAULT_CONNECT_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: None,
+            max_failures: DEFAULT_MAX_FAILURES,
+            strict: false,
+        }
+    }
+}
+
+impl ConnectionPolicy {
+    /// Returns the request timeout, or `default` if none has been configured.
+    pub fn timeout_or(&self, default: Duration) -> Duration {
+        self.timeout.unwrap_or(default)
+    }
+
+    /// Calls `connect` until it succeeds or the retries run out.
+    ///
+    /// # Returns
+    /// The last error if every attempt failed.
+    pub fn retry<T>(&self, mut connect: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            match connect() {
+                Ok(val) => return Ok(val),
+                Err(err) if attempt < self.connect_retries => {
+                    attempt += 1;
+                    warn!("Failed to reach AI ({err}), retrying ({attempt}/{})", self.connect_retries);
+                    thread::sleep(self.retry_delay);
+                },
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Why an AI stopped playing.
+///
+/// # Variants
+/// - `Timeout`: The AI kept answering too late.
+/// - `InvalidResponse`: The AI kept sending answers that aren't valid actions.
+/// - `Disconnected`: The AI, or the remote player, can't be reached anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Fault {
+    Timeout,
+    InvalidResponse,
+    Disconnected
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Timeout => write!(f, "kept timing out"),
+            Fault::InvalidResponse => write!(f, "kept sending invalid responses"),
+            Fault::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+/// Counts failed requests in a row and raises the `ForfeitFlag` once there are too many.
+#[derive(Debug, Clone)]
+pub struct FaultCounter {
+    failures: u32,
+    max_failures: u32,
+    strict: bool,
+    forfeit: ForfeitFlag,
+}
+
+impl FaultCounter {
+    pub fn new(policy: &ConnectionPolicy, forfeit: ForfeitFlag) -> Self {
+        FaultCounter { failures: 0, max_failures: policy.max_failures, strict: policy.strict, forfeit }
+    }
+
+    /// Records a failed request.
+    pub fn fail(&mut self, fault: Fault) {
+        self.failures += 1;
+
+        if self.failures >= self.max_failures || (self.strict && fault == Fault::InvalidResponse) {
+            self.forfeit.raise(fault);
+        }
+    }
+
+    /// Records a successful request.
+    pub fn succeed(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// Raised by a connection thread when its player forfeits, the first fault is kept.
+#[derive(Debug, Clone, Default)]
+pub struct ForfeitFlag(Arc<RwLock<Option<Fault>>>);
+
+impl ForfeitFlag {
+    pub fn raise(&self, fault: Fault) {
+        if let Ok(mut flag) = self.0.write() {
+            flag.get_or_insert(fault);
+        }
+    }
+
+    pub fn get(&self) -> Option<Fault> {
+        self.0.read().ok().and_then(|flag| *flag)
+    }
+}
+
+/// The `ForfeitFlag` of a player controlled by an AI.
+#[derive(Resource)]
+pub struct PlayerForfeit<const P_FLAG: u32>(pub ForfeitFlag);
+
+/// Removes the tank of a player that forfeited and reports why with `TankForfeited`.
+pub fn forfeit_player<const P_FLAG: u32>(
+    mut commands: Commands,
+    forfeit: Res<PlayerForfeit<P_FLAG>>,
+
+    tank_query: Query<(Entity, &Tank), With<PlayerID<P_FLAG>>>,
+    mut tank_forfeited: EventWriter<TankForfeited>,
+) {
+    let Some(fault) = forfeit.0.get() else {
+        return;
+    };
+
+    for (entity, tank) in &tank_query {
+        warn!("Player {P_FLAG} forfeits the game, its controller {fault}");
+        tank_forfeited.send(TankForfeited { player: P_FLAG, reason: fault.to_string() });
+
+        commands.entity(tank.turret).despawn();
+        commands.entity(entity).despawn();
+    }
+}
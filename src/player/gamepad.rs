@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    input::{gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType}, Axis, ButtonInput},
+    math::Vec2,
+    prelude::{EventWriter, GlobalTransform, Local, Query, Res, Resource, With}
+};
+
+use crate::engine::{game_time::DeltaTime, tank::{gen::{Tank, Turret}, instruction::{Action, Instruction}}};
+
+use super::{key_board::{aim_action, send_delayed}, network::InputDelay, PlayerID};
+
+/// How far a stick must be pushed before it is taken into account, from `0` to `1`.
+pub const GAMEPAD_DEAD_ZONE: f32 = 0.3;
+
+/// The gamepad controlling a player.
+#[derive(Resource)]
+pub struct PlayerGamepad<const P_FLAG: u32>(pub Gamepad);
+
+/// Sends the instructions of the gamepad, `InputDelay` ticks later if there is one.
+///
+/// - The left stick drives the tank, up and down to move, left and right to rotate.
+/// - The turret turns towards the direction of the right stick.
+/// - The right trigger shoots.
+///
+/// Nothing is sent while the gamepad is disconnected.
+#[allow(clippy::too_many_arguments)]
+pub fn gamepad_input<const P_FLAG: u32>(
+    player_gamepad: Res<PlayerGamepad<P_FLAG>>,
+    axes: Res<Axis<GamepadAxis>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    time: Res<DeltaTime>,
+
+    tank_query: Query<&Tank, With<PlayerID<P_FLAG>>>,
+    turret_query: Query<&GlobalTransform, With<Turret>>,
+
+    input_delay: Option<Res<InputDelay>>,
+    mut delayed: Local<VecDeque<Vec<Action>>>,
+    mut event_writer: EventWriter<Instruction<P_FLAG>>
+){
+    let gamepad = player_gamepad.0;
+    let stick = |x, y| Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, x)).unwrap_or_default(),
+        axes.get(GamepadAxis::new(gamepad, y)).unwrap_or_default()
+    );
+
+    let left_stick = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+    let right_stick = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+    let mut actions = Vec::new();
+
+    if left_stick.y > GAMEPAD_DEAD_ZONE {
+        actions.push(Action::MoveForward);
+    }
+    else if left_stick.y < -GAMEPAD_DEAD_ZONE {
+        actions.push(Action::MoveBackward);
+    }
+
+    // `RotateLeft` turns clockwise, like `D` in `wasd`
+    if left_stick.x > GAMEPAD_DEAD_ZONE {
+        actions.push(Action::RotateLeft);
+    }
+    else if left_stick.x < -GAMEPAD_DEAD_ZONE {
+        actions.push(Action::RotateRight);
+    }
+
+    let turret = tank_query.iter()
+        .next()
+        .and_then(|tank| turret_query.get(tank.turret).ok());
+
+    if let (Some(turret), true) = (turret, right_stick.length() > GAMEPAD_DEAD_ZONE) {
+        actions.extend(aim_action(turret, right_stick, time.0));
+    }
+
+    if buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::RightTrigger2)) {
+        actions.push(Action::Shoot);
+    }
+
+    send_delayed(actions, input_delay, &mut delayed, &mut event_writer);
+}
@@ -0,0 +1,182 @@
+//! This module defines the `start_game` handshake shared by every AI controller.
+//! The engine describes the game it is about to start (`GameInfo`) and the AI
+//! answers with what it is and what it supports (`BotInfo`).
+//!
+//! Bots that don't declare a `protocol_version` are assumed to predate the
+//! handshake and are still accepted, bots declaring another version are refused.
+
+use bevy::{asset::Assets, log::warn, prelude::Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{
+    map::{CurrentMap, Map, WALL_SIZE},
+    pickup::{EXTRA_AMMO, FAST_RELOAD_FACTOR, PICKUP_DURATION, SPEED_BOOST_FACTOR},
+    tank::{
+        bullet::BULLET_SPEED,
+        gen::{RELOAD_TIME, TANK_SIZE},
+        instruction::{TANK_MOVE_SPEED, TANK_ROTATION_SPEED, TURRET_ROTATION_SPEED},
+        vision::{HULL_RAY_MAX_DIST, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY, TURRET_RAY_MAX_DIST, TURRET_VISION_ANGLE}
+    }
+};
+
+/// The version of the AI protocol spoken by the engine.
+///
+/// Bumped whenever a message changes in a way older bots can't understand.
+///
+/// - `1`: The `start_game` handshake.
+/// - `2`: `VisionHit` gains `DestructibleWall`, `LowWall`, `Water` and `Hole`.
+/// - `3`: `VisionHit` gains `Pickup`, `Rules` describe the power-ups.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// The optional features the engine knows about.
+pub const KNOWN_FEATURES: [&str; 0] = [];
+
+/// The order of the hull rays in `hull_vision`.
+pub const HULL_RAY_ORDER: [&str; NUM_OF_HULL_RAY] = ["n", "nw", "w", "sw", "s", "se", "e", "ne"];
+
+/// Everything sent to an AI in `start_game`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameInfo {
+    pub game_id: String,
+    pub protocol_version: u32,
+    pub rules: Rules,
+    pub sensors: SensorLayout,
+    pub map: MapInfo,
+}
+
+/// The physical rules of the game, distances are in world units and angles in radians.
+///
+/// # Fields
+/// - `pickup_duration`: How long `FastReload`, `SpeedBoost` and `Shield` last.
+/// - `speed_boost_factor`: The share of `tank_move_speed` a boosted tank moves at.
+/// - `fast_reload_factor`: The share of `reload_time` reloading takes with `FastReload`.
+/// - `extra_ammo`: The shots `ExtraAmmo` fires without reloading.
+#[derive(Debug, Clone, Serialize)]
+pub struct Rules {
+    pub tank_size: f32,
+    pub tank_move_speed: f32,
+    pub tank_rotation_speed: f32,
+    pub turret_rotation_speed: f32,
+    pub bullet_speed: f32,
+    pub reload_time: f32,
+    pub pickup_duration: f32,
+    pub speed_boost_factor: f32,
+    pub fast_reload_factor: f32,
+    pub extra_ammo: u32,
+}
+
+/// The layout of the vision rays in `turret_vision` and `hull_vision`.
+///
+/// # Fields
+/// - `turret_rays`: The number of turret rays, ordered from right to left.
+/// - `turret_fov`: The angle covered by the turret rays.
+/// - `turret_range`: How far the turret rays can see.
+/// - `hull_rays`: The direction of each hull ray.
+/// - `hull_range`: How far the hull rays can see.
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorLayout {
+    pub turret_rays: usize,
+    pub turret_fov: f32,
+    pub turret_range: f32,
+    pub hull_rays: Vec<String>,
+    pub hull_range: f32,
+}
+
+/// The size of the map, `width` and `height` are in tiles of `tile_size` world units.
+#[derive(Debug, Clone, Serialize)]
+pub struct MapInfo {
+    pub width: usize,
+    pub height: usize,
+    pub tile_size: f32,
+}
+
+/// The answer of an AI to `start_game`, every field is optional.
+///
+/// # Fields
+/// - `name`: The name of the bot.
+/// - `version`: The version of the bot.
+/// - `protocol_version`: The version of the AI protocol the bot speaks.
+/// - `features`: The optional features the bot supports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotInfo {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub protocol_version: Option<u32>,
+    pub features: Vec<String>,
+}
+
+/// What a player's AI declared during the handshake.
+#[derive(Debug, Clone, Resource)]
+pub struct PlayerInfo<const P_FLAG: u32>(pub BotInfo);
+
+impl GameInfo {
+    /// Describes the game about to start on `map`.
+    pub fn new(game_id: &str, map: &Map) -> Self {
+        GameInfo {
+            game_id: game_id.to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            rules: Rules {
+                tank_size: TANK_SIZE,
+                tank_move_speed: TANK_MOVE_SPEED,
+                tank_rotation_speed: TANK_ROTATION_SPEED,
+                turret_rotation_speed: TURRET_ROTATION_SPEED,
+                bullet_speed: BULLET_SPEED,
+                reload_time: RELOAD_TIME,
+                pickup_duration: PICKUP_DURATION,
+                speed_boost_factor: SPEED_BOOST_FACTOR,
+                fast_reload_factor: FAST_RELOAD_FACTOR,
+                extra_ammo: EXTRA_AMMO,
+            },
+            sensors: SensorLayout {
+                turret_rays: NUM_OF_TURRET_RAY,
+                turret_fov: TURRET_VISION_ANGLE,
+                turret_range: TURRET_RAY_MAX_DIST,
+                hull_rays: HULL_RAY_ORDER.map(String::from).to_vec(),
+                hull_range: HULL_RAY_MAX_DIST,
+            },
+            map: MapInfo {
+                width: map.dim.0,
+                height: map.dim.1,
+                tile_size: WALL_SIZE,
+            }
+        }
+    }
+
+    /// Describes the game about to start on the map that has just been generated.
+    ///
+    /// # Panics
+    /// Panics if the map hasn't been loaded.
+    pub fn from_current_map(game_id: &str, current_map: &CurrentMap, maps: &Assets<Map>) -> Self {
+        let map = match current_map {
+            CurrentMap::Handle(handle) => maps.get(handle),
+            _ => None
+        }.expect("The map must be loaded before the game starts");
+
+        GameInfo::new(game_id, map)
+    }
+}
+
+impl BotInfo {
+    /// Checks that the bot can play with this engine.
+    ///
+    /// # Returns
+    /// A description of the problem if the bot speaks another protocol version.
+    pub fn check<const P_FLAG: u32>(&self) -> Result<(), String> {
+        let name = self.name.as_deref().unwrap_or("unnamed bot");
+
+        match self.protocol_version {
+            Some(PROTOCOL_VERSION) => {},
+            Some(version) => return Err(format!(
+                "Player {P_FLAG} ({name}) speaks protocol version {version}, but the engine speaks version {PROTOCOL_VERSION}"
+            )),
+            None => warn!("Player {P_FLAG} ({name}) didn't declare a protocol version, assuming it is compatible"),
+        }
+
+        for feature in self.features.iter().filter(|feature| !KNOWN_FEATURES.contains(&feature.as_str())) {
+            warn!("Player {P_FLAG} ({name}) declared an unknown feature ({feature}), it will be ignored");
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,14 @@
-use bevy::{input::ButtonInput, prelude::{EventWriter, KeyCode, Res, Resource}};
+use std::{collections::VecDeque, f32::consts::PI, fs, path::Path};
 
-use crate::engine::tank::instruction::Instruction;
+use bevy::{input::ButtonInput, math::Vec2, prelude::{EventWriter, GlobalTransform, KeyCode, Local, Res, Resource}, reflect::{DynamicEnum, DynamicVariant, Enum, FromReflect}};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::PlayerController;
+use crate::engine::tank::instruction::{get_rotation_z, Action, Instruction, TURRET_ROTATION_SPEED};
+
+use super::{network::InputDelay, rebind::RESERVED_KEYS, PlayerController};
+
+/// The number of actions bound to a key.
+pub const NUM_OF_BINDINGS: usize = 7;
 
 
 /// Handles keyboard input for player controls and sends instructions based on key presses.
@@ -50,34 +56,253 @@ impl<const P_FLAG: u32> From<&PlayerController<P_FLAG>> for PlayerKeyBind<P_FLAG
     }
 }
 
+impl<const P_FLAG: u32> PlayerKeyBind<P_FLAG> {
+    /// The keys with the names of their actions as shown to players, in the order of `KeyLayout`.
+    pub fn bindings(&self) -> [(&'static str, KeyCode); NUM_OF_BINDINGS] {
+        [
+            ("forward", self.move_forward),
+            ("backward", self.move_backward),
+            ("turn left", self.rotate_right),
+            ("turn right", self.rotate_left),
+            ("turret left", self.spin_turret_right),
+            ("turret right", self.spin_turret_left),
+            ("shoot", self.shoot),
+        ]
+    }
+
+    /// Same as `bindings`, the actions are prefixed with the player (e.g. `player 1 shoot`).
+    pub fn labelled_bindings(&self) -> Vec<(String, KeyCode)> {
+        self.bindings()
+            .into_iter()
+            .map(|(action, key)| (format!("player {} {action}", P_FLAG + 1), key))
+            .collect()
+    }
+
+    /// The key at the given index of `bindings`.
+    ///
+    /// # Panics
+    /// Panics if `index` is `NUM_OF_BINDINGS` or more.
+    pub fn binding_mut(&mut self, index: usize) -> &mut KeyCode {
+        match index {
+            0 => &mut self.move_forward,
+            1 => &mut self.move_backward,
+            2 => &mut self.rotate_right,
+            3 => &mut self.rotate_left,
+            4 => &mut self.spin_turret_right,
+            5 => &mut self.spin_turret_left,
+            6 => &mut self.shoot,
+            _ => panic!("Invalid binding ({index})")
+        }
+    }
+
+    /// Returns the actions of the keys currently held.
+    pub fn pressed(&self, keys: &ButtonInput<KeyCode>) -> Vec<Action> {
+        let mut actions = Vec::new();
+
+        if keys.pressed(self.move_forward) {
+            actions.push(Action::MoveForward);
+        }
+        else if keys.pressed(self.move_backward) {
+            actions.push(Action::MoveBackward);
+        }
+
+        if keys.pressed(self.rotate_left) {
+            actions.push(Action::RotateLeft);
+        }
+        else if keys.pressed(self.rotate_right) {
+            actions.push(Action::RotateRight);
+        }
+
+        if keys.pressed(self.spin_turret_left) {
+            actions.push(Action::SpinLeft);
+        }
+        else if keys.pressed(self.spin_turret_right) {
+            actions.push(Action::SpinRight);
+        }
+
+        if keys.pressed(self.shoot) {
+            actions.push(Action::Shoot);
+        }
+
+        actions
+    }
+}
+
+/// Sends the instructions of the keys held, `InputDelay` ticks later if there is one.
 pub fn keyboard_input<const P_FLAG: u32>(
     player_keybinding: Res<PlayerKeyBind<P_FLAG>>,
     keys: Res<ButtonInput<KeyCode>>,
+    input_delay: Option<Res<InputDelay>>,
+    mut delayed: Local<VecDeque<Vec<Action>>>,
     mut event_writer: EventWriter<Instruction<P_FLAG>>
 ){
-    if keys.pressed(player_keybinding.move_forward) {
-        event_writer.send(Instruction::MoveForward);
+    send_delayed(player_keybinding.pressed(&keys), input_delay, &mut delayed, &mut event_writer);
+}
+
+/// Queues the actions of a local player and sends the ones queued `InputDelay` ticks ago.
+///
+/// # Parameters
+/// - `actions`: The actions taken this tick.
+/// - `input_delay`: The delay, the actions are sent immediately without it.
+/// - `delayed`: The actions waiting to be sent.
+/// - `event_writer`: Event writer for sending the instructions.
+pub fn send_delayed<const P_FLAG: u32>(
+    actions: Vec<Action>,
+    input_delay: Option<Res<InputDelay>>,
+    delayed: &mut VecDeque<Vec<Action>>,
+    event_writer: &mut EventWriter<Instruction<P_FLAG>>
+) {
+    let delay = input_delay.map(|delay| delay.0).unwrap_or_default();
+
+    delayed.push_back(actions);
+    if delayed.len() <= delay as usize {
+        return;
     }
-    else if keys.pressed(player_keybinding.move_backward) {
-        event_writer.send(Instruction::MoveBackward);
+
+    for action in delayed.pop_front().unwrap_or_default() {
+        if let Some(inst) = action.instruction::<P_FLAG>() {
+            event_writer.send(inst);
+        }
     }
+}
 
-    if keys.pressed(player_keybinding.rotate_left) {
-        event_writer.send(Instruction::RotateLeft);
+/// The spin turning a turret towards a direction, for controllers aiming at a point.
+///
+/// # Parameters
+/// - `turret`: The turret's transform.
+/// - `dir`: The direction to aim at, in world space.
+/// - `delta_time`: The duration of a tick.
+///
+/// # Returns
+/// `None` if the turret is already aimed, within half a step it would only overshoot.
+pub fn aim_action(turret: &GlobalTransform, dir: Vec2, delta_time: f32) -> Option<Action> {
+    let turret_rot = get_rotation_z(turret.up().truncate());
+    let diff = (get_rotation_z(dir) - turret_rot + PI).rem_euclid(2. * PI) - PI;
+
+    if diff.abs() <= TURRET_ROTATION_SPEED * delta_time / 2. {
+        return None;
     }
-    else if keys.pressed(player_keybinding.rotate_right) {
-        event_writer.send(Instruction::RotateRight);
+
+    match diff > 0. {
+        true => Some(Action::SpinRight),
+        false => Some(Action::SpinLeft),
     }
+}
 
-    if keys.pressed(player_keybinding.spin_turret_left) {
-        event_writer.send(Instruction::SpinTurretLeft);
+/// A keyboard layout loaded with `-p1 keys:<file>.ron`.
+///
+/// Keys are named after `KeyCode` (e.g. `"KeyW"`, `"ArrowUp"`, `"Space"`) and the
+/// directions are the ones seen on screen, `turn_left` turns the tank counter-clockwise
+/// which is the `rotate_right` action of the AI API.
+///
+/// # Fields
+/// - `forward`: Moves the tank forward.
+/// - `backward`: Moves the tank backward.
+/// - `turn_left`: Turns the tank counter-clockwise.
+/// - `turn_right`: Turns the tank clockwise.
+/// - `turret_left`: Spins the turret counter-clockwise.
+/// - `turret_right`: Spins the turret clockwise.
+/// - `shoot`: Fires the turret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyLayout {
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub forward: KeyCode,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub backward: KeyCode,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub turn_left: KeyCode,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub turn_right: KeyCode,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub turret_left: KeyCode,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub turret_right: KeyCode,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    pub shoot: KeyCode,
+}
+
+impl KeyLayout {
+    /// Reads a layout from a RON file, layouts binding a key twice or a key of the rebinding screen are refused.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let layout = fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read {} ({err})", path.display()))?;
+        let layout: KeyLayout = ron::from_str(&layout)
+            .map_err(|err| format!("Invalid key layout {} ({err})", path.display()))?;
+
+        let bindings: Vec<(String, KeyCode)> = PlayerKeyBind::<0>::from(&PlayerController::from(layout))
+            .bindings()
+            .into_iter()
+            .map(|(action, key)| (action.to_string(), key))
+            .collect();
+
+        match key_conflicts(&bindings).into_iter().next() {
+            Some(conflict) => Err(format!("Invalid key layout {} ({conflict})", path.display())),
+            None => Ok(layout),
+        }
     }
-    else if keys.pressed(player_keybinding.spin_turret_right) {
-        event_writer.send(Instruction::SpinTurretRight);
+}
+
+impl<const P_FLAG: u32> From<KeyLayout> for PlayerController<P_FLAG> {
+    fn from(layout: KeyLayout) -> Self {
+        PlayerController::Control {
+            move_forward: layout.forward,
+            move_backward: layout.backward,
+            rotate_left: layout.turn_right,
+            rotate_right: layout.turn_left,
+            spin_turret_left: layout.turret_right,
+            spin_turret_right: layout.turret_left,
+            shoot: layout.shoot,
+        }
     }
+}
 
-    if keys.pressed(player_keybinding.shoot) {
-        event_writer.send(Instruction::Shoot);
+impl<const P_FLAG: u32> From<&PlayerKeyBind<P_FLAG>> for KeyLayout {
+    fn from(keys: &PlayerKeyBind<P_FLAG>) -> Self {
+        KeyLayout {
+            forward: keys.move_forward,
+            backward: keys.move_backward,
+            turn_left: keys.rotate_right,
+            turn_right: keys.rotate_left,
+            turret_left: keys.spin_turret_right,
+            turret_right: keys.spin_turret_left,
+            shoot: keys.shoot,
+        }
     }
+}
+
+/// Finds the keys bound to several actions, or to a key of the rebinding screen.
+///
+/// # Parameters
+/// - `bindings`: The actions of every keyboard player with their keys.
+///
+/// # Returns
+/// A description of every conflict, empty if there is none.
+pub fn key_conflicts(bindings: &[(String, KeyCode)]) -> Vec<String> {
+    let reserved = bindings.iter()
+        .filter(|(_, key)| RESERVED_KEYS.contains(key))
+        .map(|(action, key)| format!("{key:?} is bound to {action} but is used by the rebinding screen"));
+
+    reserved.chain(bindings.iter()
+        .enumerate()
+        .flat_map(|(i, (action, key))| bindings[i + 1..].iter()
+            .filter(move |(_, other_key)| other_key == key)
+            .map(move |(other_action, _)| format!("{key:?} is bound to {action} and {other_action}"))
+        ))
+        .collect()
+}
+
+/// Parses the name of a `KeyCode`, e.g. `"KeyW"`.
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    KeyCode::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+fn serialize_key<S: Serializer>(key: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(key.variant_name())
+}
+
+fn deserialize_key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+    let name = String::deserialize(deserializer)?;
 
+    key_code(&name).ok_or_else(|| serde::de::Error::custom(format!("Unknown key ({name})")))
 }
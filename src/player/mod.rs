@@ -4,14 +4,30 @@
 
 use std::net::IpAddr;
 
-use bevy::{app::{Plugin, Update}, input::InputPlugin, prelude::{in_state, Component, IntoSystemConfigs, KeyCode}};
-use key_board::{keyboard_input, PlayerKeyBind};
-use server::{end_game_msg, server_input, update_player_data, PlayerServer};
+use bevy::{app::{Plugin, PostUpdate, Update}, input::{gamepad::Gamepad, InputPlugin}, prelude::{in_state, not, resource_exists, Component, IntoSystemConfigs, IntoSystemSetConfigs, KeyCode, OnEnter}, ui::UiPlugin};
+use builtin::{builtin_input, BuiltinBrain};
+use fault::{forfeit_player, ConnectionPolicy, ForfeitFlag, PlayerForfeit};
+use telemetry::{report_telemetry, track_sim_time, PlayerTelemetry, Telemetry};
+use key_board::{key_conflicts, keyboard_input, PlayerKeyBind};
+use rebind::{rebind_keys, RebindScreen};
+use gamepad::{gamepad_input, PlayerGamepad};
+use mouse::{mouse_input, MouseAim};
+use network::{network_input, InputDelay, NetworkPlayer, DEFAULT_INPUT_DELAY};
+use remote::{grpc::{GrpcTransport, GRPC_TIMEOUT}, http::HttpTransport, process::{ProcessTransport, PROCESS_TIMEOUT}, remote_input, web_socket::{WebSocketTransport, WEB_SOCKET_TIMEOUT}, RemotePlayer};
 
-use crate::engine::map::gen_state::Step;
+use crate::{engine::{map::gen_state::Step, tank::instruction::ProcessInstructions, Simulation}, spectator::{broadcast::Broadcast, BroadcastPlugin}};
 
-pub mod server;
 pub mod key_board;
+pub mod gamepad;
+pub mod mouse;
+pub mod rebind;
+pub mod observation;
+pub mod handshake;
+pub mod fault;
+pub mod telemetry;
+pub mod builtin;
+pub mod remote;
+pub mod network;
 
 /// Represents a unique identifier for a player.
 #[derive(Component)]
@@ -26,8 +42,8 @@ pub struct PlayerControllerPlugin<const P_FLAG_1: u32, const P_FLAG_2: u32>(
 impl<const P_FLAG_1: u32, const P_FLAG_2: u32> Plugin for PlayerControllerPlugin<P_FLAG_1, P_FLAG_2>{
     fn build(&self, app: &mut bevy::prelude::App) {
         match (&self.0, &self.1, app.is_plugin_added::<InputPlugin>()) {
-            (_, PlayerController::Control{ .. }, false) |
-            (PlayerController::Control { .. }, _, false) => {
+            (_, PlayerController::Control{ .. } | PlayerController::Gamepad { .. }, false) |
+            (PlayerController::Control { .. } | PlayerController::Gamepad { .. }, _, false) => {
                 app.add_plugins(InputPlugin);
             },
             _ => {}
@@ -42,6 +58,33 @@ impl<const P_FLAG_1: u32, const P_FLAG_2: u32> Plugin for PlayerControllerPlugin
             _ => {}
         }
 
+        match app.world().get_resource::<MouseAim>() {
+            Some(MouseAim(player)) if *player == P_FLAG_1 && !matches!(self.0, PlayerController::Control { .. }) => {
+                panic!("Mouse aim needs a keyboard player, player {} is {:?}", player + 1, self.0);
+            },
+            Some(MouseAim(player)) if *player == P_FLAG_2 && !matches!(self.1, PlayerController::Control { .. }) => {
+                panic!("Mouse aim needs a keyboard player, player {} is {:?}", player + 1, self.1);
+            },
+            _ => {}
+        }
+
+        let mut bindings = Vec::new();
+        if let PlayerController::Control { .. } = &self.0 {
+            bindings.extend(PlayerKeyBind::from(&self.0).labelled_bindings());
+        }
+        if let PlayerController::Control { .. } = &self.1 {
+            bindings.extend(PlayerKeyBind::from(&self.1).labelled_bindings());
+        }
+        if let Some(conflict) = key_conflicts(&bindings).first() {
+            panic!("Conflicting key bindings, {conflict}");
+        }
+
+        // the rebinding screen needs text, which is only available when rendering
+        if !bindings.is_empty() && app.is_plugin_added::<UiPlugin>() {
+            app.add_systems(Update, rebind_keys::<P_FLAG_1, P_FLAG_2>)
+                .configure_sets(Update, Simulation.run_if(not(resource_exists::<RebindScreen>)));
+        }
+
         // Copy values over
         app.add_plugins(self.0.clone())
             .add_plugins(self.1.clone());
@@ -65,6 +108,31 @@ pub enum PlayerController<const P_FLAG: u32> {
         spin_turret_left: KeyCode,
         spin_turret_right: KeyCode,
         shoot: KeyCode
+    },
+    /// A gamepad, `id` is the order in which gamepads were connected starting from `0` (see `gamepad`).
+    Gamepad{
+        id: usize,
+    },
+    /// One of the reference bots in `builtin::bots`, selected by name.
+    Builtin(String),
+    /// An AI reached over a persistent WebSocket connection (see `remote`).
+    WebSocket{
+        url: String,
+        game_id: String,
+    },
+    /// A bot started as a child process, talking over stdin and stdout (see `remote::process`).
+    Process{
+        cmd: String,
+        game_id: String,
+    },
+    /// An AI serving the gRPC service in `proto/tank_ai.proto` (see `remote::grpc`).
+    Grpc{
+        url: String,
+        game_id: String,
+    },
+    /// A human playing from another machine with `-join`, `addr` is where the host waits for them (see `network`).
+    Network{
+        addr: String,
     }
 }
 impl<const P_FLAG: u32> PlayerController<P_FLAG> {
@@ -100,20 +168,118 @@ impl<const P_FLAG: u32> Plugin for PlayerController<P_FLAG> {
     fn build(&self, app: &mut bevy::prelude::App) {
         println!("ADDING PLAYER PLUGIN {P_FLAG}");
         match &self {
-            PlayerController::Server { ip, port, game_id  } => { // todo!() replace placeholder with a higher order function that interacts with server
-                app.insert_resource::<PlayerServer<P_FLAG>>(PlayerServer::new(*ip, *port, &game_id))
-                    .add_systems(Update, server_input::<P_FLAG>)
-                    .add_systems(Update, update_player_data::<P_FLAG>)
-                    .add_systems(
-                        Update,
-                        end_game_msg::<P_FLAG>.run_if(in_state(Step::Finished))
-                    );
+            PlayerController::Server { ip, port, game_id  } => {
+                let policy = connection_policy(app);
+                let transport = HttpTransport::new(*ip, *port, &policy)
+                    .unwrap_or_else(|err| panic!("Failed to create the HTTP client for {ip}:{port} - {err}"));
+
+                add_remote_systems(app, RemotePlayer::<P_FLAG>::new(transport, game_id));
             },
             PlayerController::Control { .. } => { // todo!() replace placeholder with a higher order function that creates keyboard_input using key mapping
                 app.insert_resource::<PlayerKeyBind<P_FLAG>>(self.into())
-                    .add_systems(Update, keyboard_input::<P_FLAG>);
+                    .add_systems(
+                        Update,
+                        keyboard_input::<P_FLAG>
+                            .run_if(not(resource_exists::<RebindScreen>))
+                            .before(ProcessInstructions)
+                    );
+
+                if app.world().get_resource::<MouseAim>() == Some(&MouseAim(P_FLAG)) {
+                    app.add_systems(
+                        Update,
+                        mouse_input::<P_FLAG>
+                            .run_if(not(resource_exists::<RebindScreen>))
+                            .before(ProcessInstructions)
+                    );
+                }
                 println!("key board controls");
             },
+            PlayerController::Gamepad { id } => {
+                app.insert_resource(PlayerGamepad::<P_FLAG>(Gamepad::new(*id)))
+                    .add_systems(Update, gamepad_input::<P_FLAG>.before(ProcessInstructions));
+            },
+            PlayerController::Builtin(name) => {
+                let brain = builtin::from_name(name)
+                    .unwrap_or_else(|| panic!("Unknown builtin bot ({name})"));
+
+                app.insert_resource(BuiltinBrain::<P_FLAG>::new(brain))
+                    .add_systems(Update, builtin_input::<P_FLAG>.before(ProcessInstructions));
+            },
+            PlayerController::WebSocket { url, game_id } => {
+                let policy = connection_policy(app);
+                let transport = policy.retry(|| WebSocketTransport::connect(url, policy.timeout_or(WEB_SOCKET_TIMEOUT)))
+                    .unwrap_or_else(|err| panic!("Failed to connect to {url} - {err}"));
+
+                add_remote_systems(app, RemotePlayer::<P_FLAG>::new(transport, game_id));
+            },
+            PlayerController::Process { cmd, game_id } => {
+                let policy = connection_policy(app);
+                let transport = ProcessTransport::spawn(cmd, policy.timeout_or(PROCESS_TIMEOUT))
+                    .unwrap_or_else(|err| panic!("Failed to start {cmd} - {err}"));
+
+                add_remote_systems(app, RemotePlayer::<P_FLAG>::new(transport, game_id));
+            },
+            PlayerController::Grpc { url, game_id } => {
+                let policy = connection_policy(app);
+                let transport = policy.retry(|| GrpcTransport::connect(url, policy.timeout_or(GRPC_TIMEOUT)))
+                    .unwrap_or_else(|err| panic!("Failed to connect to {url} - {err}"));
+
+                add_remote_systems(app, RemotePlayer::<P_FLAG>::new(transport, game_id));
+            },
+            PlayerController::Network { addr } => {
+                if !app.world().contains_resource::<InputDelay>() {
+                    app.insert_resource(InputDelay(DEFAULT_INPUT_DELAY));
+                }
+                // the remote player watches the game through the broadcast
+                if !app.world().contains_resource::<Broadcast>() {
+                    let ip = addr.rsplit_once(':').map(|(ip, _)| ip).unwrap_or(addr);
+
+                    app.add_plugins(BroadcastPlugin(format!("{ip}:0")));
+                }
+
+                let broadcast_port = app.world().resource::<Broadcast>().local_addr().port();
+                let input_delay = app.world().resource::<InputDelay>().0;
+                let connect_timeout = connection_policy(app).connect_timeout;
+                let player = NetworkPlayer::<P_FLAG>::listen(addr.as_str(), broadcast_port, input_delay, connect_timeout)
+                    .unwrap_or_else(|err| panic!("Failed to wait for player {P_FLAG} on {addr} - {err}"));
+
+                app.insert_resource(PlayerForfeit::<P_FLAG>(player.forfeit_flag()))
+                    .insert_resource(player)
+                    .add_systems(Update, network_input::<P_FLAG>.before(ProcessInstructions))
+                    .add_systems(Update, forfeit_player::<P_FLAG>.run_if(in_state(Step::Finished)));
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+/// The `ConnectionPolicy` inserted by the caller, or the default one.
+fn connection_policy(app: &mut bevy::prelude::App) -> ConnectionPolicy {
+    app.init_resource::<ConnectionPolicy>()
+        .world()
+        .resource::<ConnectionPolicy>()
+        .clone()
+}
+
+/// Systems shared by every player controlled by an AI.
+fn add_ai_systems<const P_FLAG: u32>(app: &mut bevy::prelude::App, forfeit: ForfeitFlag, telemetry: Telemetry) {
+    app.init_resource::<ConnectionPolicy>()
+        .insert_resource(PlayerForfeit::<P_FLAG>(forfeit))
+        .insert_resource(PlayerTelemetry::<P_FLAG>::new(telemetry))
+        .add_systems(Update, forfeit_player::<P_FLAG>.run_if(in_state(Step::Finished)))
+        .add_systems(Update, track_sim_time::<P_FLAG>.run_if(in_state(Step::Finished)))
+        .add_systems(PostUpdate, report_telemetry::<P_FLAG>.run_if(in_state(Step::Finished)));
+}
+
+fn add_remote_systems<const P_FLAG: u32>(app: &mut bevy::prelude::App, player: RemotePlayer<P_FLAG>) {
+    add_ai_systems::<P_FLAG>(app, player.forfeit_flag(), player.telemetry());
+
+    app.insert_resource(player)
+        .add_systems(OnEnter(Step::Finished), remote::start_game_msg::<P_FLAG>)
+        .add_systems(Update, remote_input::<P_FLAG>.before(ProcessInstructions))
+        .add_systems(Update, remote::update_player_data::<P_FLAG>)
+        // same schedule as the engine's `end_game`, so the result is sent before the app exits
+        .add_systems(
+            PostUpdate,
+            remote::end_game_msg::<P_FLAG>.run_if(in_state(Step::Finished))
+        );
+}
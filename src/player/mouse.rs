@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    input::{mouse::MouseButton, ButtonInput},
+    prelude::{Camera, EventWriter, GlobalTransform, Local, Query, Res, Resource, With, Without},
+    window::{PrimaryWindow, Window}
+};
+
+use crate::engine::{game_time::DeltaTime, tank::{gen::{Tank, Turret}, instruction::{Action, Instruction}}};
+
+use super::{key_board::{aim_action, send_delayed}, network::InputDelay, PlayerID};
+
+/// The keyboard player whose turret follows the mouse, `0` for `-p1` and `1` for `-p2`.
+///
+/// The keys still drive the tank. Insert it before adding `PlayerControllerPlugin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct MouseAim(pub u32);
+
+/// Turns the turret towards the cursor and shoots on left click, `InputDelay` ticks later if there is one.
+///
+/// The turret spins at `TURRET_ROTATION_SPEED` like with the keys, nothing is sent while
+/// the cursor is outside the window.
+#[allow(clippy::too_many_arguments)]
+pub fn mouse_input<const P_FLAG: u32>(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), Without<Turret>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    time: Res<DeltaTime>,
+
+    tank_query: Query<&Tank, With<PlayerID<P_FLAG>>>,
+    turret_query: Query<&GlobalTransform, With<Turret>>,
+
+    input_delay: Option<Res<InputDelay>>,
+    mut delayed: Local<VecDeque<Vec<Action>>>,
+    mut event_writer: EventWriter<Instruction<P_FLAG>>
+){
+    let mut actions = Vec::new();
+
+    let cursor = window_query.get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .zip(camera_query.get_single().ok())
+        .and_then(|(cursor, (camera, camera_transform))| camera.viewport_to_world_2d(camera_transform, cursor));
+
+    let turret = tank_query.iter()
+        .next()
+        .and_then(|tank| turret_query.get(tank.turret).ok());
+
+    if let (Some(cursor), Some(turret)) = (cursor, turret) {
+        actions.extend(aim_action(turret, cursor - turret.translation().truncate(), time.0));
+
+        if buttons.pressed(MouseButton::Left) {
+            actions.push(Action::Shoot);
+        }
+    }
+
+    send_delayed(actions, input_delay, &mut delayed, &mut event_writer);
+}
@@ -0,0 +1,94 @@
+//! The remote side of a network game, started with `-join`.
+//!
+//! The engine shows the host's broadcast like `-spectate` and sends the keys
+//! held every frame, the game itself only runs on the host.
+
+use std::{io::{self, BufRead, BufReader, ErrorKind, Write}, net::{SocketAddr, TcpStream, ToSocketAddrs}, sync::Mutex};
+
+use bevy::{app::{Plugin, Update}, input::ButtonInput, log::{info, warn}, prelude::{KeyCode, Local, Res, Resource}};
+
+use crate::{
+    player::{fault::ConnectionPolicy, key_board::PlayerKeyBind, PlayerController},
+    spectator::client::SpectatorPlugin
+};
+
+use super::{InputFrame, JoinInfo};
+
+/// The connection to the host.
+#[derive(Resource)]
+pub struct HostConnection(Mutex<TcpStream>);
+
+/// Joins the game hosted at the given address (e.g. `192.168.0.10:6500`).
+///
+/// # Fields
+/// - `0`: The address given to the host's `net:` player.
+/// - `1`: The keys used to play, must be a keyboard controller.
+///
+/// # Panics
+/// Panics if the host can't be reached or if the controller isn't a keyboard, connecting is retried according to the `ConnectionPolicy`.
+pub struct JoinPlugin(pub String, pub PlayerController<0>);
+
+impl Plugin for JoinPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        if !matches!(self.1, PlayerController::Control { .. }) {
+            panic!("Joining a game needs keyboard controls, got {:?}", self.1);
+        }
+
+        let policy = app.world()
+            .get_resource::<ConnectionPolicy>()
+            .cloned()
+            .unwrap_or_default();
+
+        let (stream, host, join_info) = policy.retry(|| join(self.0.as_str()))
+            .unwrap_or_else(|err| panic!("Unable to join {} ({err})", self.0));
+
+        info!("Joined {host} as player {}, inputs are delayed by {} ticks", join_info.player, join_info.input_delay);
+
+        app.insert_resource::<PlayerKeyBind<0>>((&self.1).into())
+            .insert_resource(HostConnection(Mutex::new(stream)))
+            .add_plugins(SpectatorPlugin(SocketAddr::new(host.ip(), join_info.broadcast_port).to_string(), true))
+            .add_systems(Update, send_input);
+    }
+}
+
+/// Connects to a host and reads its greeting.
+fn join(addr: &str) -> io::Result<(TcpStream, SocketAddr, JoinInfo)> {
+    let host = addr.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "No address"))?;
+
+    let stream = TcpStream::connect(host)?;
+    stream.set_nodelay(true)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let join_info = serde_json::from_str(&line)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+    Ok((stream, host, join_info))
+}
+
+/// Sends the keys held during the frame to the host.
+pub fn send_input(
+    host: Res<HostConnection>,
+    player_keybinding: Res<PlayerKeyBind<0>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut disconnected: Local<bool>
+) {
+    if *disconnected {
+        return;
+    }
+
+    let frame = InputFrame { actions: player_keybinding.pressed(&keys) };
+    let line = serde_json::to_string(&frame).expect("Input frames are valid JSON");
+
+    let Ok(mut stream) = host.0.lock() else {
+        return;
+    };
+
+    if let Err(err) = writeln!(stream, "{line}") {
+        warn!("Lost the connection to the host ({err})");
+        *disconnected = true;
+    }
+}
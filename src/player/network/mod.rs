@@ -0,0 +1,177 @@
+//! This module lets a human play from another machine. The host runs the game
+//! as usual with a `net:<IP>:<Port>` player, the remote player joins with
+//! `-join` (see `client`), sends the keys it holds every frame and watches the
+//! game through the host's broadcast (see `spectator`).
+//!
+//! Messages are newline delimited JSON over TCP.
+//! - The host greets the remote player with a `JoinInfo`.
+//! - The remote player then sends an `InputFrame` every frame.
+//!
+//! The host applies the inputs with a delay of `InputDelay` ticks, the frames
+//! received in the meantime absorb the network jitter. Local keyboard players
+//! get the same delay so neither player has an advantage.
+
+use std::{collections::VecDeque, io::{self, BufRead, BufReader, ErrorKind, Write}, net::{TcpListener, ToSocketAddrs}, sync::{mpsc::{self, Receiver}, Mutex}, thread, time::{Duration, Instant}};
+
+use bevy::{log::{info, warn}, prelude::{EventWriter, Res, ResMut, Resource}};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::tank::instruction::{Action, Instruction};
+
+use super::fault::{Fault, ForfeitFlag};
+
+pub mod client;
+
+/// The input delay used when a network player is in the game and none has been configured, in ticks.
+pub const DEFAULT_INPUT_DELAY: u32 = 3;
+
+/// How often the host checks whether the remote player has joined.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How many ticks keyboard inputs wait before being applied.
+///
+/// Without it local keyboard inputs are applied immediately. Insert it before
+/// adding `PlayerControllerPlugin` to override `DEFAULT_INPUT_DELAY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub struct InputDelay(pub u32);
+
+/// Sent by the host once a remote player has connected.
+///
+/// # Fields
+/// - `player`: The id of the player the remote player controls.
+/// - `broadcast_port`: The port of the host's broadcast, to watch the game (see `spectator`).
+/// - `input_delay`: How many ticks the inputs wait before being applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinInfo {
+    pub player: u32,
+    pub broadcast_port: u16,
+    pub input_delay: u32,
+}
+
+/// The keys held by the remote player during a frame.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct InputFrame {
+    pub actions: Vec<Action>,
+}
+
+/// A player controlled from another machine.
+///
+/// # Fields
+/// - `frames`: The frames received from the remote player.
+/// - `buffer`: The frames waiting to be applied.
+/// - `started`: Whether the buffer has filled up once.
+/// - `forfeit`: Raised when the remote player disconnects.
+#[derive(Resource)]
+pub struct NetworkPlayer<const P_FLAG: u32> {
+    frames: Mutex<Receiver<InputFrame>>,
+    buffer: VecDeque<InputFrame>,
+    started: bool,
+    forfeit: ForfeitFlag,
+}
+
+impl<const P_FLAG: u32> NetworkPlayer<P_FLAG> {
+    /// Waits for the remote player to connect and greets it.
+    ///
+    /// # Parameters
+    /// - `addr`: The address to listen on, e.g. `0.0.0.0:6500`.
+    /// - `broadcast_port`: The port of the host's broadcast.
+    /// - `input_delay`: How many ticks the inputs wait before being applied.
+    /// - `connect_timeout`: How long to wait for the remote player.
+    ///
+    /// # Returns
+    /// A `TimedOut` error if nobody joined within `connect_timeout`.
+    pub fn listen(addr: impl ToSocketAddrs, broadcast_port: u16, input_delay: u32, connect_timeout: Duration) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        info!("Waiting for player {P_FLAG} to join on {}", listener.local_addr()?);
+        let deadline = Instant::now() + connect_timeout;
+        let (mut stream, remote) = loop {
+            match listener.accept() {
+                Ok(connection) => break connection,
+                Err(err) if err.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => thread::sleep(ACCEPT_INTERVAL),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    return Err(io::Error::new(ErrorKind::TimedOut, format!("nobody joined within {connect_timeout:?}")));
+                },
+                Err(err) => return Err(err),
+            }
+        };
+        info!("Player {P_FLAG} joined from {remote}");
+
+        // the stream may inherit the listener's non-blocking mode
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        let join_info = JoinInfo { player: P_FLAG, broadcast_port, input_delay };
+        writeln!(stream, "{}", serde_json::to_string(&join_info)?)?;
+
+        let forfeit = ForfeitFlag::default();
+        let (send_frame, frames) = mpsc::channel();
+        {
+            let forfeit = forfeit.clone();
+
+            thread::spawn(move || {
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+
+                    match serde_json::from_str(&line) {
+                        Ok(frame) => {
+                            if send_frame.send(frame).is_err() {
+                                return;
+                            }
+                        },
+                        Err(err) => warn!("Invalid input from player {P_FLAG} ({err})"),
+                    }
+                }
+
+                forfeit.raise(Fault::Disconnected);
+            });
+        }
+
+        Ok(NetworkPlayer {
+            frames: Mutex::new(frames),
+            buffer: VecDeque::new(),
+            started: false,
+            forfeit,
+        })
+    }
+
+    pub fn forfeit_flag(&self) -> ForfeitFlag {
+        self.forfeit.clone()
+    }
+}
+
+/// Applies one frame of the remote player's inputs per tick, `InputDelay` ticks after it was received.
+///
+/// Frames piling up beyond twice the delay (e.g. the remote player runs at a higher frame rate) are dropped,
+/// so the delay doesn't grow over the game.
+pub fn network_input<const P_FLAG: u32>(
+    mut player: ResMut<NetworkPlayer<P_FLAG>>,
+    input_delay: Res<InputDelay>,
+    mut event_writer: EventWriter<Instruction<P_FLAG>>
+) {
+    let player = player.as_mut();
+    let delay = input_delay.0 as usize;
+
+    if let Ok(frames) = player.frames.get_mut() {
+        player.buffer.extend(frames.try_iter());
+    }
+
+    if !player.started {
+        if player.buffer.len() <= delay {
+            return;
+        }
+        player.started = true;
+    }
+
+    while player.buffer.len() > 2 * delay + 1 {
+        player.buffer.pop_front();
+    }
+
+    for action in player.buffer.pop_front().unwrap_or_default().actions {
+        if let Some(inst) = action.instruction::<P_FLAG>() {
+            event_writer.send(inst);
+        }
+    }
+}
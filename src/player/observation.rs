@@ -0,0 +1,134 @@
+//! This module defines the sensor data handed to AI players each tick.
+//! `PlayerData` is the in-engine snapshot of a tank, while
+//! `PlayerDataSerialized` is the JSON payload sent over the wire.
+
+use bevy::{math::Vec2, prelude::GlobalTransform};
+use serde::Serialize;
+
+use crate::engine::tank::{gen::{Tank, Turret}, instruction::get_rotation_z, vision::{VisionHit, VisionRay, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY}};
+
+/// The tank components needed to build a `PlayerData`, for use in queries.
+pub type TankSensors = (
+    &'static GlobalTransform,
+    &'static Tank,
+    &'static VisionRay<NUM_OF_HULL_RAY, Tank>,
+    &'static VisionRay<NUM_OF_TURRET_RAY, Turret>
+);
+
+/// A snapshot of everything a player is allowed to know about its own tank.
+///
+/// # Fields
+/// - `pos`: The tank's position in the game world.
+/// - `rot`: The rotation of the tank's hull in radians.
+/// - `turret_rot`: The rotation of the turret in radians.
+/// - `turret_vision`: The hits seen by the turret rays.
+/// - `hull_vision`: The hits seen by the hull rays.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerData<const TURRET_RAYS: usize, const HULL_RAYS: usize> {
+    pub pos: Vec2,
+    pub rot: f32,
+    pub turret_rot: f32,
+    pub turret_vision: [Option<VisionHit>; TURRET_RAYS],
+    pub hull_vision: [Option<VisionHit>; HULL_RAYS],
+}
+
+impl<const TURRET_RAYS: usize, const HULL_RAYS: usize> Default for PlayerData<TURRET_RAYS, HULL_RAYS> {
+    fn default() -> Self {
+        Self {
+            pos: Default::default(),
+            rot: Default::default(),
+            turret_rot: Default::default(),
+            turret_vision: [None; TURRET_RAYS],
+            hull_vision: [None; HULL_RAYS]
+        }
+    }
+}
+
+impl<const TURRET_RAYS: usize, const HULL_RAYS: usize> PlayerData<TURRET_RAYS, HULL_RAYS> {
+    /// Builds a snapshot from the components of a tank and its turret.
+    ///
+    /// # Parameters
+    /// - `transform`: The global transform of the tank's hull.
+    /// - `turret_transform`: The global transform of the tank's turret.
+    /// - `turret_vision`: The latest turret ray hits.
+    /// - `hull_vision`: The latest hull ray hits.
+    pub fn new(
+        transform: &GlobalTransform,
+        turret_transform: &GlobalTransform,
+        turret_vision: [Option<VisionHit>; TURRET_RAYS],
+        hull_vision: [Option<VisionHit>; HULL_RAYS],
+    ) -> Self {
+        PlayerData{
+            pos: {
+                let pos = transform.translation();
+
+                Vec2::new(pos.x, pos.y)
+            },
+            rot: get_rotation_z({
+                let up = transform.up();
+
+                Vec2::new(up.x, up.y)
+            }),
+            turret_rot: get_rotation_z({
+                let dir = turret_transform.compute_transform().up().as_vec3();
+
+                Vec2::new(dir.x, dir.y)
+            }),
+            turret_vision,
+            hull_vision,
+        }
+    }
+}
+
+/// The JSON body posted to an AI's `/brain` route.
+///
+/// `errors` describes what was wrong with the AI's answers since the previous
+/// observation (e.g. an unknown action), it is empty when they were valid.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerDataSerialized {
+    pub game_id: String,
+    pub pos: Vec2,
+    pub rot: f32,
+    pub turret_rot: f32,
+    pub turret_vision: Vec<Option<VisionHit>>,
+    pub hull_vision: Vec<Option<VisionHit>>,
+    pub errors: Vec<String>,
+}
+
+impl PlayerDataSerialized {
+    pub fn new(game_id: &str) -> Self {
+        let player_data: PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY> = PlayerData::default();
+
+        PlayerDataSerialized{
+            game_id: game_id.to_string(),
+            pos: player_data.pos,
+            rot: player_data.rot,
+            turret_rot: player_data.turret_rot,
+            turret_vision: player_data.turret_vision.into(),
+            hull_vision: player_data.hull_vision.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, player_data: &PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY>) {
+        self.pos = player_data.pos;
+        self.rot = player_data.rot;
+        self.turret_rot = player_data.turret_rot;
+        self.turret_vision = player_data.turret_vision.into();
+        self.hull_vision = player_data.hull_vision.into();
+    }
+}
+
+impl<const T: usize, const H: usize> From<(String, PlayerData<T, H>)> for PlayerDataSerialized{
+    fn from((game_id, player_data): (String, PlayerData<T, H>)) -> Self {
+        PlayerDataSerialized{
+            game_id,
+            pos: player_data.pos,
+            rot: player_data.rot,
+            turret_rot: player_data.turret_rot,
+            turret_vision: player_data.turret_vision.into(),
+            hull_vision: player_data.hull_vision.into(),
+            errors: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! An in-game screen to change the keys of the keyboard players, opened with `F1` in rendered games.
+//!
+//! The keys of each keyboard player are asked in turn, keys already bound are refused.
+//! The match is paused while the screen is open.
+//! The new layouts are logged in the `keys:<file>.ron` format so they can be kept.
+
+use bevy::{
+    color::Color,
+    input::ButtonInput,
+    log::info,
+    prelude::{Commands, Component, DespawnRecursiveExt, Entity, KeyCode, Query, Res, ResMut, Resource, TextBundle, With},
+    text::{Text, TextStyle},
+    ui::{PositionType, Style, Val}
+};
+use ron::ser::PrettyConfig;
+
+use super::key_board::{key_conflicts, KeyLayout, PlayerKeyBind, NUM_OF_BINDINGS};
+
+/// Opens and closes the rebinding screen.
+pub const REBIND_KEY: KeyCode = KeyCode::F1;
+
+/// Keeps the current key of an action.
+pub const SKIP_KEY: KeyCode = KeyCode::Escape;
+
+/// The keys used by the rebinding screen, they can't be bound to an action.
+pub const RESERVED_KEYS: [KeyCode; 2] = [REBIND_KEY, SKIP_KEY];
+
+/// The rebinding screen, the resource only exists while it is open.
+///
+/// The `Simulation` is paused and keyboard players can't move while it is open.
+///
+/// # Fields
+/// - `player`: The player whose keys are changed.
+/// - `binding`: The index of the key changed in `PlayerKeyBind::bindings`.
+/// - `message`: Why the last key was refused.
+#[derive(Debug, Resource)]
+pub struct RebindScreen {
+    player: u32,
+    binding: usize,
+    message: Option<String>,
+}
+
+/// The text of the rebinding screen.
+#[derive(Component)]
+pub struct RebindText;
+
+/// Opens the rebinding screen on `REBIND_KEY` and binds the keys pressed while it is open.
+///
+/// # Parameters
+/// - `P_FLAG_1`, `P_FLAG_2`: The players, only the keyboard ones have a `PlayerKeyBind`.
+pub fn rebind_keys<const P_FLAG_1: u32, const P_FLAG_2: u32>(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    screen: Option<ResMut<RebindScreen>>,
+
+    mut player_1: Option<ResMut<PlayerKeyBind<P_FLAG_1>>>,
+    mut player_2: Option<ResMut<PlayerKeyBind<P_FLAG_2>>>,
+
+    mut text_query: Query<(Entity, &mut Text), With<RebindText>>,
+) {
+    let Some(mut screen) = screen else {
+        let first_player = match (&player_1, &player_2) {
+            (Some(_), _) => P_FLAG_1,
+            (_, Some(_)) => P_FLAG_2,
+            _ => return,
+        };
+
+        if keys.just_pressed(REBIND_KEY) {
+            commands.insert_resource(RebindScreen { player: first_player, binding: 0, message: None });
+            commands.spawn((
+                RebindText,
+                TextBundle::from_section("", TextStyle { font_size: 24., ..Default::default() })
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(16.),
+                        left: Val::Px(16.),
+                        ..Default::default()
+                    })
+                    .with_background_color(Color::srgba(0., 0., 0., 0.8))
+            ));
+        }
+        return;
+    };
+
+    let close = |commands: &mut Commands| {
+        commands.remove_resource::<RebindScreen>();
+        for (entity, _) in &text_query {
+            commands.entity(entity).despawn_recursive();
+        }
+    };
+
+    if keys.just_pressed(REBIND_KEY) {
+        close(&mut commands);
+        return;
+    }
+
+    if let Some(&key) = keys.get_just_pressed().next() {
+        let mut bindings: Vec<(String, KeyCode)> = player_1.iter().flat_map(|keys| keys.labelled_bindings()).collect();
+        let current = match screen.player == P_FLAG_1 {
+            true => screen.binding,
+            false => bindings.len() + screen.binding,
+        };
+        bindings.extend(player_2.iter().flat_map(|keys| keys.labelled_bindings()));
+
+        if key != SKIP_KEY {
+            bindings[current].1 = key;
+            screen.message = key_conflicts(&bindings).into_iter().next();
+
+            if screen.message.is_none() {
+                match (screen.player == P_FLAG_1, player_1.as_mut(), player_2.as_mut()) {
+                    (true, Some(keys), _) => *keys.binding_mut(screen.binding) = key,
+                    (false, _, Some(keys)) => *keys.binding_mut(screen.binding) = key,
+                    _ => {}
+                }
+            }
+        }
+        else {
+            screen.message = None;
+        }
+
+        if screen.message.is_none() {
+            screen.binding += 1;
+        }
+    }
+
+    if screen.binding == NUM_OF_BINDINGS {
+        let layout = match screen.player == P_FLAG_1 {
+            true => player_1.as_deref().map(KeyLayout::from),
+            false => player_2.as_deref().map(KeyLayout::from),
+        };
+        if let Some(layout) = layout {
+            let layout = ron::ser::to_string_pretty(&layout, PrettyConfig::default())
+                .expect("Key layouts are valid RON");
+
+            info!("New keys of player {}, save them in a file to use them with keys:<file>.ron\n{layout}", screen.player + 1);
+        }
+
+        match (screen.player == P_FLAG_1, &player_2) {
+            (true, Some(_)) => {
+                screen.player = P_FLAG_2;
+                screen.binding = 0;
+            },
+            _ => {
+                close(&mut commands);
+                return;
+            }
+        }
+    }
+
+    let bindings = match screen.player == P_FLAG_1 {
+        true => player_1.as_deref().map(PlayerKeyBind::bindings),
+        false => player_2.as_deref().map(PlayerKeyBind::bindings),
+    };
+    let Some(bindings) = bindings else {
+        return;
+    };
+
+    let mut lines = vec![format!("Keys of player {} ({REBIND_KEY:?} to close, {SKIP_KEY:?} to keep a key)", screen.player + 1)];
+    lines.extend(bindings.iter().enumerate().map(|(i, (action, key))| match i == screen.binding {
+        true => format!("> {action}: press a key (now {key:?})"),
+        false => format!("  {action}: {key:?}"),
+    }));
+    lines.extend(screen.message.clone());
+
+    for (_, mut text) in &mut text_query {
+        text.sections[0].value = lines.join("\n");
+    }
+}
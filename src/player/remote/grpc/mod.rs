@@ -0,0 +1,148 @@
+//! A `Transport` that calls the `TankAi` gRPC service defined in `proto/tank_ai.proto`.
+//!
+//! `brain` calls run in the background so a slow answer can time out without
+//! being lost, like the other transports it is then discarded when it arrives.
+
+use std::{collections::VecDeque, future::Future, io::{self, ErrorKind}, time::Duration};
+
+use tokio::{runtime::{self, Runtime}, task::JoinHandle, time::timeout};
+use tonic::{client::Grpc, codec::ProstCodec, codegen::http::uri::PathAndQuery, transport::{Channel, Endpoint}, Code, Request, Response, Status};
+
+use crate::{engine::tank::instruction::Action, player::handshake::{BotInfo, GameInfo}};
+
+use super::{EngineMessage, Transport};
+
+pub mod proto;
+
+/// How long the AI has to answer a call by default.
+pub const GRPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+type BrainCall = JoinHandle<Result<Response<proto::Action>, Status>>;
+
+pub struct GrpcTransport {
+    runtime: Runtime,
+    client: Grpc<Channel>,
+    pending: VecDeque<BrainCall>,
+    timeout: Duration,
+}
+
+impl GrpcTransport {
+    /// Connects to an AI.
+    ///
+    /// # Parameters
+    /// - `url`: The address of the AI, e.g. `grpc://127.0.0.1:5000`.
+    /// - `timeout`: How long the AI has to answer a call.
+    pub fn connect(url: &str, timeout: Duration) -> io::Result<Self> {
+        let addr = url.strip_prefix("grpc://").unwrap_or(url);
+
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let endpoint = Endpoint::from_shared(format!("http://{addr}"))
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?
+            .connect_timeout(timeout)
+            .tcp_nodelay(true);
+        let channel = runtime.block_on(endpoint.connect())
+            .map_err(io::Error::other)?;
+
+        Ok(GrpcTransport {
+            runtime,
+            client: Grpc::new(channel),
+            pending: VecDeque::new(),
+            timeout,
+        })
+    }
+
+    fn call<Req, Resp>(&self, path: &'static str, msg: Req) -> impl Future<Output = Result<Response<Resp>, Status>> + Send + 'static
+    where
+        Req: prost::Message + Send + 'static,
+        Resp: prost::Message + Default + Send + 'static,
+    {
+        let mut client = self.client.clone();
+
+        async move {
+            client.ready().await
+                .map_err(|err| Status::unavailable(err.to_string()))?;
+
+            client.unary(Request::new(msg), PathAndQuery::from_static(path), ProstCodec::default()).await
+        }
+    }
+
+    fn call_blocking<Req, Resp>(&self, path: &'static str, msg: Req) -> io::Result<Resp>
+    where
+        Req: prost::Message + Send + 'static,
+        Resp: prost::Message + Default + Send + 'static,
+    {
+        let call = self.call::<Req, Resp>(path, msg);
+
+        match self.runtime.block_on(async { timeout(self.timeout, call).await }) {
+            Ok(result) => result.map(Response::into_inner).map_err(status_to_io),
+            Err(_elapsed) => Err(ErrorKind::TimedOut.into()),
+        }
+    }
+}
+
+impl Transport for GrpcTransport {
+    fn start_game(&mut self, game_info: &GameInfo) -> io::Result<BotInfo> {
+        self.call_blocking::<_, proto::BotInfo>("/tank_ai.TankAi/StartGame", proto::StartGame::from(game_info))
+            .map(Into::into)
+    }
+
+    fn send(&mut self, msg: &EngineMessage) -> io::Result<()> {
+        match msg {
+            EngineMessage::StartGame(game_info) => {
+                self.call_blocking::<_, proto::BotInfo>("/tank_ai.TankAi/StartGame", proto::StartGame::from(*game_info))
+                    .map(|_| ())
+            },
+            EngineMessage::Brain(data) => {
+                let call = self.call("/tank_ai.TankAi/Brain", proto::Brain::from(*data));
+
+                self.pending.push_back(self.runtime.spawn(call));
+                Ok(())
+            },
+            EngineMessage::Win { game_id } => {
+                self.call_blocking::<_, proto::Ack>("/tank_ai.TankAi/Win", proto::GameOver { game_id: game_id.to_string() })
+                    .map(|_| ())
+            },
+            EngineMessage::Loss { game_id } => {
+                self.call_blocking::<_, proto::Ack>("/tank_ai.TankAi/Loss", proto::GameOver { game_id: game_id.to_string() })
+                    .map(|_| ())
+            },
+        }
+    }
+
+    fn recv(&mut self) -> io::Result<Action> {
+        let call = self.pending.front_mut()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "No brain call in progress"))?;
+
+        let result = match self.runtime.block_on(async { timeout(self.timeout, call).await }) {
+            Ok(result) => result,
+            Err(_elapsed) => return Err(ErrorKind::TimedOut.into()),
+        };
+        self.pending.pop_front();
+
+        let response = result.map_err(io::Error::other)?
+            .map_err(status_to_io)?
+            .into_inner();
+
+        proto::action::Kind::try_from(response.action)
+            .map(Into::into)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn close(&mut self) {
+        for call in self.pending.drain(..) {
+            call.abort();
+        }
+    }
+}
+
+/// Errors raised by the AI's handler count as invalid answers, anything else as a lost connection.
+fn status_to_io(status: Status) -> io::Error {
+    match status.code() {
+        Code::Unavailable | Code::Cancelled => io::Error::new(ErrorKind::ConnectionAborted, status),
+        Code::DeadlineExceeded => io::Error::new(ErrorKind::TimedOut, status),
+        _ => io::Error::new(ErrorKind::InvalidData, status),
+    }
+}
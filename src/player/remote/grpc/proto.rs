@@ -0,0 +1,252 @@
+//! The messages of `proto/tank_ai.proto`.
+//!
+//! These are written by hand, in the same shape `prost-build` would generate,
+//! so building the engine doesn't require `protoc`. Keep them in sync with the schema.
+
+use crate::engine::tank::{instruction, vision};
+use crate::player::{handshake, observation::PlayerDataSerialized};
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StartGame {
+    #[prost(string, tag = "1")]
+    pub game_id: String,
+    #[prost(uint32, tag = "2")]
+    pub protocol_version: u32,
+    #[prost(message, optional, tag = "3")]
+    pub rules: Option<Rules>,
+    #[prost(message, optional, tag = "4")]
+    pub sensors: Option<SensorLayout>,
+    #[prost(message, optional, tag = "5")]
+    pub map: Option<MapInfo>,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Rules {
+    #[prost(float, tag = "1")]
+    pub tank_size: f32,
+    #[prost(float, tag = "2")]
+    pub tank_move_speed: f32,
+    #[prost(float, tag = "3")]
+    pub tank_rotation_speed: f32,
+    #[prost(float, tag = "4")]
+    pub turret_rotation_speed: f32,
+    #[prost(float, tag = "5")]
+    pub bullet_speed: f32,
+    #[prost(float, tag = "6")]
+    pub reload_time: f32,
+    #[prost(float, tag = "7")]
+    pub pickup_duration: f32,
+    #[prost(float, tag = "8")]
+    pub speed_boost_factor: f32,
+    #[prost(float, tag = "9")]
+    pub fast_reload_factor: f32,
+    #[prost(uint32, tag = "10")]
+    pub extra_ammo: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SensorLayout {
+    #[prost(uint32, tag = "1")]
+    pub turret_rays: u32,
+    #[prost(float, tag = "2")]
+    pub turret_fov: f32,
+    #[prost(float, tag = "3")]
+    pub turret_range: f32,
+    #[prost(string, repeated, tag = "4")]
+    pub hull_rays: Vec<String>,
+    #[prost(float, tag = "5")]
+    pub hull_range: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct MapInfo {
+    #[prost(uint32, tag = "1")]
+    pub width: u32,
+    #[prost(uint32, tag = "2")]
+    pub height: u32,
+    #[prost(float, tag = "3")]
+    pub tile_size: f32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BotInfo {
+    #[prost(string, optional, tag = "1")]
+    pub name: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub version: Option<String>,
+    #[prost(uint32, optional, tag = "3")]
+    pub protocol_version: Option<u32>,
+    #[prost(string, repeated, tag = "4")]
+    pub features: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Vec2 {
+    #[prost(float, tag = "1")]
+    pub x: f32,
+    #[prost(float, tag = "2")]
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct VisionHit {
+    #[prost(oneof = "vision_hit::Hit", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub hit: Option<vision_hit::Hit>,
+}
+
+pub mod vision_hit {
+    #[derive(Clone, Copy, PartialEq, ::prost::Oneof)]
+    pub enum Hit {
+        #[prost(float, tag = "1")]
+        Wall(f32),
+        #[prost(float, tag = "2")]
+        Enemy(f32),
+        #[prost(float, tag = "3")]
+        DestructibleWall(f32),
+        #[prost(float, tag = "4")]
+        LowWall(f32),
+        #[prost(float, tag = "5")]
+        Water(f32),
+        #[prost(float, tag = "6")]
+        Hole(f32),
+        #[prost(float, tag = "7")]
+        Pickup(f32),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Brain {
+    #[prost(string, tag = "1")]
+    pub game_id: String,
+    #[prost(message, optional, tag = "2")]
+    pub pos: Option<Vec2>,
+    #[prost(float, tag = "3")]
+    pub rot: f32,
+    #[prost(float, tag = "4")]
+    pub turret_rot: f32,
+    #[prost(message, repeated, tag = "5")]
+    pub turret_vision: Vec<VisionHit>,
+    #[prost(message, repeated, tag = "6")]
+    pub hull_vision: Vec<VisionHit>,
+    #[prost(string, repeated, tag = "7")]
+    pub errors: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Action {
+    #[prost(enumeration = "action::Kind", tag = "1")]
+    pub action: i32,
+}
+
+pub mod action {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Kind {
+        Wait = 0,
+        Shoot = 1,
+        MoveForward = 2,
+        MoveBackward = 3,
+        RotateLeft = 4,
+        RotateRight = 5,
+        SpinLeft = 6,
+        SpinRight = 7,
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GameOver {
+    #[prost(string, tag = "1")]
+    pub game_id: String,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Ack {}
+
+impl From<&handshake::GameInfo> for StartGame {
+    fn from(info: &handshake::GameInfo) -> Self {
+        StartGame {
+            game_id: info.game_id.clone(),
+            protocol_version: info.protocol_version,
+            rules: Some(Rules {
+                tank_size: info.rules.tank_size,
+                tank_move_speed: info.rules.tank_move_speed,
+                tank_rotation_speed: info.rules.tank_rotation_speed,
+                turret_rotation_speed: info.rules.turret_rotation_speed,
+                bullet_speed: info.rules.bullet_speed,
+                reload_time: info.rules.reload_time,
+                pickup_duration: info.rules.pickup_duration,
+                speed_boost_factor: info.rules.speed_boost_factor,
+                fast_reload_factor: info.rules.fast_reload_factor,
+                extra_ammo: info.rules.extra_ammo,
+            }),
+            sensors: Some(SensorLayout {
+                turret_rays: info.sensors.turret_rays as u32,
+                turret_fov: info.sensors.turret_fov,
+                turret_range: info.sensors.turret_range,
+                hull_rays: info.sensors.hull_rays.clone(),
+                hull_range: info.sensors.hull_range,
+            }),
+            map: Some(MapInfo {
+                width: info.map.width as u32,
+                height: info.map.height as u32,
+                tile_size: info.map.tile_size,
+            }),
+        }
+    }
+}
+
+impl From<BotInfo> for handshake::BotInfo {
+    fn from(info: BotInfo) -> Self {
+        handshake::BotInfo {
+            name: info.name,
+            version: info.version,
+            protocol_version: info.protocol_version,
+            features: info.features,
+        }
+    }
+}
+
+impl From<&Option<vision::VisionHit>> for VisionHit {
+    fn from(hit: &Option<vision::VisionHit>) -> Self {
+        VisionHit {
+            hit: hit.map(|hit| match hit {
+                vision::VisionHit::Wall(dist) => vision_hit::Hit::Wall(dist),
+                vision::VisionHit::Enemy(dist) => vision_hit::Hit::Enemy(dist),
+                vision::VisionHit::DestructibleWall(dist) => vision_hit::Hit::DestructibleWall(dist),
+                vision::VisionHit::LowWall(dist) => vision_hit::Hit::LowWall(dist),
+                vision::VisionHit::Water(dist) => vision_hit::Hit::Water(dist),
+                vision::VisionHit::Hole(dist) => vision_hit::Hit::Hole(dist),
+                vision::VisionHit::Pickup(dist) => vision_hit::Hit::Pickup(dist),
+            })
+        }
+    }
+}
+
+impl From<&PlayerDataSerialized> for Brain {
+    fn from(data: &PlayerDataSerialized) -> Self {
+        Brain {
+            game_id: data.game_id.clone(),
+            pos: Some(Vec2 { x: data.pos.x, y: data.pos.y }),
+            rot: data.rot,
+            turret_rot: data.turret_rot,
+            turret_vision: data.turret_vision.iter().map(Into::into).collect(),
+            hull_vision: data.hull_vision.iter().map(Into::into).collect(),
+            errors: data.errors.clone(),
+        }
+    }
+}
+
+impl From<action::Kind> for instruction::Action {
+    fn from(kind: action::Kind) -> Self {
+        match kind {
+            action::Kind::Wait => instruction::Action::Wait,
+            action::Kind::Shoot => instruction::Action::Shoot,
+            action::Kind::MoveForward => instruction::Action::MoveForward,
+            action::Kind::MoveBackward => instruction::Action::MoveBackward,
+            action::Kind::RotateLeft => instruction::Action::RotateLeft,
+            action::Kind::RotateRight => instruction::Action::RotateRight,
+            action::Kind::SpinLeft => instruction::Action::SpinLeft,
+            action::Kind::SpinRight => instruction::Action::SpinRight,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! A `Transport` that posts every message to its route of the AI's REST API
+//! (`/start_game`, `/brain`, `/win` and `/loss`), one HTTP request per message.
+//!
+//! `brain` requests run on their own thread so a slow answer can time out without
+//! being lost, like the other transports it is then discarded when it arrives.
+
+use std::{collections::VecDeque, io::{self, ErrorKind}, net::IpAddr, sync::mpsc::{self, Receiver, RecvTimeoutError}, thread, time::Duration};
+
+use reqwest::blocking::{Client, Response};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{engine::tank::instruction::Action, player::{fault::ConnectionPolicy, handshake::{BotInfo, GameInfo}}};
+
+use super::{action_from_json, EngineMessage, Transport};
+
+/// How long the AI has to answer a request by default.
+pub const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a `brain` request is kept open at least, an AI that hasn't answered by then is considered gone.
+const BRAIN_REQUEST_LIMIT: Duration = Duration::from_secs(30);
+
+pub struct HttpTransport {
+    client: Client,
+    url: String,
+    pending: VecDeque<Receiver<io::Result<Action>>>,
+    policy: ConnectionPolicy,
+    timeout: Duration,
+}
+
+impl HttpTransport {
+    /// Creates the transport, nothing is sent until `start_game`.
+    ///
+    /// # Parameters
+    /// - `ip`, `port`: The address of the AI.
+    /// - `policy`: How often `/start_game` is retried and how long requests may take.
+    pub fn new(ip: IpAddr, port: u16, policy: &ConnectionPolicy) -> io::Result<Self> {
+        let client = Client::builder()
+            .timeout(None)
+            .build()
+            .map_err(into_io)?;
+
+        Ok(HttpTransport {
+            client,
+            url: format!("http://{ip}:{port}"),
+            pending: VecDeque::new(),
+            policy: policy.clone(),
+            timeout: policy.timeout_or(HTTP_TIMEOUT),
+        })
+    }
+
+    fn post(&self, route: &str, body: &impl Serialize) -> io::Result<Response> {
+        post(&self.client, &format!("{}/{route}", self.url), body, self.timeout)
+    }
+}
+
+impl Transport for HttpTransport {
+    fn start_game(&mut self, game_info: &GameInfo) -> io::Result<BotInfo> {
+        let response = self.policy.retry(|| self.post("start_game", game_info))?;
+
+        // bots that predate the handshake answer without a body
+        Ok(response.json::<BotInfo>().unwrap_or_default())
+    }
+
+    fn send(&mut self, msg: &EngineMessage) -> io::Result<()> {
+        match msg {
+            EngineMessage::StartGame(game_info) => self.post("start_game", game_info).map(|_| ()),
+            EngineMessage::Brain(data) => {
+                let (client, url, data) = (self.client.clone(), format!("{}/brain", self.url), (*data).clone());
+                let limit = BRAIN_REQUEST_LIMIT.max(self.timeout * 2);
+                let (send_action, action) = mpsc::channel();
+
+                thread::spawn(move || {
+                    let action = match post(&client, &url, &data, limit) {
+                        Ok(response) => response.text().map_err(into_io).and_then(|text| action_from_json(&text)),
+                        Err(err) if err.kind() == ErrorKind::TimedOut => {
+                            Err(io::Error::new(ErrorKind::ConnectionAborted, format!("No answer to /brain in {}s", limit.as_secs())))
+                        },
+                        Err(err) => Err(err),
+                    };
+                    let _ = send_action.send(action);
+                });
+
+                self.pending.push_back(action);
+                Ok(())
+            },
+            EngineMessage::Win { game_id } => self.post("win", &json!({ "game_id": game_id })).map(|_| ()),
+            EngineMessage::Loss { game_id } => self.post("loss", &json!({ "game_id": game_id })).map(|_| ()),
+        }
+    }
+
+    fn recv(&mut self) -> io::Result<Action> {
+        let action = self.pending.front()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "No brain request in progress"))?;
+
+        let result = match action.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => return Err(ErrorKind::TimedOut.into()),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(ErrorKind::ConnectionAborted, "Brain request lost")),
+        };
+        self.pending.pop_front();
+
+        result
+    }
+
+    fn close(&mut self) {
+        // the requests still running finish on their own
+        self.pending.clear();
+    }
+}
+
+/// Posts `body` as JSON, answers with a status outside of the 200 series are invalid.
+fn post(client: &Client, url: &str, body: &impl Serialize, timeout: Duration) -> io::Result<Response> {
+    let response = client.post(url)
+        .json(body)
+        .timeout(timeout)
+        .send()
+        .map_err(into_io)?;
+
+    match response.status().is_success() {
+        true => Ok(response),
+        false => Err(io::Error::new(ErrorKind::InvalidData, format!("{url} returned {}", response.status()))),
+    }
+}
+
+fn into_io(err: reqwest::Error) -> io::Error {
+    match (err.is_timeout(), err.is_connect()) {
+        (true, _) => io::Error::new(ErrorKind::TimedOut, err),
+        (_, true) => io::Error::new(ErrorKind::ConnectionRefused, err),
+        _ => io::Error::other(err),
+    }
+}
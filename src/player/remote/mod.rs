@@ -0,0 +1,372 @@
+//! This module provides AI players, whatever carries their messages: requests to
+//! the REST routes, a WebSocket, a child process or gRPC (see `Transport`).
+//! The engine streams observations and the AI answers each one with an action.
+//!
+//! Messages are JSON objects tagged with `type`, how they are framed depends on the `Transport`,
+//! over HTTP each message is posted to the route of the same name instead.
+//! - `{"type": "start_game", "game_id": "...", ...}` is sent once the map is ready, the AI
+//!   must reply with its `BotInfo` (see `handshake`), `{}` is enough.
+//! - `{"type": "brain", "game_id": "...", "pos": ..., ...}` is sent whenever a new
+//!   observation is available, the AI must reply with `{"action": "..."}`.
+//! - `{"type": "win", "game_id": "..."}` or `{"type": "loss", ...}` is sent when
+//!   the game ends, then the connection is closed.
+//!
+//! Late or invalid answers count as failures, the player forfeits after too many
+//! in a row or as soon as the connection is lost (see `fault`).
+
+use std::{io::{self, ErrorKind}, sync::{mpsc::{self, Receiver, RecvTimeoutError, Sender}, Arc, Mutex}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use bevy::{asset::Assets, log::warn, prelude::{Commands, EventWriter, GlobalTransform, Query, Res, ResMut, Resource, With, Without}};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{map::{CurrentMap, Map}, tank::{gen::{Tank, Turret}, instruction::{Action, Instruction}, vision::{NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY}}};
+
+use super::{fault::{ConnectionPolicy, Fault, FaultCounter, ForfeitFlag}, handshake::{BotInfo, GameInfo, PlayerInfo}, observation::{PlayerData, PlayerDataSerialized, TankSensors}, telemetry::Telemetry, PlayerID};
+
+pub mod http;
+pub mod web_socket;
+pub mod process;
+pub mod grpc;
+
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+
+/// A connection to an AI that carries one message at a time.
+pub trait Transport: Send + 'static {
+    /// Sends the `start_game` message and waits for the AI to describe itself.
+    fn start_game(&mut self, game_info: &GameInfo) -> io::Result<BotInfo>;
+
+    /// Sends a message to the AI.
+    fn send(&mut self, msg: &EngineMessage) -> io::Result<()>;
+
+    /// Waits for the AI's answer to a `brain` message.
+    ///
+    /// # Returns
+    /// An error of kind `ErrorKind::TimedOut` if the AI took too long to answer,
+    /// or `ErrorKind::InvalidData` if the answer isn't a valid action.
+    fn recv(&mut self) -> io::Result<Action>;
+
+    /// Releases the connection once the game is over.
+    fn close(&mut self) {}
+}
+
+/// A message sent by the engine to the AI.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineMessage<'a> {
+    StartGame(&'a GameInfo),
+    Brain(&'a PlayerDataSerialized),
+    Win {
+        game_id: &'a str
+    },
+    Loss {
+        game_id: &'a str
+    }
+}
+
+impl EngineMessage<'_> {
+    /// Serializes the message for the JSON based transports.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Engine messages are always serializable")
+    }
+}
+
+#[derive(Deserialize)]
+struct PlayerInstruction{
+    action: String
+}
+
+/// Parses the answer of the JSON based transports to `start_game`.
+pub fn bot_info_from_json(text: &str) -> io::Result<BotInfo> {
+    serde_json::from_str(text)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+/// Parses an answer of the JSON based transports, `{"action": "..."}`.
+pub fn action_from_json(text: &str) -> io::Result<Action> {
+    serde_json::from_str::<PlayerInstruction>(text)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, format!("Invalid answer ({err}), expected {{\"action\": \"...\"}}")))?
+        .action
+        .parse()
+        .map_err(|err: String| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+/// Messages sent from the game to the connection thread.
+enum GameUpdate {
+    Data(PlayerData<NUM_OF_TURRET_RAY, NUM_OF_HULL_RAY>),
+    End { win: bool }
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock)
+}
+
+/// A player controlled by an AI over a persistent connection.
+#[derive(Resource)]
+pub struct RemotePlayer<const P_FLAG: u32>{
+    game_id: String,
+    transport: Mutex<Option<Box<dyn Transport>>>,
+    channels: Mutex<Option<(Sender<Instruction<P_FLAG>>, Receiver<GameUpdate>)>>,
+
+    recv: Arc<Mutex<Receiver<Instruction<P_FLAG>>>>,
+    send: Option<Sender<GameUpdate>>,
+    connection: Option<JoinHandle<()>>,
+    forfeit: ForfeitFlag,
+    telemetry: Telemetry,
+    ended: bool
+}
+
+impl<const P_FLAG: u32> RemotePlayer<P_FLAG> {
+    /// Creates a player, nothing is sent to the AI until `start` is called.
+    ///
+    /// # Parameters
+    /// - `transport`: The connection to the AI.
+    /// - `game_id`: The id of the game.
+    pub fn new(transport: impl Transport, game_id: &str) -> Self {
+        let (send_inst, recv_inst) = mpsc::channel();
+        let (send_update, recv_update) = mpsc::channel::<GameUpdate>();
+
+        RemotePlayer{
+            game_id: game_id.to_string(),
+            transport: Mutex::new(Some(Box::new(transport))),
+            channels: Mutex::new(Some((send_inst, recv_update))),
+
+            recv: Arc::new(Mutex::new(recv_inst)),
+            send: Some(send_update),
+            connection: None,
+            forfeit: ForfeitFlag::default(),
+            telemetry: Telemetry::default(),
+            ended: false
+        }
+    }
+
+    /// Performs the `start_game` handshake and starts exchanging messages on a background thread.
+    ///
+    /// # Parameters
+    /// - `game_info`: The description of the game sent to the AI.
+    /// - `policy`: How many failed answers in a row are tolerated.
+    ///
+    /// # Returns
+    /// What the AI declared about itself.
+    ///
+    /// # Panics
+    /// Panics if the player has already started.
+    pub fn start(&mut self, game_info: &GameInfo, policy: &ConnectionPolicy) -> io::Result<BotInfo> {
+        let mut transport = self.transport.get_mut().ok().and_then(Option::take)
+            .expect("Remote player already started");
+        let (send_inst, recv_update) = self.channels.get_mut().ok().and_then(Option::take)
+            .expect("Remote player already started");
+
+        let bot_info = transport.start_game(game_info)?;
+
+        let game_id = self.game_id.clone();
+        let forfeit = self.forfeit.clone();
+        let mut failures = FaultCounter::new(policy, forfeit.clone());
+        let telemetry = self.telemetry.clone();
+        let connection = thread::spawn(move || {
+            let mut player_data = PlayerDataSerialized::new(&game_id);
+            // answers to observations that timed out, they are discarded when they arrive
+            let mut late_replies = 0;
+
+            loop {
+                let update = match recv_update.recv_timeout(IDLE_WAIT) {
+                    Ok(update) => update,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                // only the latest observation matters
+                let update = recv_update.try_iter().fold(update, |latest, update| match latest {
+                    GameUpdate::End { .. } => latest,
+                    GameUpdate::Data(_) => update,
+                });
+
+                match update {
+                    // the player is out, only the result is still sent
+                    GameUpdate::Data(_) if forfeit.get().is_some() => {},
+                    GameUpdate::Data(new_player_data) => {
+                        player_data.update(&new_player_data);
+
+                        if late_replies > 0 {
+                            match transport.recv() {
+                                Ok(_) => late_replies -= 1,
+                                Err(err) if err.kind() == ErrorKind::InvalidData => {
+                                    late_replies -= 1;
+                                    player_data.errors.push(err.to_string());
+                                },
+                                Err(err) if is_timeout(&err) => {
+                                    failures.fail(Fault::Timeout);
+                                    continue;
+                                },
+                                Err(err) => {
+                                    warn!("Lost connection to player {P_FLAG} - {err}");
+                                    telemetry.error();
+                                    forfeit.raise(Fault::Disconnected);
+                                    break;
+                                }
+                            }
+                        }
+
+                        let sent = Instant::now();
+                        if let Err(err) = transport.send(&EngineMessage::Brain(&player_data)) {
+                            warn!("Lost connection to player {P_FLAG} - {err}");
+                            telemetry.error();
+                            forfeit.raise(Fault::Disconnected);
+                            break;
+                        }
+                        player_data.errors.clear();
+
+                        match transport.recv() {
+                            Ok(action) => {
+                                telemetry.action(sent.elapsed());
+                                failures.succeed();
+
+                                if let Some(inst) = action.instruction() {
+                                    let _ = send_inst.send(inst);
+                                }
+                            },
+                            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                                warn!("Player {P_FLAG} sent an invalid action - {err}");
+                                telemetry.invalid_action(sent.elapsed());
+                                player_data.errors.push(err.to_string());
+                                failures.fail(Fault::InvalidResponse);
+                            },
+                            Err(err) if is_timeout(&err) => {
+                                warn!("Player {P_FLAG} took too long to respond");
+                                telemetry.timeout();
+                                late_replies += 1;
+                                failures.fail(Fault::Timeout);
+                            },
+                            Err(err) => {
+                                warn!("Lost connection to player {P_FLAG} - {err}");
+                                telemetry.error();
+                                forfeit.raise(Fault::Disconnected);
+                                break;
+                            }
+                        }
+                    },
+                    GameUpdate::End { win } => {
+                        let game_id = game_id.as_str();
+                        let msg = match win {
+                            true => EngineMessage::Win { game_id },
+                            false => EngineMessage::Loss { game_id },
+                        };
+
+                        let _ = transport.send(&msg);
+                        break;
+                    }
+                }
+            }
+
+            transport.close();
+        });
+
+        self.connection = Some(connection);
+
+        Ok(bot_info)
+    }
+
+    /// The flag raised when the player forfeits.
+    pub fn forfeit_flag(&self) -> ForfeitFlag {
+        self.forfeit.clone()
+    }
+
+    /// What is measured about the AI.
+    pub fn telemetry(&self) -> Telemetry {
+        self.telemetry.clone()
+    }
+
+    fn send(&self, update: GameUpdate) {
+        if let Some(send) = &self.send {
+            let _ = send.send(update);
+        }
+    }
+
+    fn end(&mut self, win: bool) {
+        if !self.ended {
+            self.send(GameUpdate::End { win });
+            self.ended = true;
+        }
+    }
+}
+
+impl<const P_FLAG: u32> Drop for RemotePlayer<P_FLAG> {
+    /// Waits for the connection to close, so the AI is told the result before the game exits.
+    fn drop(&mut self) {
+        self.send = None;
+
+        if let Some(connection) = self.connection.take() {
+            let _ = connection.join();
+        }
+    }
+}
+
+pub fn start_game_msg<const P_FLAG: u32>(
+    mut commands: Commands,
+    mut player: ResMut<RemotePlayer<P_FLAG>>,
+
+    policy: Res<ConnectionPolicy>,
+    current_map: Res<CurrentMap>,
+    maps: Res<Assets<Map>>
+) {
+    let game_info = GameInfo::from_current_map(&player.game_id, &current_map, &maps);
+    let bot_info = match player.start(&game_info, &policy) {
+        Ok(bot_info) => bot_info,
+        Err(err) => {
+            // `forfeit_player` removes the tank and the match result names the fault
+            warn!("Failed to initialize player {P_FLAG} - {err}");
+            player.telemetry.error();
+            player.forfeit.raise(Fault::Disconnected);
+            return;
+        }
+    };
+
+    if let Err(err) = bot_info.check::<P_FLAG>() {
+        panic!("{err}");
+    }
+
+    commands.insert_resource(PlayerInfo::<P_FLAG>(bot_info));
+}
+
+pub fn update_player_data<const P_FLAG: u32>(
+    player: Res<RemotePlayer<P_FLAG>>,
+
+    tank_query: Query<TankSensors, With<PlayerID<P_FLAG>>>,
+    turret_query: Query<&GlobalTransform, With<Turret>>,
+) {
+    let Ok((transform, tank, tank_vision, turret_vision)) = tank_query.get_single() else {
+        return;
+    };
+    let turret_transform = turret_query.get(tank.turret).expect("Tank lost ref to turret entity");
+
+    player.send(
+        GameUpdate::Data(PlayerData::new(
+            transform,
+            turret_transform,
+            turret_vision.rays,
+            tank_vision.rays
+        ))
+    );
+}
+
+pub fn remote_input<const P_FLAG: u32>(
+    player: Res<RemotePlayer<P_FLAG>>,
+    mut event_writer: EventWriter<Instruction<P_FLAG>>
+) {
+    if let Ok(recv) = player.recv.try_lock() {
+        while let Ok(val) = recv.try_recv(){
+            event_writer.send(val);
+        };
+    }
+}
+
+pub fn end_game_msg<const P_FLAG: u32>(
+    mut player: ResMut<RemotePlayer<P_FLAG>>,
+    player_tanks: Query<(), (With<Tank>, With<PlayerID<P_FLAG>>)>,
+    other_tanks: Query<(), (With<Tank>, Without<PlayerID<P_FLAG>>)>
+) {
+    match (player_tanks.iter().count(), other_tanks.iter().count()) {
+        (0, _) => player.end(false),
+        (_, 0) => player.end(true),
+        _ => {}
+    }
+}
@@ -0,0 +1,148 @@
+//! A `Transport` that runs the AI as a child process and exchanges
+//! newline delimited JSON over its stdin and stdout.
+//!
+//! The bot must write exactly one line per `brain` message to stdout,
+//! logs should go to stderr which is shown in the engine's output.
+
+use std::{io::{self, BufRead, BufReader, ErrorKind, Write}, process::{Child, ChildStdin, Command, Stdio}, sync::mpsc::{self, Receiver, RecvTimeoutError}, thread, time::{Duration, Instant}};
+
+use crate::{engine::tank::instruction::Action, player::handshake::{BotInfo, GameInfo}};
+
+use super::{action_from_json, bot_info_from_json, EngineMessage, Transport};
+
+/// How long the bot has to answer an observation by default.
+pub const PROCESS_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How long the bot has to answer `start_game`, it may still be starting up.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the bot has to exit on its own after the game ends before it is killed.
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+pub struct ProcessTransport {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<io::Result<String>>,
+    timeout: Duration,
+}
+
+impl ProcessTransport {
+    /// Starts the bot.
+    ///
+    /// # Parameters
+    /// - `cmd`: The program followed by its arguments, separated by spaces (see `split_command`).
+    /// - `timeout`: How long the bot has to answer an observation.
+    pub fn spawn(cmd: &str, timeout: Duration) -> io::Result<Self> {
+        let args = split_command(cmd)?;
+        let (program, args) = args.split_first()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Empty command"))?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // stdout is read on its own thread so `recv` can time out
+        let (send_line, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if send_line.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(ProcessTransport { child, stdin, lines, timeout })
+    }
+
+    fn read_line(&mut self, timeout: Duration) -> io::Result<String> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => Err(ErrorKind::TimedOut.into()),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(ErrorKind::UnexpectedEof, "Bot exited")),
+        }
+    }
+}
+
+/// Splits a command into the program and its arguments like a shell would.
+///
+/// Arguments are separated by spaces, double or single quotes keep spaces in an
+/// argument (e.g. `python "C:\My Bots\bot.py"`). Backslashes are kept as they are,
+/// so Windows paths don't need escaping.
+///
+/// # Returns
+/// An error of kind `ErrorKind::InvalidInput` if a quote isn't closed.
+pub fn split_command(cmd: &str) -> io::Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+
+    for c in cmd.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            },
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(open) = quote {
+        return Err(io::Error::new(ErrorKind::InvalidInput, format!("Unclosed {open} in {cmd}")));
+    }
+    args.extend(arg);
+
+    Ok(args)
+}
+
+impl Transport for ProcessTransport {
+    fn start_game(&mut self, game_info: &GameInfo) -> io::Result<BotInfo> {
+        self.send(&EngineMessage::StartGame(game_info))?;
+
+        bot_info_from_json(&self.read_line(START_TIMEOUT)?)
+    }
+
+    fn send(&mut self, msg: &EngineMessage) -> io::Result<()> {
+        let stdin = self.stdin.as_mut().ok_or(ErrorKind::BrokenPipe)?;
+
+        writeln!(stdin, "{}", msg.to_json())?;
+        stdin.flush()
+    }
+
+    fn recv(&mut self) -> io::Result<Action> {
+        action_from_json(&self.read_line(self.timeout)?)
+    }
+
+    fn close(&mut self) {
+        // closing stdin lets the bot see the end of input and exit cleanly
+        self.stdin = None;
+
+        let start = Instant::now();
+        while start.elapsed() < EXIT_GRACE {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for ProcessTransport {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! A `Transport` that exchanges JSON text frames over a WebSocket connection.
+
+use std::{io, net::TcpStream, time::Duration};
+
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+use crate::{engine::tank::instruction::Action, player::handshake::{BotInfo, GameInfo}};
+
+use super::{action_from_json, bot_info_from_json, EngineMessage, Transport};
+
+/// How long the AI has to answer an observation by default.
+pub const WEB_SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct WebSocketTransport(WebSocket<MaybeTlsStream<TcpStream>>);
+
+impl WebSocketTransport {
+    /// Connects to an AI.
+    ///
+    /// # Parameters
+    /// - `url`: The address of the AI, e.g. `ws://127.0.0.1:5000`.
+    /// - `timeout`: How long the AI has to answer a message.
+    pub fn connect(url: &str, timeout: Duration) -> io::Result<Self> {
+        let (socket, _response) = tungstenite::connect(url).map_err(into_io)?;
+
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_nodelay(true)?;
+            stream.set_read_timeout(Some(timeout))?;
+        }
+
+        Ok(WebSocketTransport(socket))
+    }
+
+    fn read_text(&mut self) -> io::Result<String> {
+        loop {
+            match self.0.read().map_err(into_io)? {
+                Message::Text(text) => return Ok(text.to_string()),
+                Message::Close(_) => return Err(io::ErrorKind::ConnectionAborted.into()),
+                _ => {
+                    //ping, pong & binary frames
+                }
+            }
+        }
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn start_game(&mut self, game_info: &GameInfo) -> io::Result<BotInfo> {
+        self.send(&EngineMessage::StartGame(game_info))?;
+
+        bot_info_from_json(&self.read_text()?)
+    }
+
+    fn send(&mut self, msg: &EngineMessage) -> io::Result<()> {
+        self.0.send(Message::text(msg.to_json())).map_err(into_io)
+    }
+
+    fn recv(&mut self) -> io::Result<Action> {
+        action_from_json(&self.read_text()?)
+    }
+
+    fn close(&mut self) {
+        let _ = self.0.close(None);
+        let _ = self.0.flush();
+    }
+}
+
+fn into_io(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
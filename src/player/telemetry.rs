@@ -0,0 +1,238 @@
+//! This module measures how each AI player behaves: how long its answers take,
+//! how often it times out or sends invalid actions, and how many actions it
+//! issues per simulated second.
+//!
+//! The connection threads record into a shared `Telemetry`, a summary of every
+//! AI player is logged when the game ends and is available from `Match::telemetry`.
+
+use std::{fmt, sync::{Arc, Mutex}, time::Duration};
+
+use bevy::{log::info, prelude::{Local, Query, Res, ResMut, Resource, With, Without}};
+use serde::Serialize;
+
+use crate::engine::{game_time::DeltaTime, tank::gen::Tank};
+
+use super::PlayerID;
+
+/// The upper bounds of the latency histogram buckets in milliseconds, the last bucket holds everything above.
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [1, 2, 5, 10, 20, 50, 100, 250, 1000, 5000];
+
+/// A histogram of request latencies.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS_MS.iter()
+            .position(|bound| latency <= Duration::from_millis(*bound))
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+        self.counts[bucket] += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    /// The number of recorded latencies.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The number of latencies in each bucket of `LATENCY_BUCKETS_MS`.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::ZERO,
+            count => self.total / count as u32,
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns an upper bound of the `q` quantile (e.g. `0.95`), it is exact for the slowest request.
+    pub fn quantile(&self, q: f32) -> Duration {
+        let target = (self.count() as f32 * q).ceil() as u64;
+        let mut seen = 0;
+
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+
+            if seen >= target && *count > 0 {
+                return LATENCY_BUCKETS_MS.get(bucket)
+                    .map(|bound| Duration::from_millis(*bound).min(self.max))
+                    .unwrap_or(self.max);
+            }
+        }
+
+        self.max
+    }
+}
+
+/// What has been measured for an AI player.
+///
+/// # Fields
+/// - `latency`: How long the answers took, late and lost answers aren't included.
+/// - `actions`: The number of valid actions received, `wait` included.
+/// - `timeouts`: The number of requests the AI didn't answer in time.
+/// - `invalid_actions`: The number of answers that weren't a valid action.
+/// - `errors`: The number of requests that failed for any other reason (e.g. connection refused).
+#[derive(Debug, Clone, Default)]
+pub struct AiStats {
+    pub latency: LatencyHistogram,
+    pub actions: u64,
+    pub timeouts: u64,
+    pub invalid_actions: u64,
+    pub errors: u64,
+}
+
+/// Shared between a connection thread and the game.
+#[derive(Debug, Clone, Default)]
+pub struct Telemetry(Arc<Mutex<AiStats>>);
+
+impl Telemetry {
+    pub fn action(&self, latency: Duration) {
+        self.update(|stats| {
+            stats.latency.record(latency);
+            stats.actions += 1;
+        });
+    }
+
+    pub fn invalid_action(&self, latency: Duration) {
+        self.update(|stats| {
+            stats.latency.record(latency);
+            stats.invalid_actions += 1;
+        });
+    }
+
+    pub fn timeout(&self) {
+        self.update(|stats| stats.timeouts += 1);
+    }
+
+    pub fn error(&self) {
+        self.update(|stats| stats.errors += 1);
+    }
+
+    pub fn stats(&self) -> AiStats {
+        self.0.lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut AiStats)) {
+        if let Ok(mut stats) = self.0.lock() {
+            update(&mut stats);
+        }
+    }
+}
+
+/// The `Telemetry` of a player controlled by an AI.
+///
+/// # Fields
+/// - `telemetry`: What the connection thread measured.
+/// - `sim_time`: How long the game has been running in simulated seconds.
+#[derive(Resource)]
+pub struct PlayerTelemetry<const P_FLAG: u32> {
+    pub telemetry: Telemetry,
+    pub sim_time: f32,
+}
+
+impl<const P_FLAG: u32> PlayerTelemetry<P_FLAG> {
+    pub fn new(telemetry: Telemetry) -> Self {
+        PlayerTelemetry { telemetry, sim_time: 0. }
+    }
+
+    pub fn summary(&self) -> TelemetrySummary {
+        let stats = self.telemetry.stats();
+        let to_ms = |latency: Duration| latency.as_secs_f32() * 1000.;
+
+        TelemetrySummary {
+            player: P_FLAG,
+            requests: stats.latency.count() + stats.timeouts + stats.errors,
+            mean_latency_ms: to_ms(stats.latency.mean()),
+            p50_latency_ms: to_ms(stats.latency.quantile(0.5)),
+            p95_latency_ms: to_ms(stats.latency.quantile(0.95)),
+            max_latency_ms: to_ms(stats.latency.max()),
+            latency_histogram: stats.latency.counts().to_vec(),
+            actions: stats.actions,
+            actions_per_second: match self.sim_time > 0. {
+                true => stats.actions as f32 / self.sim_time,
+                false => 0.,
+            },
+            timeouts: stats.timeouts,
+            invalid_actions: stats.invalid_actions,
+            errors: stats.errors,
+        }
+    }
+}
+
+/// The telemetry of an AI player at the end of a game.
+///
+/// `latency_histogram` counts the answers in each bucket of `LATENCY_BUCKETS_MS`,
+/// the p50 and p95 latencies are upper bounds given by those buckets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TelemetrySummary {
+    pub player: u32,
+    pub requests: u64,
+    pub mean_latency_ms: f32,
+    pub p50_latency_ms: f32,
+    pub p95_latency_ms: f32,
+    pub max_latency_ms: f32,
+    pub latency_histogram: Vec<u64>,
+    pub actions: u64,
+    pub actions_per_second: f32,
+    pub timeouts: u64,
+    pub invalid_actions: u64,
+    pub errors: u64,
+}
+
+impl fmt::Display for TelemetrySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Player {}: {} requests, latency mean {:.1}ms p50 <={:.1}ms p95 <={:.1}ms max {:.1}ms, {} actions ({:.1}/s), {} timeouts, {} invalid actions, {} errors",
+            self.player,
+            self.requests,
+            self.mean_latency_ms,
+            self.p50_latency_ms,
+            self.p95_latency_ms,
+            self.max_latency_ms,
+            self.actions,
+            self.actions_per_second,
+            self.timeouts,
+            self.invalid_actions,
+            self.errors
+        )
+    }
+}
+
+/// Counts the simulated time, to compute the actions per second.
+pub fn track_sim_time<const P_FLAG: u32>(
+    mut telemetry: ResMut<PlayerTelemetry<P_FLAG>>,
+    delta_time: Res<DeltaTime>
+) {
+    telemetry.sim_time += delta_time.0;
+}
+
+/// Logs the summary once the game is over.
+pub fn report_telemetry<const P_FLAG: u32>(
+    mut reported: Local<bool>,
+    telemetry: Res<PlayerTelemetry<P_FLAG>>,
+
+    player_tanks: Query<(), (With<Tank>, With<PlayerID<P_FLAG>>)>,
+    other_tanks: Query<(), (With<Tank>, Without<PlayerID<P_FLAG>>)>
+) {
+    if *reported || (!player_tanks.is_empty() && !other_tanks.is_empty()) {
+        return;
+    }
+
+    info!("{}", telemetry.summary());
+    *reported = true;
+}
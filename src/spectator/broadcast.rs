@@ -0,0 +1,260 @@
+//! An HTTP server streaming `SpectatorMessage`s as server-sent events.
+//!
+//! - `GET /` serves a page drawing the match in the browser.
+//! - `GET /stream` answers with a `text/event-stream`, one `data:` line of JSON per message.
+//!
+//! Every spectator is written to by its own thread through a bounded queue, so a slow
+//! spectator can't slow down the game or the other spectators. Spectators that fall
+//! `SPECTATOR_BACKLOG` messages behind or disconnect are dropped.
+
+use std::{io::{self, BufRead, BufReader, ErrorKind, Write}, mem, net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs}, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Sender, SyncSender}, Arc, Mutex}, thread::{self, JoinHandle}, time::Duration};
+
+use bevy::{log::{info, warn}, prelude::Resource};
+
+use super::{MatchEvent, SpectatorMessage};
+
+/// How long a spectator has to send its request, and to accept a message before it is dropped.
+const SPECTATOR_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many messages a spectator may lag behind before it is dropped.
+const SPECTATOR_BACKLOG: usize = 1024;
+
+/// The page served on `/`.
+const VIEWER: &str = include_str!("viewer.html");
+
+/// A subscribed spectator.
+///
+/// # Fields
+/// - `messages`: The queue of the messages not written yet.
+/// - `writer`: The thread writing the messages to the spectator.
+struct Spectator {
+    messages: SyncSender<Arc<str>>,
+    writer: JoinHandle<()>
+}
+
+/// The spectators, with the map and the latest state which are sent again to every spectator joining late.
+#[derive(Default)]
+struct Spectators {
+    spectators: Vec<Spectator>,
+    map: Option<Arc<str>>,
+    state: Option<Arc<str>>
+}
+
+impl Spectators {
+    /// Queues a message for every spectator, without waiting for any of them.
+    fn send(&mut self, data: &Arc<str>) {
+        self.spectators.retain(|spectator| match spectator.messages.try_send(data.clone()) {
+            Ok(()) => true,
+            Err(mpsc::TrySendError::Full(_)) => {
+                warn!("Dropping a spectator that fell behind");
+                false
+            },
+            Err(mpsc::TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+/// The running broadcast server.
+///
+/// When it is dropped the port is released and the last messages are flushed to the spectators.
+#[derive(Resource)]
+pub struct Broadcast {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+    messages: Option<Sender<SpectatorMessage>>,
+    writer: Option<JoinHandle<()>>
+}
+
+impl Broadcast {
+    /// Starts listening for spectators.
+    ///
+    /// # Parameters
+    /// - `addr`: The address to listen on, e.g. `127.0.0.1:7000` (port `0` picks a free port).
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let spectators = Arc::new(Mutex::new(Spectators::default()));
+
+        info!("Broadcasting the game on http://{addr}");
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let listener = {
+            let spectators = spectators.clone();
+            let stopped = stopped.clone();
+
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    // woken up by `drop`, the listener is closed when the thread returns
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let spectators = spectators.clone();
+
+                    match stream {
+                        Ok(stream) => {
+                            thread::spawn(move || {
+                                if let Err(err) = accept(stream, &spectators) {
+                                    warn!("Failed to accept spectator - {err}");
+                                }
+                            });
+                        },
+                        Err(err) => warn!("Failed to accept spectator - {err}"),
+                    }
+                }
+            })
+        };
+
+        let (messages, recv_message) = mpsc::channel::<SpectatorMessage>();
+        let writer = thread::spawn(move || {
+            let mut map = None;
+
+            for msg in recv_message {
+                let data: Arc<str> = serde_json::to_string(&msg).expect("Spectator messages are valid JSON").into();
+                let Ok(mut spectators) = spectators.lock() else {
+                    return;
+                };
+
+                match msg {
+                    SpectatorMessage::Map { .. } => {
+                        spectators.map = Some(data.clone());
+                        map = Some(msg);
+                    },
+                    SpectatorMessage::State(snapshot) => {
+                        spectators.state = Some(data.clone());
+
+                        // spectators joining late get the map without the walls destroyed so far
+                        if let Some(SpectatorMessage::Map { destructible_walls, .. }) = &mut map {
+                            let destroyed: Vec<_> = snapshot.events.iter()
+                                .filter_map(|event| match event {
+                                    MatchEvent::WallDestroyed { tile, .. } => Some(*tile),
+                                    _ => None
+                                })
+                                .collect();
+
+                            if !destroyed.is_empty() {
+                                destructible_walls.retain(|wall| !destroyed.contains(wall));
+                                spectators.map = Some(serde_json::to_string(&map).expect("Spectator messages are valid JSON").into());
+                            }
+                        }
+                    },
+                    SpectatorMessage::GameOver { .. } => {}
+                }
+                spectators.send(&data);
+            }
+
+            // closing the queues lets the spectators' threads end once they have written everything
+            let subscribed = match spectators.lock() {
+                Ok(mut spectators) => mem::take(&mut spectators.spectators),
+                Err(_) => return,
+            };
+            for Spectator { messages, writer } in subscribed {
+                drop(messages);
+                let _ = writer.join();
+            }
+        });
+
+        Ok(Broadcast { addr, stopped, listener: Some(listener), messages: Some(messages), writer: Some(writer) })
+    }
+
+    /// The address spectators connect to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Queues a message for every spectator.
+    pub fn send(&self, msg: SpectatorMessage) {
+        if let Some(messages) = &self.messages {
+            let _ = messages.send(msg);
+        }
+    }
+}
+
+impl Drop for Broadcast {
+    fn drop(&mut self) {
+        // `accept` only returns on a new connection, so the listener is woken up by connecting to it
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(listener) = self.listener.take() {
+            let mut addr = self.addr;
+            match addr {
+                SocketAddr::V4(_) if addr.ip().is_unspecified() => addr.set_ip(Ipv4Addr::LOCALHOST.into()),
+                SocketAddr::V6(_) if addr.ip().is_unspecified() => addr.set_ip(Ipv6Addr::LOCALHOST.into()),
+                _ => {}
+            }
+
+            match TcpStream::connect_timeout(&addr, SPECTATOR_TIMEOUT) {
+                Ok(_) => {
+                    let _ = listener.join();
+                },
+                Err(err) => warn!("Failed to stop listening for spectators - {err}"),
+            }
+        }
+
+        // closing the channel stops the writer once it has sent everything
+        self.messages = None;
+
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Reads a spectator's request and either serves the viewer or subscribes it to the stream.
+fn accept(mut stream: TcpStream, spectators: &Mutex<Spectators>) -> io::Result<()> {
+    stream.set_read_timeout(Some(SPECTATOR_TIMEOUT))?;
+    stream.set_write_timeout(Some(SPECTATOR_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+
+    // the headers aren't needed but must be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    match path {
+        "/" | "/index.html" => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{VIEWER}",
+                VIEWER.len()
+            )?;
+            stream.flush()
+        },
+        "/stream" => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\n\r\n"
+            )?;
+
+            stream.flush()?;
+
+            let mut spectators = spectators.lock()
+                .map_err(|_| io::Error::other("Broadcast stopped"))?;
+            let (messages, recv_message) = mpsc::sync_channel::<Arc<str>>(SPECTATOR_BACKLOG);
+            for data in spectators.map.iter().chain(&spectators.state) {
+                let _ = messages.try_send(data.clone());
+            }
+
+            let writer = thread::spawn(move || {
+                for data in recv_message {
+                    if write!(stream, "data: {data}\n\n").and_then(|_| stream.flush()).is_err() {
+                        return;
+                    }
+                }
+            });
+            spectators.spectators.push(Spectator { messages, writer });
+
+            Ok(())
+        },
+        _ => {
+            write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+            Err(io::Error::new(ErrorKind::NotFound, format!("Unknown path ({path})")))
+        }
+    }
+}
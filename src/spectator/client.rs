@@ -0,0 +1,372 @@
+//! Mirrors a match streamed by another engine's `BroadcastPlugin`.
+//!
+//! The engine runs without authority (see `engine::Authority`), the map, tanks,
+//! bullets and pickups are rebuilt from the host's messages instead of being simulated.
+
+use std::{io::{self, BufRead, BufReader, ErrorKind, Write}, net::TcpStream, sync::{mpsc::{self, Receiver, TryRecvError}, Mutex}, thread};
+
+use bevy::{
+    app::{Plugin, Update},
+    asset::{AssetServer, Assets},
+    color::LinearRgba,
+    log::{info, warn},
+    math::{Quat, Vec2},
+    prelude::{Commands, Entity, GlobalTransform, IntoSystemConfigs, Local, Mesh, Query, Res, ResMut, Resource, Transform, With, Without},
+    sprite::SpriteBundle
+};
+
+use crate::{
+    engine::{
+        map::{create_camera, create_minimal_walls, create_walls, terrain::{create_minimal_terrain, create_terrain, Destructible}, Coord, CurrentMap, Map, WALL_SIZE},
+        pickup::{create_minimal_pickups, create_pickups, Pickup, PickupKind},
+        tank::{bullet::{Bullet, BULLET_HEIGHT}, gen::{create_minimal_tank, create_tank, GunState, Tank, Turret}, material::TankMaterial},
+        Authority
+    },
+    player::{fault::ConnectionPolicy, PlayerID}
+};
+
+use super::{MatchEvent, PickupState, Snapshot, SpectatorMessage, TankState};
+
+/// The messages received from the host.
+#[derive(Resource)]
+pub struct HostStream(Mutex<Receiver<SpectatorMessage>>);
+
+/// The latest state received from the host, `None` until the first tick.
+#[derive(Resource, Default)]
+pub struct HostSnapshot(pub Option<Snapshot>);
+
+/// The components of a tank updated from the host, for use in queries.
+type MirroredTank = (
+    Entity,
+    &'static Tank,
+    &'static mut Transform,
+    Option<&'static PlayerID<0>>,
+    Option<&'static PlayerID<1>>
+);
+
+/// The components of a bullet updated from the host, for use in queries.
+type MirroredBullet = (Entity, &'static mut Transform, &'static mut Bullet);
+
+/// The components identifying the player of a tank, for use in queries.
+type TankPlayer = (Entity, Option<&'static PlayerID<0>>, Option<&'static PlayerID<1>>);
+
+/// Shows the match broadcast by another engine at the given address (e.g. `127.0.0.1:7000`).
+///
+/// # Fields
+/// - `0`: The address given to the host's `-broadcast`.
+/// - `1`: Whether the entities are rendered, must match the `EnginePlugin`.
+///
+/// # Panics
+/// Panics if the host can't be reached, connecting is retried according to the `ConnectionPolicy`.
+pub struct SpectatorPlugin(pub String, pub bool);
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        let policy = app.world()
+            .get_resource::<ConnectionPolicy>()
+            .cloned()
+            .unwrap_or_default();
+
+        let reader = policy.retry(|| subscribe(self.0.as_str()))
+            .unwrap_or_else(|err| panic!("Unable to spectate {} ({err})", self.0));
+
+        let (send_msg, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let Some(data) = line.strip_prefix("data: ") else {
+                    continue;
+                };
+
+                match serde_json::from_str(data) {
+                    Ok(msg) => {
+                        if send_msg.send(msg).is_err() {
+                            break;
+                        }
+                    },
+                    Err(err) => warn!("Invalid message from the host ({err})"),
+                }
+            }
+        });
+
+        app.insert_resource(Authority::Spectator)
+            .insert_resource(HostStream(Mutex::new(messages)))
+            .init_resource::<HostSnapshot>();
+
+        match self.1 {
+            true => {
+                app.add_systems(Update, (receive_messages::<true>, sync_tanks::<true>, sync_bullets::<true>, sync_pickups::<true>).chain());
+            },
+            false => {
+                app.add_systems(Update, (receive_messages::<false>, sync_tanks::<false>, sync_bullets::<false>, sync_pickups::<false>).chain());
+            }
+        }
+    }
+}
+
+/// Connects to a host and subscribes to its stream.
+///
+/// # Returns
+/// The stream, positioned after the HTTP headers.
+fn subscribe(addr: &str) -> io::Result<BufReader<TcpStream>> {
+    let addr = addr.strip_prefix("http://").unwrap_or(addr);
+
+    let mut stream = TcpStream::connect(addr)?;
+    write!(stream, "GET /stream HTTP/1.1\r\nHost: {addr}\r\nAccept: text/event-stream\r\n\r\n")?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(io::Error::new(ErrorKind::InvalidData, format!("Not a broadcast ({})", status.trim())));
+    }
+
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    Ok(reader)
+}
+
+/// Builds the map, removes the walls destroyed on the host and keeps the latest state received from it.
+#[allow(clippy::too_many_arguments)]
+pub fn receive_messages<const RENDER: bool>(
+    mut commands: Commands,
+    host: Res<HostStream>,
+    mut snapshot: ResMut<HostSnapshot>,
+    mut disconnected: Local<bool>,
+
+    mut current_map: ResMut<CurrentMap>,
+    mut maps: ResMut<Assets<Map>>,
+    asset_server: Res<AssetServer>,
+
+    wall_query: Query<(Entity, &Transform), With<Destructible>>,
+) {
+    let Ok(messages) = host.0.lock() else {
+        return;
+    };
+
+    loop {
+        let msg = match messages.try_recv() {
+            Ok(msg) => msg,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                if !*disconnected {
+                    warn!("Lost the connection to the host");
+                    *disconnected = true;
+                }
+                return;
+            },
+        };
+
+        match msg {
+            SpectatorMessage::Map { dim, tile_size: _, walls, destructible_walls, low_walls, water, holes } => {
+                let map = Map { dim, walls, destructible_walls, low_walls, water, holes, ..Default::default() };
+
+                match RENDER {
+                    true => {
+                        create_walls(&map.walls, &mut commands, &asset_server);
+                        create_terrain(&map, &mut commands, &asset_server);
+                        create_camera(dim, &mut commands);
+                    },
+                    false => {
+                        create_minimal_walls(&map.walls, &mut commands);
+                        create_minimal_terrain(&map, &mut commands);
+                    },
+                }
+
+                *current_map = CurrentMap::Handle(maps.add(map));
+            },
+            SpectatorMessage::State(state) => {
+                for event in &state.events {
+                    let MatchEvent::WallDestroyed { tile, .. } = event else {
+                        continue;
+                    };
+                    let pos = Vec2::new(tile.0 as f32, tile.1 as f32) * WALL_SIZE;
+
+                    wall_query.iter()
+                        .filter(|(_, transform)| transform.translation.truncate() == pos)
+                        .for_each(|(entity, _)| commands.entity(entity).despawn());
+                }
+
+                snapshot.0 = Some(state);
+            },
+            SpectatorMessage::GameOver { winner: Some(winner) } => info!("Player {winner} wins"),
+            SpectatorMessage::GameOver { winner: None } => info!("Draw"),
+        }
+    }
+}
+
+/// Moves the tanks to where the host has them, spawning and despawning them as needed.
+pub fn sync_tanks<const RENDER: bool>(
+    mut commands: Commands,
+    snapshot: Res<HostSnapshot>,
+
+    mut tank_query: Query<MirroredTank, Without<Turret>>,
+    mut turret_query: Query<(&mut Transform, &mut Turret), Without<Tank>>,
+
+    asset_server: Res<AssetServer>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut materials: Option<ResMut<Assets<TankMaterial>>>,
+) {
+    let Some(snapshot) = &snapshot.0 else {
+        return;
+    };
+
+    let mut missing: Vec<&TankState> = snapshot.tanks.iter().collect();
+
+    for (entity, tank, mut transform, p1, p2) in &mut tank_query {
+        let Some(player) = player_id(p1, p2) else {
+            continue;
+        };
+
+        let Some(i) = missing.iter().position(|state| state.player == player) else {
+            commands.entity(tank.turret).despawn();
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let state = missing.swap_remove(i);
+
+        transform.translation = state.pos.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(state.rot);
+
+        if let Ok((mut turret_transform, mut turret)) = turret_query.get_mut(tank.turret) {
+            turret_transform.rotation = Quat::from_rotation_z(state.turret_rot - state.rot);
+
+            match (state.reloading, &turret.0) {
+                (true, GunState::Ready) => turret.0 = GunState::reload(),
+                (false, GunState::Reload(_)) => turret.0 = GunState::Ready,
+                _ => {}
+            }
+        }
+    }
+
+    for state in missing {
+        let tank = match (RENDER, meshes.as_mut(), materials.as_mut()) {
+            (true, Some(meshes), Some(materials)) => create_tank(
+                state.pos.x,
+                state.pos.y,
+                state.player as u8,
+                match state.player {
+                    0 => LinearRgba::new(1., 0., 0., 1.),
+                    _ => LinearRgba::new(0., 0., 1., 1.),
+                },
+                &mut commands,
+                meshes,
+                materials,
+                &asset_server
+            ),
+            _ => create_minimal_tank(state.pos.x, state.pos.y, state.player as u8, &mut commands),
+        };
+
+        match state.player {
+            0 => commands.entity(tank).insert(PlayerID::<0>),
+            _ => commands.entity(tank).insert(PlayerID::<1>),
+        };
+    }
+}
+
+/// Moves the bullets to where the host has them, the host doesn't identify bullets so they are matched in order.
+pub fn sync_bullets<const RENDER: bool>(
+    mut commands: Commands,
+    snapshot: Res<HostSnapshot>,
+
+    mut bullet_query: Query<MirroredBullet, (Without<Tank>, Without<Turret>)>,
+    tank_query: Query<TankPlayer, With<Tank>>,
+
+    asset_server: Res<AssetServer>,
+) {
+    let Some(snapshot) = &snapshot.0 else {
+        return;
+    };
+
+    let source = |player: Option<u32>| tank_query.iter()
+        .find(|(_, p1, p2)| player.is_some() && player_id(*p1, *p2) == player)
+        .map(|(entity, _, _)| entity)
+        .unwrap_or(Entity::PLACEHOLDER);
+
+    let mut states = snapshot.bullets.iter();
+
+    for (entity, mut transform, mut bullet) in &mut bullet_query {
+        let Some(state) = states.next() else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        transform.translation = state.pos.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(state.rot);
+        bullet.0 = source(state.player);
+    }
+
+    for state in states {
+        let transform = Transform {
+            translation: state.pos.extend(BULLET_HEIGHT),
+            rotation: Quat::from_rotation_z(state.rot),
+            ..Default::default()
+        };
+
+        match RENDER {
+            true => commands.spawn((
+                Bullet(source(state.player)),
+                SpriteBundle {
+                    transform,
+                    texture: asset_server.load("textures\\tanks\\bullet.png"),
+                    ..Default::default()
+                }
+            )),
+            false => commands.spawn((
+                Bullet(source(state.player)),
+                transform,
+                GlobalTransform::default()
+            )),
+        };
+    }
+}
+
+/// Shows the pickups the host has, they don't move so they are respawned when they change.
+pub fn sync_pickups<const RENDER: bool>(
+    mut commands: Commands,
+    snapshot: Res<HostSnapshot>,
+
+    pickup_query: Query<(Entity, &Pickup, &Transform)>,
+) {
+    let Some(snapshot) = &snapshot.0 else {
+        return;
+    };
+
+    let mut missing: Vec<&PickupState> = snapshot.pickups.iter().collect();
+
+    for (entity, Pickup(kind), transform) in &pickup_query {
+        match missing.iter().position(|state| state.kind == *kind && state.pos == transform.translation.truncate()) {
+            Some(i) => {
+                missing.swap_remove(i);
+            },
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    let pickups: Vec<(Coord, PickupKind)> = missing.into_iter()
+        .map(|state| {
+            let tile = (state.pos / WALL_SIZE).round();
+            ((tile.x as usize, tile.y as usize), state.kind)
+        })
+        .collect();
+
+    match RENDER {
+        true => create_pickups(&pickups, &mut commands),
+        false => create_minimal_pickups(&pickups, &mut commands),
+    }
+}
+
+fn player_id(p1: Option<&PlayerID<0>>, p2: Option<&PlayerID<1>>) -> Option<u32> {
+    match (p1, p2) {
+        (Some(_), _) => Some(0),
+        (_, Some(_)) => Some(1),
+        _ => None
+    }
+}
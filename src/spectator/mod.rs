@@ -0,0 +1,320 @@
+//! This module lets other programs watch a match. `BroadcastPlugin` streams the
+//! authoritative state of the game every tick, so a browser page or another
+//! engine instance can spectate a headless match with `client::SpectatorPlugin`.
+//!
+//! Every message is a `SpectatorMessage` serialized as JSON. Spectators first
+//! receive the `map` and the latest `state`, then one `state` per tick and a
+//! `game_over` at the end.
+
+use std::collections::HashMap;
+
+use bevy::{
+    app::{Plugin, PostUpdate},
+    asset::Assets,
+    math::Vec2,
+    prelude::{in_state, Entity, EventReader, GlobalTransform, IntoSystemConfigs, Local, OnEnter, Query, Res, Transform}
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{
+        game_time::DeltaTime,
+        map::{gen_state::Step, terrain::TankFell, Coord, CurrentMap, Map, WALL_SIZE},
+        pickup::{Pickup, PickupCollected, PickupKind},
+        tank::{bullet::{Bullet, NewBullet, TankHit, WallDestroyed}, gen::{GunState, Tank, Turret}, instruction::get_rotation_z}
+    },
+    player::PlayerID
+};
+
+use broadcast::Broadcast;
+
+pub mod broadcast;
+pub mod client;
+
+/// A message streamed to spectators.
+///
+/// # Variants
+/// - `Map`: The map being played, sent first.
+/// - `State`: The state of the game after a tick.
+/// - `GameOver`: The id of the winner, `None` for a draw.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorMessage {
+    Map {
+        dim: (usize, usize),
+        tile_size: f32,
+        walls: Vec<Coord>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        destructible_walls: Vec<Coord>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        low_walls: Vec<Coord>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        water: Vec<Coord>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        holes: Vec<Coord>
+    },
+    State(Snapshot),
+    GameOver {
+        winner: Option<u32>
+    }
+}
+
+/// The state of the game after a tick.
+///
+/// # Fields
+/// - `tick`: The number of ticks since the map was generated.
+/// - `time`: The simulated time since the map was generated, in seconds.
+/// - `tanks`: The tanks still in the game.
+/// - `bullets`: The bullets in flight.
+/// - `pickups`: The pickups lying on the map.
+/// - `events`: What happened during the tick.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub time: f32,
+    pub tanks: Vec<TankState>,
+    pub bullets: Vec<BulletState>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pickups: Vec<PickupState>,
+    pub events: Vec<MatchEvent>
+}
+
+/// A tank as seen by spectators, positions are in world units and rotations in radians.
+///
+/// # Fields
+/// - `player`: The id of the player controlling the tank.
+/// - `pos`: The tank's position in the game world.
+/// - `rot`: The rotation of the tank's hull.
+/// - `turret_rot`: The rotation of the turret in the game world, not relative to the hull.
+/// - `reloading`: Whether the gun is reloading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TankState {
+    pub player: u32,
+    pub pos: Vec2,
+    pub rot: f32,
+    pub turret_rot: f32,
+    pub reloading: bool
+}
+
+/// A bullet as seen by spectators.
+///
+/// # Fields
+/// - `player`: The id of the player who fired it, `None` if its tank has been destroyed.
+/// - `pos`: The bullet's position in the game world.
+/// - `rot`: The direction the bullet travels in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulletState {
+    pub player: Option<u32>,
+    pub pos: Vec2,
+    pub rot: f32
+}
+
+/// A pickup as seen by spectators.
+///
+/// # Fields
+/// - `kind`: The power-up it gives.
+/// - `pos`: The pickup's position in the game world.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickupState {
+    pub kind: PickupKind,
+    pub pos: Vec2
+}
+
+/// Something that happened during a tick.
+///
+/// # Variants
+/// - `Shot`: A player fired its gun.
+/// - `TankHit`: A bullet fired by `source` destroyed the tank of `target`.
+/// - `TankFell`: The tank of `player` drove into a hole.
+/// - `WallDestroyed`: A bullet fired by `source` destroyed the destructible wall on `tile`.
+/// - `PickupCollected`: The tank of `player` drove over a pickup giving `kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MatchEvent {
+    Shot {
+        player: u32
+    },
+    TankHit {
+        source: Option<u32>,
+        target: Option<u32>
+    },
+    TankFell {
+        player: Option<u32>
+    },
+    WallDestroyed {
+        source: Option<u32>,
+        tile: Coord
+    },
+    PickupCollected {
+        player: Option<u32>,
+        kind: PickupKind
+    }
+}
+
+/// Streams the game to spectators connecting to the given address (e.g. `127.0.0.1:7000`).
+///
+/// # Panics
+/// Panics if the address can't be bound.
+pub struct BroadcastPlugin(pub String);
+
+impl Plugin for BroadcastPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        let broadcast = Broadcast::bind(self.0.as_str())
+            .unwrap_or_else(|err| panic!("Unable to broadcast on {} ({err})", self.0));
+
+        app.insert_resource(broadcast)
+            .add_systems(OnEnter(Step::Finished), broadcast_map)
+            .add_systems(PostUpdate, broadcast_state.run_if(in_state(Step::Finished)));
+    }
+}
+
+/// Sends the map once it has been generated.
+pub fn broadcast_map(
+    broadcast: Res<Broadcast>,
+
+    current_map: Res<CurrentMap>,
+    maps: Res<Assets<Map>>
+) {
+    let CurrentMap::Handle(handle) = current_map.as_ref() else {
+        return;
+    };
+    let Some(map) = maps.get(handle) else {
+        return;
+    };
+
+    broadcast.send(SpectatorMessage::Map {
+        dim: map.dim,
+        tile_size: WALL_SIZE,
+        walls: map.walls.clone(),
+        destructible_walls: map.destructible_walls.clone(),
+        low_walls: map.low_walls.clone(),
+        water: map.water.clone(),
+        holes: map.holes.clone()
+    });
+}
+
+/// The components identifying a tank and its player, for use in queries.
+type TankPlayer = (
+    Entity,
+    &'static GlobalTransform,
+    &'static Tank,
+    Option<&'static PlayerID<0>>,
+    Option<&'static PlayerID<1>>
+);
+
+/// Sends the state of the game after every tick, then the winner once a side has no tank left.
+///
+/// Tanks are remembered by entity so the events of a tick can name a tank destroyed during it.
+#[allow(clippy::too_many_arguments)]
+pub fn broadcast_state(
+    broadcast: Res<Broadcast>,
+    delta_time: Res<DeltaTime>,
+
+    mut snapshot: Local<Snapshot>,
+    mut players: Local<HashMap<Entity, u32>>,
+    mut game_over: Local<bool>,
+
+    tank_query: Query<TankPlayer>,
+    turret_query: Query<(&GlobalTransform, &Turret)>,
+    bullet_query: Query<(&Bullet, &Transform)>,
+    pickup_query: Query<(&Pickup, &Transform)>,
+
+    mut new_bullets: EventReader<NewBullet>,
+    mut tank_hits: EventReader<TankHit>,
+    mut tanks_fell: EventReader<TankFell>,
+    mut walls_destroyed: EventReader<WallDestroyed>,
+    mut pickups_collected: EventReader<PickupCollected>,
+) {
+    if *game_over {
+        return;
+    }
+
+    for (entity, _, _, p1, p2) in &tank_query {
+        match (p1, p2) {
+            (Some(_), _) => players.insert(entity, 0),
+            (_, Some(_)) => players.insert(entity, 1),
+            _ => None
+        };
+    }
+
+    let events = new_bullets.read()
+        .filter_map(|bullet| players.get(&bullet.source))
+        .map(|player| MatchEvent::Shot { player: *player })
+        .chain(
+            tank_hits.read()
+                .map(|hit| MatchEvent::TankHit {
+                    source: players.get(&hit.source).copied(),
+                    target: players.get(&hit.target).copied()
+                })
+        )
+        .chain(
+            tanks_fell.read()
+                .map(|fell| MatchEvent::TankFell { player: players.get(&fell.0).copied() })
+        )
+        .chain(
+            walls_destroyed.read()
+                .map(|wall| MatchEvent::WallDestroyed {
+                    source: players.get(&wall.source).copied(),
+                    tile: wall.tile
+                })
+        )
+        .chain(
+            pickups_collected.read()
+                .map(|pickup| MatchEvent::PickupCollected {
+                    player: players.get(&pickup.tank).copied(),
+                    kind: pickup.kind
+                })
+        )
+        .collect();
+
+    let tanks: Vec<TankState> = tank_query.iter()
+        .filter_map(|(entity, transform, tank, _, _)| {
+            let player = *players.get(&entity)?;
+            let (turret_transform, turret) = turret_query.get(tank.turret).ok()?;
+            let pos = transform.translation();
+
+            Some(TankState {
+                player,
+                pos: Vec2::new(pos.x, pos.y),
+                rot: get_rotation_z(transform.up().truncate()),
+                turret_rot: get_rotation_z(turret_transform.up().truncate()),
+                reloading: matches!(turret.0, GunState::Reload(_))
+            })
+        })
+        .collect();
+
+    let bullets = bullet_query.iter()
+        .map(|(bullet, transform)| BulletState {
+            player: players.get(&bullet.0).copied(),
+            pos: transform.translation.truncate(),
+            rot: get_rotation_z(transform.up().truncate())
+        })
+        .collect();
+
+    let pickups = pickup_query.iter()
+        .map(|(Pickup(kind), transform)| PickupState {
+            kind: *kind,
+            pos: transform.translation.truncate()
+        })
+        .collect();
+
+    snapshot.tick += 1;
+    snapshot.time += delta_time.0;
+    snapshot.tanks = tanks;
+    snapshot.bullets = bullets;
+    snapshot.pickups = pickups;
+    snapshot.events = events;
+
+    broadcast.send(SpectatorMessage::State(snapshot.clone()));
+
+    let alive = |player: u32| snapshot.tanks.iter().any(|tank| tank.player == player);
+    let winner = match (alive(0), alive(1)) {
+        (true, true) => return,
+        (true, false) => Some(0),
+        (false, true) => Some(1),
+        (false, false) => None,
+    };
+
+    broadcast.send(SpectatorMessage::GameOver { winner });
+    *game_over = true;
+}
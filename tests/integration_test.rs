@@ -1,4 +1,8 @@
-use std::{process::{Child, Command}, sync::{Arc, RwLock}, thread, time::Duration};
+use std::{collections::HashSet, f32::consts::PI, io::{BufRead, BufReader, Write}, process::{Child, Command}, sync::{Arc, RwLock}, thread, time::Duration};
+
+use bevy::{app::{App, PluginsState, Update}, asset::{AssetEvent, Assets}, core_pipeline::core_2d::Camera2dBundle, ecs::event::Events, input::{gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType}, mouse::MouseButton, Axis, ButtonInput}, math::{Quat, Vec2}, prelude::{GlobalTransform, Image, IntoSystemConfigs, KeyCode, Transform, With}, render::camera::{camera_system, ManualTextureViews, OrthographicProjection}, window::{PrimaryWindow, Window, WindowCreated, WindowResized, WindowResolution, WindowScaleFactorChanged}};
+use game::{editor::{EditorTool, MapEditor}, engine::{game_time::DeltaTime, map::{fairness::Fairness, terrain::{WallHealth, DEFAULT_WALL_HIT_POINTS, WATER_SPEED_FACTOR}, import::{import_image, Palette, TileKind}, pick_spawn_points, procgen::MapGen, validate::{validate_file, MapError}, CurrentMap, Map, SpawnPolicy, Wall, WALL_SIZE}, pickup::{random_pickup_tile, Pickup, PickupKind, PowerUps, RandomPickup, EXTRA_AMMO, FAST_RELOAD_FACTOR, MAX_RANDOM_PICKUPS, PICKUP_DURATION, SPEED_BOOST_FACTOR}, tank::{bullet::Bullet, gen::{GunState, Tank, Turret, RELOAD_TIME}, instruction::{ActionPolicy, Instruction, MAX_ACTIONS_PER_TICK, TANK_MOVE_SPEED, TURRET_ROTATION_SPEED}, vision::{VisionHit, NUM_OF_HULL_RAY, NUM_OF_TURRET_RAY}, TankForfeited}}, gym::{GymEnv, RewardConfig}, spectator::{broadcast::Broadcast, client::SpectatorPlugin, BroadcastPlugin, MatchEvent, SpectatorMessage}, player::{builtin::{bots::WallFollower, TankBrain}, gamepad::{gamepad_input, PlayerGamepad, GAMEPAD_DEAD_ZONE}, key_board::{aim_action, PlayerKeyBind, NUM_OF_BINDINGS}, mouse::mouse_input, observation::PlayerData, rebind::{rebind_keys, RebindScreen, REBIND_KEY, SKIP_KEY}, fault::{ConnectionPolicy, Fault}, handshake::{BotInfo, GameInfo, PlayerInfo, PROTOCOL_VERSION}, network::{InputDelay, InputFrame, JoinInfo, NetworkPlayer}, remote::process::split_command, PlayerID}, Action, EnginePlugin, Match, MatchOutcome, PlayerController, PlayerControllerPlugin};
+use rand::{rngs::StdRng, SeedableRng};
 
 
 fn cmd(inst: &str) -> String {
@@ -58,6 +62,30 @@ fn start_server(command: &str) -> Child {
     }
 }
 
+/// Shoots when the middle turret ray sees the enemy, spins the turret to find it otherwise.
+fn hit_action(turret_vision: &[Option<VisionHit>]) -> Action {
+    match turret_vision[NUM_OF_TURRET_RAY / 2] {
+        Some(VisionHit::Enemy(_)) => Action::Shoot,
+        _ => Action::SpinLeft
+    }
+}
+
+/// Plays `player` with `hit_action` until the match is over, the other player isn't moved.
+fn play_until_hit(game_match: &mut Match, player: u32) {
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        let action = game_match.observe(player)
+            .map(|data| hit_action(&data.turret_vision))
+            .unwrap_or(Action::SpinLeft);
+
+        game_match.act(player, action);
+        game_match.step();
+    }
+}
+
 #[test]
 fn render_test() {
     has_python();
@@ -159,4 +187,1487 @@ fn headless_test() {
     let last_line = game_output.lines().rev().find(|line| !line.is_empty()).unwrap_or("");
 
     assert_eq!(last_line, "1", "Expected last line to be '1' but got '{}'", last_line);
-}
\ No newline at end of file
+}
+#[test]
+fn embedded_test() {
+    let mut game_match = Match::new(Some("test_1.ron".to_string()), 1. / 60.);
+
+    play_until_hit(&mut game_match, 0);
+
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+}
+
+#[test]
+fn action_policy_test() {
+    for actions_per_tick in [1, MAX_ACTIONS_PER_TICK] {
+        let mut app = App::new();
+        app.insert_resource(ActionPolicy { actions_per_tick });
+
+        let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+        let before = game_match.observe(0).unwrap();
+
+        game_match.act(0, Action::SpinLeft);
+        game_match.act(0, Action::RotateLeft);
+        game_match.step();
+
+        let after = game_match.observe(0).unwrap();
+        assert_ne!(before.turret_rot, after.turret_rot);
+        assert_eq!(before.rot == after.rot, actions_per_tick == 1);
+    }
+}
+
+#[test]
+fn broadcast_test() {
+    let mut app = App::new();
+    app.add_plugins(BroadcastPlugin("127.0.0.1:0".to_string()));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+    let addr = game_match.app_mut().world().resource::<Broadcast>().local_addr();
+
+    // the map was sent before the spectator joined, it must be sent again with the latest state
+    let spectator = thread::spawn(move || {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /stream HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+
+        BufReader::new(stream).lines()
+            .map(Result::unwrap)
+            .filter_map(|line| line.strip_prefix("data: ").map(|data| serde_json::from_str::<SpectatorMessage>(data).unwrap()))
+            .take_while(|msg| !matches!(msg, SpectatorMessage::GameOver { .. }))
+            .collect::<Vec<_>>()
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    play_until_hit(&mut game_match, 0);
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+    drop(game_match);
+
+    let messages = spectator.join().unwrap();
+    assert!(matches!(messages[0], SpectatorMessage::Map { .. }));
+
+    let snapshots: Vec<_> = messages.iter()
+        .filter_map(|msg| match msg {
+            SpectatorMessage::State(snapshot) => Some(snapshot),
+            _ => None
+        })
+        .collect();
+    assert_eq!(snapshots[0].tanks.len(), 2);
+
+    let events: Vec<_> = snapshots.iter().flat_map(|snapshot| snapshot.events.clone()).collect();
+    assert!(events.contains(&MatchEvent::Shot { player: 0 }));
+    assert_eq!(events.last(), Some(&MatchEvent::TankHit { source: Some(0), target: Some(1) }));
+}
+
+#[test]
+fn broadcast_lag_test() {
+    let broadcast = Broadcast::bind("127.0.0.1:0").unwrap();
+    let addr = broadcast.local_addr();
+    let subscribe = || {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /stream HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+        stream
+    };
+    // the last message of a dropped spectator may be cut
+    let read_messages = |stream| {
+        BufReader::new(stream).lines()
+            .map_while(Result::ok)
+            .filter_map(|line| line.strip_prefix("data: ").and_then(|data| serde_json::from_str::<SpectatorMessage>(data).ok()))
+            .collect::<Vec<_>>()
+    };
+
+    // doesn't read until the broadcast is over
+    let stalled = subscribe();
+    let reader = subscribe();
+    let reader = thread::spawn(move || read_messages(reader));
+    thread::sleep(Duration::from_millis(200));
+
+    // far more than the socket buffers hold
+    let map = SpectatorMessage::Map {
+        dim: (100, 100),
+        tile_size: WALL_SIZE,
+        walls: (0..100).flat_map(|x| (0..100).map(move |y| (x, y))).collect(),
+        destructible_walls: Vec::new(),
+        low_walls: Vec::new(),
+        water: Vec::new(),
+        holes: Vec::new(),
+    };
+    for _ in 0..200 {
+        broadcast.send(map.clone());
+    }
+    broadcast.send(SpectatorMessage::GameOver { winner: Some(0) });
+    drop(broadcast);
+
+    // the other spectator got everything, the stalled one was dropped on the way
+    let messages = reader.join().unwrap();
+    assert_eq!(messages.len(), 201);
+    assert_eq!(messages.last(), Some(&SpectatorMessage::GameOver { winner: Some(0) }));
+
+    let messages = read_messages(stalled);
+    assert!(messages.len() < 200);
+    assert!(!messages.contains(&SpectatorMessage::GameOver { winner: Some(0) }));
+
+    // the port was released, late spectators aren't accepted into a finished broadcast
+    assert!(std::net::TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn spectate_test() {
+    let mut host_app = App::new();
+    host_app.add_plugins(BroadcastPlugin("127.0.0.1:0".to_string()));
+
+    let mut host = Match::from_app(host_app, Some("test_1.ron".to_string()), 1. / 60.);
+    let addr = host.app_mut().world().resource::<Broadcast>().local_addr();
+
+    let mut spectator = App::new();
+    spectator.add_plugins(EnginePlugin(false, CurrentMap::None, Some(1. / 60.)))
+        .add_plugins(SpectatorPlugin(addr.to_string(), false));
+    while spectator.plugins_state() == PluginsState::Adding {
+        thread::yield_now();
+    }
+    spectator.finish();
+    spectator.cleanup();
+
+    // waits until the spectator has caught up with the host
+    let sync = |spectator: &mut App, expected: usize| {
+        for _ in 0..500 {
+            spectator.update();
+
+            let world = spectator.world_mut();
+            let tanks: Vec<_> = world.query_filtered::<&GlobalTransform, With<Tank>>()
+                .iter(world)
+                .map(|transform| transform.translation().truncate())
+                .collect();
+            if tanks.len() == expected {
+                return tanks;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("The spectator didn't receive the match");
+    };
+
+    let tanks = sync(&mut spectator, 2);
+    assert!(tanks.contains(&host.observe(0).unwrap().pos));
+    assert!(spectator.world_mut().query::<&Wall>().iter(spectator.world()).count() > 0);
+
+    play_until_hit(&mut host, 0);
+    assert_eq!(host.outcome(), Some(MatchOutcome::Winner(0)));
+
+    let tanks = sync(&mut spectator, 1);
+    assert_eq!(tanks, [host.observe(0).unwrap().pos]);
+}
+
+#[test]
+fn gym_test() {
+    let mut env = GymEnv::new(
+        RewardConfig{ hit: 1., damage: -1., survival: 0.001 },
+        10_000,
+        1. / 60.
+    );
+
+    let first_obs = env.reset(7, Some("test_1.ron".to_string()));
+    let second_obs = env.reset(7, Some("test_1.ron".to_string()));
+    assert_eq!(
+        first_obs.iter().map(|obs| obs.as_ref().map(|obs| obs.pos)).collect::<Vec<_>>(),
+        second_obs.iter().map(|obs| obs.as_ref().map(|obs| obs.pos)).collect::<Vec<_>>(),
+        "The same seed should pick the same spawn points"
+    );
+
+    let mut obs = second_obs;
+    let mut total_reward = [0f32; 2];
+    loop {
+        let action = obs[0].as_ref()
+            .map(|data| hit_action(&data.turret_vision))
+            .unwrap_or(Action::SpinLeft);
+
+        let result = env.step([action, Action::Wait]);
+        total_reward[0] += result.reward[0];
+        total_reward[1] += result.reward[1];
+        obs = result.obs;
+
+        if result.done {
+            assert_eq!(result.info.outcome, Some(MatchOutcome::Winner(0)));
+            assert_eq!(result.info.hits, [1, 0]);
+            break;
+        }
+    }
+
+    assert!(total_reward[0] > 1.);
+    assert!(total_reward[1] < 0.);
+
+    // in test_2 one tank has a hole behind it, backing into it costs the damage penalty
+    env.reward = RewardConfig{ hit: 1., damage: -1., survival: 0. };
+    let obs = env.reset(7, Some("test_2.ron".to_string()));
+    let player = obs.iter().position(|obs| obs.as_ref().unwrap().pos == Vec2::splat(2. * WALL_SIZE)).unwrap();
+
+    let mut total_reward = [0f32; 2];
+    for _ in 0..1_000 {
+        let mut actions = [Action::Wait; 2];
+        actions[player] = Action::MoveBackward;
+
+        let result = env.step(actions);
+        total_reward[0] += result.reward[0];
+        total_reward[1] += result.reward[1];
+
+        if result.done {
+            assert_eq!(result.info.outcome, Some(MatchOutcome::Winner(1 - player as u32)));
+            assert_eq!(result.info.hits, [0, 0]);
+            break;
+        }
+    }
+    assert_eq!(total_reward[player], -1.);
+    assert_eq!(total_reward[1 - player], 0.);
+}
+
+#[test]
+fn editor_test() {
+    let path = std::env::temp_dir().join(format!("editor_test_{}.ron", std::process::id()));
+    let mut editor = MapEditor::open(&path).unwrap();
+    let border = editor.map.walls.len();
+
+    editor.begin_stroke();
+    assert!(editor.paint((5, 5)));
+    assert!(editor.paint((6, 5)));
+    assert!(!editor.paint((6, 5)));
+    editor.end_stroke();
+    assert_eq!(editor.map.walls.len(), border + 2);
+
+    editor.tool = EditorTool::SpawnPoint;
+    editor.begin_stroke();
+    assert!(editor.paint((6, 5)));
+    assert!(editor.paint((10, 10)));
+    editor.end_stroke();
+    assert_eq!(editor.map.walls.len(), border + 1);
+
+    assert!(editor.undo());
+    assert_eq!((editor.map.walls.len(), editor.map.spawn_points.len()), (border + 2, 0));
+    assert!(editor.undo());
+    assert_eq!(editor.map.walls.len(), border);
+    assert!(!editor.undo());
+    assert!(editor.redo());
+    assert!(editor.redo());
+    assert!(!editor.redo());
+
+    editor.save().unwrap();
+    let saved = MapEditor::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(saved.map.dim, editor.map.dim);
+    assert_eq!(saved.map.spawn_points, vec![(6, 5), (10, 10)]);
+    assert_eq!(saved.map.walls.len(), border + 1);
+    assert_eq!(editor.tile_at(Vec2::new(5. * 32. + 10., 5. * 32. - 10.)), Some((5, 5)));
+    assert_eq!(editor.tile_at(Vec2::new(-20., 0.)), None);
+}
+
+#[test]
+fn map_import_test() {
+    let path = std::env::temp_dir().join(format!("map_import_test_{}.bmp", std::process::id()));
+
+    // a wall on the first row, a spawn point in each corner below it and a blue tile
+    let image = image::RgbImage::from_fn(4, 3, |x, y| match (x, y) {
+        (_, 0) => image::Rgb([5, 5, 5]),
+        (0, 2) | (3, 2) => image::Rgb([250, 0, 0]),
+        (1, 1) => image::Rgb([0, 0, 255]),
+        _ => image::Rgb([255, 255, 255]),
+    });
+    image.save(&path).unwrap();
+
+    let imported = import_image(&path, &Palette::default()).unwrap();
+    assert_eq!(imported.map.dim, (4, 3));
+    assert_eq!(imported.map.walls, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+    assert_eq!(imported.map.spawn_points, vec![(0, 2), (3, 2)]);
+    assert_eq!(imported.unknown_colours, vec![([0, 0, 255], 1)]);
+
+    let palette: Palette = ron::from_str("(tolerance: 0., colours: [((0, 0, 255), SpawnPoint), ((5, 5, 5), Wall)])").unwrap();
+    assert_eq!(palette.tile([0, 0, 255]), Some(TileKind::SpawnPoint));
+
+    // the red spawn points are unknown without tolerance, leaving a single spawn point
+    let result = import_image(&path, &palette);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(result.unwrap_err().contains("1 distinct spawn points"));
+}
+
+#[test]
+fn map_validate_test() {
+    for map in ["map_1", "map_2", "map_3", "test_1", "test_2"] {
+        assert!(validate_file(format!("assets/maps/{map}.ron")).is_ok(), "{map} should be valid");
+    }
+
+    // the bottom right corner is walled off, the wall at (4, 0) and the spawn point at (0, 4) are out of bounds
+    let map = Map {
+        dim: (4, 4),
+        walls: vec![(2, 3), (3, 2), (1, 1), (4, 0)],
+        spawn_points: vec![(0, 0), (1, 1), (3, 3), (0, 4)],
+        ..Default::default()
+    };
+    assert_eq!(
+        map.validate(),
+        Err(vec![
+            MapError::WallOutOfBounds((4, 0)),
+            MapError::SpawnPointOutOfBounds((0, 4)),
+            MapError::SpawnPointOnWall((1, 1)),
+            MapError::Unreachable { from: (0, 0), to: (3, 3) },
+        ])
+    );
+
+    let map = Map { dim: (4, 4), spawn_points: vec![(1, 1), (1, 1)], ..Default::default() };
+    assert_eq!(map.validate(), Err(vec![MapError::TooFewSpawnPoints(1)]));
+}
+
+#[test]
+fn terrain_test() {
+    // maps from before the other kinds of tiles still load, and are saved the same way
+    let map: Map = ron::from_str("(dim: (2, 2), walls: [(0, 0)], spawn_points: [(1, 1)])").unwrap();
+    assert!(map.water.is_empty());
+    assert!(!map.to_ron().unwrap().contains("water"));
+
+    let map = Map { dim: (3, 3), walls: vec![(1, 1)], water: vec![(1, 1), (3, 0)], spawn_points: vec![(0, 0), (2, 2)], ..Default::default() };
+    assert_eq!(map.validate(), Err(vec![MapError::TileOutOfBounds((3, 0)), MapError::OverlappingTiles((1, 1))]));
+
+    // in test_2 one tank has water, a low wall then a wall in front of it and a hole behind it
+    let mut game_match = Match::new(Some("test_2.ron".to_string()), 1. / 60.);
+    game_match.step();
+
+    let player = (0..2).find(|player| game_match.observe(*player).unwrap().pos == Vec2::splat(2. * WALL_SIZE)).unwrap();
+    let data = game_match.observe(player).unwrap();
+    assert!(matches!(data.hull_vision[0], Some(VisionHit::Water(dist)) if (dist - WALL_SIZE / 2.).abs() < 1e-3));
+    assert!(matches!(data.turret_vision[NUM_OF_TURRET_RAY / 2], Some(VisionHit::Wall(dist)) if dist > 2. * WALL_SIZE));
+
+    let enemy = game_match.observe(1 - player).unwrap();
+    assert!(matches!(enemy.hull_vision[4], Some(VisionHit::DestructibleWall(_))));
+
+    // half speed once in the water
+    while game_match.observe(player).unwrap().pos.y < 2.5 * WALL_SIZE + 1. {
+        game_match.act(player, Action::MoveForward);
+        game_match.step();
+    }
+    let before = game_match.observe(player).unwrap().pos.y;
+    game_match.act(player, Action::MoveForward);
+    game_match.step();
+    let speed = (game_match.observe(player).unwrap().pos.y - before) * 60.;
+    assert!((speed - TANK_MOVE_SPEED * WATER_SPEED_FACTOR).abs() < 1., "{speed} should be half the speed of a tank");
+
+    // backing into the hole destroys the tank
+    for _ in 0..1_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.act(player, Action::MoveBackward);
+        game_match.step();
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(1 - player)));
+}
+
+#[test]
+fn wall_health_test() {
+    // in test_2 one tank has a destructible wall behind it
+    let mut game_match = Match::new(Some("test_2.ron".to_string()), 1. / 60.);
+    game_match.step();
+    let player = (0..2).find(|player| game_match.observe(*player).unwrap().pos == Vec2::splat(6. * WALL_SIZE)).unwrap();
+
+    for _ in 0..1_000 {
+        if matches!(game_match.observe(player).unwrap().turret_vision[NUM_OF_TURRET_RAY / 2], Some(VisionHit::DestructibleWall(_))) {
+            break;
+        }
+
+        game_match.act(player, Action::SpinLeft);
+        game_match.step();
+    }
+
+    let hit_points = |game_match: &mut Match| {
+        let world = game_match.app_mut().world_mut();
+        world.query::<&WallHealth>().iter(world).map(|health| health.hit_points).next()
+    };
+    assert_eq!(hit_points(&mut game_match), Some(DEFAULT_WALL_HIT_POINTS));
+
+    game_match.act(player, Action::Shoot);
+    for _ in 0..10 {
+        game_match.step();
+    }
+    assert_eq!(hit_points(&mut game_match), Some(DEFAULT_WALL_HIT_POINTS - 1));
+
+    for _ in 0..1_000 {
+        if !matches!(game_match.observe(player).unwrap().hull_vision[4], Some(VisionHit::DestructibleWall(_))) {
+            break;
+        }
+
+        game_match.act(player, Action::Shoot);
+        game_match.step();
+    }
+
+    // the wall is gone for the rays and the bullets
+    assert_eq!(hit_points(&mut game_match), None);
+    assert!(!matches!(game_match.observe(player).unwrap().turret_vision[NUM_OF_TURRET_RAY / 2], Some(VisionHit::DestructibleWall(_))));
+    assert_eq!(game_match.outcome(), None);
+}
+
+#[test]
+fn pickup_test() {
+    let map = Map {
+        dim: (3, 3),
+        walls: vec![(1, 1)],
+        spawn_points: vec![(0, 0), (2, 2)],
+        pickups: vec![((1, 1), PickupKind::Shield), ((0, 2), PickupKind::Repair)],
+        pickup_interval: Some(0.),
+        ..Default::default()
+    };
+    assert_eq!(map.validate(), Err(vec![MapError::MisplacedPickup((1, 1)), MapError::NoPickupInterval]));
+
+    let taken = HashSet::from([(0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2)]);
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(random_pickup_tile(&map, &taken, &mut rng), None);
+    assert_eq!(random_pickup_tile(&map, &HashSet::from([(0, 1)]), &mut rng).map(|tile| map.tile_kind(tile)), Some(TileKind::Floor));
+
+    // the column behind the walls can't be reached from the spawn points
+    let walled = Map { dim: (3, 3), walls: vec![(1, 0), (1, 1), (1, 2)], spawn_points: vec![(0, 0), (0, 2)], ..Default::default() };
+    assert_eq!(random_pickup_tile(&walled, &HashSet::new(), &mut rng), Some((0, 1)));
+
+    let mut power_ups = PowerUps::default();
+    power_ups.apply(PickupKind::Repair);
+    assert!(power_ups.is_protected());
+    power_ups.absorb_hit();
+    assert!(!power_ups.is_protected());
+
+    // in test_2 one tank has a speed boost in front of it
+    let mut game_match = Match::new(Some("test_2.ron".to_string()), 1. / 60.);
+    game_match.step();
+    let player = (0..2).find(|player| game_match.observe(*player).unwrap().pos == Vec2::splat(6. * WALL_SIZE)).unwrap();
+    let tank = game_match.tank(player).unwrap();
+
+    assert!(matches!(game_match.observe(player).unwrap().hull_vision[0], Some(VisionHit::Pickup(dist)) if dist < WALL_SIZE));
+    assert!(!matches!(game_match.observe(player).unwrap().turret_vision[NUM_OF_TURRET_RAY / 2], Some(VisionHit::Pickup(_))));
+
+    for _ in 0..1_000 {
+        if game_match.app_mut().world().get::<PowerUps>(tank).unwrap().speed_boost > 0. {
+            break;
+        }
+
+        game_match.act(player, Action::MoveForward);
+        game_match.step();
+    }
+    assert_eq!(game_match.app_mut().world().get::<PowerUps>(tank).unwrap().speed_boost, PICKUP_DURATION);
+
+    let before = game_match.observe(player).unwrap().pos.y;
+    game_match.act(player, Action::MoveForward);
+    game_match.step();
+    let speed = (game_match.observe(player).unwrap().pos.y - before) * 60.;
+    assert!((speed - TANK_MOVE_SPEED * SPEED_BOOST_FACTOR).abs() < 1., "{speed} should be the boosted speed of a tank");
+
+    let world = game_match.app_mut().world_mut();
+    assert_eq!(world.query::<&Pickup>().iter(world).count(), 0);
+
+    // the map's own pickups don't stop the random ones
+    let mut game_match = Match::new(Some("test_2.ron".to_string()), 1. / 60.);
+    let world = game_match.app_mut().world_mut();
+    let CurrentMap::Handle(handle) = world.resource::<CurrentMap>().clone() else {
+        panic!("The map is loaded");
+    };
+    world.resource_mut::<Assets<Map>>().get_mut(&handle).unwrap().pickup_interval = Some(0.1);
+    for x in 3..6 {
+        world.spawn((Pickup(PickupKind::Shield), Transform::from_xyz(x as f32 * WALL_SIZE, 7. * WALL_SIZE, 0.)));
+    }
+
+    for _ in 0..60 {
+        game_match.step();
+    }
+    let world = game_match.app_mut().world_mut();
+    assert_eq!(world.query::<&RandomPickup>().iter(world).count(), MAX_RANDOM_PICKUPS);
+    assert_eq!(world.query::<&Pickup>().iter(world).count(), MAX_RANDOM_PICKUPS + 4);
+}
+
+#[test]
+fn power_up_test() {
+    let mut game_match = Match::new(Some("test_1.ron".to_string()), 1. / 60.);
+    let tanks = [game_match.tank(0).unwrap(), game_match.tank(1).unwrap()];
+
+    let apply = |game_match: &mut Match, player: usize, kind: PickupKind| {
+        game_match.app_mut().world_mut().get_mut::<PowerUps>(tanks[player]).unwrap().apply(kind);
+    };
+    // the reload time of player 0's gun, `None` when it is loaded
+    let reload = |game_match: &mut Match| {
+        let world = game_match.app_mut().world();
+        let turret = world.get::<Tank>(tanks[0]).unwrap().turret;
+
+        match &world.get::<Turret>(turret).unwrap().0 {
+            GunState::Ready => None,
+            GunState::Reload(timer) => Some(timer.duration().as_secs_f32()),
+        }
+    };
+    // shoots as soon as the gun is loaded
+    let shoot = |game_match: &mut Match| {
+        for _ in 0..1_000 {
+            if reload(game_match).is_none() {
+                break;
+            }
+            game_match.step();
+        }
+        assert_eq!(reload(game_match), None);
+
+        game_match.act(0, Action::Shoot);
+        game_match.step();
+
+        reload(game_match)
+    };
+
+    // the enemy is on the same row, player 0 shoots at the wall above
+    let world = game_match.app_mut().world_mut();
+    let turret = world.get::<Tank>(tanks[0]).unwrap().turret;
+    world.get_mut::<Transform>(tanks[0]).unwrap().rotation = Quat::IDENTITY;
+    world.get_mut::<Transform>(turret).unwrap().rotation = Quat::IDENTITY;
+    game_match.step();
+
+    apply(&mut game_match, 0, PickupKind::ExtraAmmo);
+    for _ in 0..EXTRA_AMMO {
+        assert_eq!(shoot(&mut game_match), None);
+    }
+    assert_eq!(shoot(&mut game_match), Some(RELOAD_TIME));
+
+    apply(&mut game_match, 0, PickupKind::FastReload);
+    assert_eq!(shoot(&mut game_match), Some(RELOAD_TIME * FAST_RELOAD_FACTOR));
+
+    // timed power-ups run out after PICKUP_DURATION
+    for _ in 0..(PICKUP_DURATION * 60.) as usize + 1 {
+        game_match.step();
+    }
+    assert_eq!(game_match.app_mut().world().get::<PowerUps>(tanks[0]).unwrap().fast_reload, 0.);
+    assert_eq!(shoot(&mut game_match), Some(RELOAD_TIME));
+    assert_eq!(game_match.outcome(), None);
+
+    // a shielded tank survives a bullet, then dies to the next one once the shield is gone
+    apply(&mut game_match, 1, PickupKind::Shield);
+    for _ in 0..10_000 {
+        if reload(&mut game_match).is_none() && matches!(game_match.observe(0).unwrap().turret_vision[NUM_OF_TURRET_RAY / 2], Some(VisionHit::Enemy(_))) {
+            break;
+        }
+
+        game_match.act(0, Action::SpinLeft);
+        game_match.step();
+    }
+    shoot(&mut game_match);
+
+    let bullets = |game_match: &mut Match| {
+        let world = game_match.app_mut().world_mut();
+        world.query::<&Bullet>().iter(world).count()
+    };
+    // the bullet is spawned at the end of the tick it is shot
+    let mut flying = false;
+    for _ in 0..1_000 {
+        let count = bullets(&mut game_match);
+        if flying && count == 0 {
+            break;
+        }
+
+        flying |= count > 0;
+        game_match.step();
+    }
+    assert!(flying);
+    assert_eq!(bullets(&mut game_match), 0);
+    assert_eq!(game_match.outcome(), None);
+
+    game_match.app_mut().world_mut().get_mut::<PowerUps>(tanks[1]).unwrap().shield = 0.;
+    shoot(&mut game_match);
+    for _ in 0..1_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+        game_match.step();
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+}
+
+#[test]
+fn procgen_test() {
+    let mut rng = rand::thread_rng();
+
+    for generator in ["rooms", "caves", "arena"] {
+        for (dim, seed) in [("48x48", 0), ("48x48", 1), ("33x17", 2), ("16x64", 3)] {
+            let map_gen: MapGen = format!("gen:{generator}:{dim}:{seed}").parse().unwrap();
+            let map = map_gen.generate(&mut rng);
+            let mirror = |(x, y): (usize, usize)| (map.dim.0 - 1 - x, map.dim.1 - 1 - y);
+
+            assert_eq!(map.validate(), Ok(()), "{map_gen} should be valid");
+            assert_eq!(map.spawn_points.len(), 2);
+            assert_eq!(mirror(map.spawn_points[0]), map.spawn_points[1], "{map_gen} spawn points should mirror each other");
+            assert!(map.walls.iter().all(|wall| map.walls.contains(&mirror(*wall))), "{map_gen} should be symmetric");
+            assert_eq!(map_gen.generate(&mut rng).walls, map.walls, "{map_gen} should be the same every time");
+        }
+    }
+
+    assert!("gen:caves:48".parse::<MapGen>().is_err());
+    assert!("gen:mazes:48x48".parse::<MapGen>().is_err());
+    assert!(CurrentMap::from_name(Some("gen:rooms:8x8".to_string())).is_err());
+    assert_eq!(CurrentMap::from_name(Some("map_1.ron".to_string())), Ok(CurrentMap::AssetPath("map_1.ron".to_string())));
+
+    let mut game_match = Match::with_seed(Some("gen:arena:32x32".to_string()), 1. / 60., 5);
+    let (tank_1, tank_2) = (game_match.tank(0).unwrap(), game_match.tank(1).unwrap());
+    let world = game_match.app_mut().world();
+    let centre = world.get::<GlobalTransform>(tank_1).unwrap().translation() + world.get::<GlobalTransform>(tank_2).unwrap().translation();
+
+    assert_eq!(centre.truncate(), Vec2::splat(31. * WALL_SIZE));
+}
+
+#[test]
+fn fairness_test() {
+    let generated = MapGen { seed: Some(1), .."gen:arena:32x32".parse().unwrap() }.generate(&mut rand::thread_rng());
+    assert!(!Fairness::analyse(&generated).is_asymmetric());
+
+    // the spawn point in the bottom right corner hides behind walls
+    let map = Map {
+        dim: (12, 8),
+        walls: vec![(9, 5), (10, 5), (9, 6)],
+        spawn_points: vec![(1, 1), (10, 6)],
+        ..Default::default()
+    };
+    let fairness = Fairness::analyse(&map);
+    assert!(fairness.spawns[1].cover > fairness.spawns[0].cover);
+    assert!(fairness.spawns[1].exposure < fairness.spawns[0].exposure);
+    assert_eq!(fairness.pairs[0].path_distance, Some(16));
+    assert!(fairness.is_asymmetric());
+
+    let map = validate_file("assets/maps/map_1.ron").unwrap();
+    let fairness = Fairness::analyse(&map);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..10 {
+        let (spawn_1, spawn_2) = pick_spawn_points(&map, &mut rng, SpawnPolicy::Balanced);
+        let pair = fairness.pairs.iter()
+            .find(|pair| pair.spawns == (spawn_1, spawn_2) || pair.spawns == (spawn_2, spawn_1))
+            .unwrap();
+
+        assert!(pair.is_balanced(), "{pair:?} should be balanced");
+    }
+}
+
+#[test]
+#[should_panic(expected = "Conflicting key bindings")]
+fn key_conflict_test() {
+    App::new().add_plugins(PlayerControllerPlugin(PlayerController::<0>::wasd(), PlayerController::<1>::wasd()));
+}
+
+#[test]
+fn rebind_test() {
+    let mut app = App::new();
+    app.init_resource::<ButtonInput<KeyCode>>()
+        .insert_resource(PlayerKeyBind::<0>::from(&PlayerController::<0>::wasd()))
+        .add_systems(Update, rebind_keys::<0, 1>);
+
+    let mut press = |key: KeyCode| {
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.release_all();
+        keys.clear();
+
+        let keys = app.world().resource::<PlayerKeyBind<0>>().bindings().map(|(_, key)| key);
+        (app.world().contains_resource::<RebindScreen>(), keys)
+    };
+    // keys do nothing until the screen is opened
+    let (open, wasd) = press(KeyCode::KeyT);
+    assert!(!open);
+
+    assert_eq!(press(REBIND_KEY), (true, wasd));
+    let (open, keys) = press(KeyCode::KeyT);
+    assert!(open);
+    assert_eq!(keys[0], KeyCode::KeyT);
+
+    // keeps the backward key
+    assert_eq!(press(SKIP_KEY), (true, keys));
+
+    // KeyT is taken, turn left is still asked
+    assert_eq!(press(KeyCode::KeyT), (true, keys));
+    let (_, keys) = press(KeyCode::KeyY);
+    assert_eq!(keys[2], KeyCode::KeyY);
+
+    // closes after the last key
+    for _ in 3..NUM_OF_BINDINGS - 1 {
+        assert_eq!(press(SKIP_KEY), (true, keys));
+    }
+    assert_eq!(press(SKIP_KEY), (false, keys));
+
+    // closes without finishing
+    assert_eq!(press(REBIND_KEY), (true, keys));
+    assert_eq!(press(REBIND_KEY), (false, keys));
+}
+
+/// Runs a tick of a controller's systems and returns the instructions they sent.
+fn sent_instructions(app: &mut App) -> Vec<Instruction<0>> {
+    app.update();
+    app.world_mut().resource_mut::<Events<Instruction<0>>>().drain().collect()
+}
+
+/// Spawns a tank of player 0 with its turret aiming up at the origin.
+fn spawn_aiming_tank(app: &mut App) {
+    let turret = app.world_mut().spawn((Turret::default(), GlobalTransform::IDENTITY)).id();
+    app.world_mut().spawn((Tank { team_id: 0, turret }, PlayerID::<0>));
+}
+
+#[test]
+fn aim_test() {
+    let turret = GlobalTransform::IDENTITY;
+    let dt = 1. / 60.;
+    let angle_dir = |angle: f32| Vec2::new(-angle.sin(), angle.cos());
+
+    // angles are counter-clockwise from up, `SpinRight` spins the turret counter-clockwise
+    assert_eq!(aim_action(&turret, Vec2::Y, dt), None);
+    assert_eq!(aim_action(&turret, Vec2::NEG_X, dt), Some(Action::SpinRight));
+    assert_eq!(aim_action(&turret, Vec2::X, dt), Some(Action::SpinLeft));
+
+    // within half a step the turret would only overshoot
+    let step = TURRET_ROTATION_SPEED * dt;
+    assert_eq!(aim_action(&turret, angle_dir(step / 3.), dt), None);
+    assert_eq!(aim_action(&turret, angle_dir(-step / 3.), dt), None);
+    assert_eq!(aim_action(&turret, angle_dir(step), dt), Some(Action::SpinRight));
+
+    // the short way round, across the wraps at 0 and behind the turret
+    let turret = GlobalTransform::from(Transform::from_rotation(Quat::from_rotation_z(-0.2)));
+    assert_eq!(aim_action(&turret, angle_dir(0.2), dt), Some(Action::SpinRight));
+    assert_eq!(aim_action(&turret, angle_dir(-0.4), dt), Some(Action::SpinLeft));
+    assert_eq!(aim_action(&turret, angle_dir(PI - 0.3), dt), Some(Action::SpinRight));
+    assert_eq!(aim_action(&turret, angle_dir(PI - 0.1), dt), Some(Action::SpinLeft));
+
+    // following the spins brings the turret onto the enemy
+    let mut game_match = Match::new(Some("test_1.ron".to_string()), dt);
+    let tank = game_match.tank(0).unwrap();
+    let enemy = game_match.observe(1).unwrap().pos;
+
+    let mut aimed = false;
+    for _ in 0..1_000 {
+        let pos = game_match.observe(0).unwrap().pos;
+        let world = game_match.app_mut().world();
+        let turret = world.get::<Tank>(tank).unwrap().turret;
+        let Some(action) = aim_action(world.get::<GlobalTransform>(turret).unwrap(), enemy - pos, dt) else {
+            aimed = true;
+            break;
+        };
+
+        game_match.act(0, action);
+        game_match.step();
+    }
+    assert!(aimed, "Expected the turret to settle on the enemy");
+    assert!(game_match.observe(0).unwrap().turret_vision.iter().any(|hit| matches!(hit, Some(VisionHit::Enemy(_)))));
+}
+
+#[test]
+fn gamepad_test() {
+    let gamepad = Gamepad::new(0);
+    let mut app = App::new();
+    app.insert_resource(PlayerGamepad::<0>(gamepad))
+        .init_resource::<Axis<GamepadAxis>>()
+        .init_resource::<ButtonInput<GamepadButton>>()
+        .insert_resource(DeltaTime(1. / 60.))
+        .add_event::<Instruction<0>>()
+        .add_systems(Update, gamepad_input::<0>);
+    spawn_aiming_tank(&mut app);
+
+    let set_stick = |app: &mut App, x: GamepadAxisType, y: GamepadAxisType, pos: Vec2| {
+        let mut axes = app.world_mut().resource_mut::<Axis<GamepadAxis>>();
+        axes.set(GamepadAxis::new(gamepad, x), pos.x);
+        axes.set(GamepadAxis::new(gamepad, y), pos.y);
+    };
+
+    assert_eq!(sent_instructions(&mut app), []);
+
+    // the left stick drives, pushed to the left the tank turns counter-clockwise
+    set_stick(&mut app, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY, Vec2::new(-1., 1.));
+    assert_eq!(sent_instructions(&mut app), [Instruction::MoveForward, Instruction::RotateRight]);
+    set_stick(&mut app, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY, Vec2::new(0.8, -0.8));
+    assert_eq!(sent_instructions(&mut app), [Instruction::MoveBackward, Instruction::RotateLeft]);
+
+    // within the dead zone
+    set_stick(&mut app, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY, Vec2::splat(GAMEPAD_DEAD_ZONE / 2.));
+    assert_eq!(sent_instructions(&mut app), []);
+
+    // the turret turns towards the right stick, the trigger shoots
+    set_stick(&mut app, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY, Vec2::new(-1., 0.));
+    app.world_mut().resource_mut::<ButtonInput<GamepadButton>>().press(GamepadButton::new(gamepad, GamepadButtonType::RightTrigger2));
+    assert_eq!(sent_instructions(&mut app), [Instruction::SpinTurretRight, Instruction::Shoot]);
+    set_stick(&mut app, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY, Vec2::new(0.5, 0.));
+    assert_eq!(sent_instructions(&mut app), [Instruction::SpinTurretLeft, Instruction::Shoot]);
+}
+
+#[test]
+fn mouse_test() {
+    let mut app = App::new();
+    app.add_event::<WindowCreated>()
+        .add_event::<WindowResized>()
+        .add_event::<WindowScaleFactorChanged>()
+        .add_event::<AssetEvent<Image>>()
+        .init_resource::<Assets<Image>>()
+        .init_resource::<ManualTextureViews>()
+        .init_resource::<ButtonInput<MouseButton>>()
+        .insert_resource(DeltaTime(1. / 60.))
+        .add_event::<Instruction<0>>()
+        .add_systems(Update, (camera_system::<OrthographicProjection>, mouse_input::<0>).chain());
+    spawn_aiming_tank(&mut app);
+
+    // the camera looks at the tank, in the middle of the window
+    let window = app.world_mut().spawn((Window { resolution: WindowResolution::new(800., 600.), ..Default::default() }, PrimaryWindow)).id();
+    app.world_mut().send_event(WindowCreated { window });
+    app.world_mut().spawn(Camera2dBundle::default());
+    let move_cursor = |app: &mut App, pos: Option<Vec2>| {
+        app.world_mut().get_mut::<Window>(window).unwrap().set_cursor_position(pos);
+    };
+
+    // outside the window
+    app.world_mut().resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left);
+    assert_eq!(sent_instructions(&mut app), []);
+
+    // the window's y axis points down
+    move_cursor(&mut app, Some(Vec2::new(300., 300.)));
+    assert_eq!(sent_instructions(&mut app), [Instruction::SpinTurretRight, Instruction::Shoot]);
+    move_cursor(&mut app, Some(Vec2::new(500., 310.)));
+    assert_eq!(sent_instructions(&mut app), [Instruction::SpinTurretLeft, Instruction::Shoot]);
+
+    app.world_mut().resource_mut::<ButtonInput<MouseButton>>().release(MouseButton::Left);
+    move_cursor(&mut app, Some(Vec2::new(400., 100.)));
+    assert_eq!(sent_instructions(&mut app), []);
+}
+
+#[test]
+fn builtin_test() {
+    let mut app = App::new();
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::Builtin("level_1".to_string()),
+        PlayerController::<1>::Builtin("level_2".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+    }
+
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(1)));
+}
+
+#[test]
+fn wall_follower_test() {
+    // `RotateLeft` turns the tank clockwise, to its right on screen
+    let mut game_match = Match::new(Some("test_1.ron".to_string()), 1. / 60.);
+    game_match.step();
+    let before = game_match.observe(0).unwrap().rot;
+    game_match.act(0, Action::RotateLeft);
+    game_match.step();
+    let turned = game_match.observe(0).unwrap().rot - before;
+    assert!(turned.rem_euclid(2. * PI) > PI, "{turned} should be clockwise");
+
+    // a wall ahead and one along a side (hull rays n, nw, w, sw, s, se, e, ne), the bot backs off and turns away from the side
+    for (side, turn) in [([1, 2], Action::RotateLeft), ([7, 6], Action::RotateRight)] {
+        let mut hull_vision = [None; NUM_OF_HULL_RAY];
+        hull_vision[0] = Some(VisionHit::Wall(20.));
+        hull_vision[side[0]] = Some(VisionHit::Wall(20.));
+        hull_vision[side[1]] = Some(VisionHit::Wall(10.));
+        let data = PlayerData { hull_vision, ..Default::default() };
+
+        let mut bot = WallFollower::default();
+        assert_eq!(bot.brain(&data), Action::MoveBackward);
+        assert_eq!(bot.brain(&data), turn, "walls on rays {side:?} should be turned away from");
+    }
+}
+
+/// Serves the REST routes of an AI that aims at the enemy and shoots, returns the routes it was called on.
+fn http_bot(listener: std::net::TcpListener) -> thread::JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let (send_route, routes) = std::sync::mpsc::channel();
+
+        // requests of the same game may come on several connections
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (mut stream, send_route) = (stream.unwrap(), send_route.clone());
+
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                    loop {
+                        let mut request_line = String::new();
+                        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                            break;
+                        }
+                        let route = request_line.split_whitespace().nth(1).unwrap().trim_start_matches('/').to_string();
+
+                        let mut content_length = 0;
+                        loop {
+                            let mut header = String::new();
+                            reader.read_line(&mut header).unwrap();
+                            if header.trim().is_empty() {
+                                break;
+                            }
+                            if let Some((name, value)) = header.split_once(':') {
+                                if name.eq_ignore_ascii_case("content-length") {
+                                    content_length = value.trim().parse().unwrap();
+                                }
+                            }
+                        }
+                        let mut body = vec![0; content_length];
+                        std::io::Read::read_exact(&mut reader, &mut body).unwrap();
+                        let msg = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+
+                        let answer = match route.as_str() {
+                            "brain" => match msg["turret_vision"][NUM_OF_TURRET_RAY / 2].get("Enemy") {
+                                Some(_) => r#"{"action": "shoot"}"#.to_string(),
+                                None => r#"{"action": "spin_left"}"#.to_string(),
+                            },
+                            _ => String::new(),
+                        };
+                        let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{answer}", answer.len());
+                        stream.write_all(response.as_bytes()).unwrap();
+
+                        if send_route.send(route).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut msg_routes = Vec::new();
+        for route in routes.iter() {
+            let over = route == "win" || route == "loss";
+
+            if msg_routes.last() != Some(&route) {
+                msg_routes.push(route);
+            }
+            if over {
+                break;
+            }
+        }
+
+        msg_routes
+    })
+}
+
+#[test]
+fn http_test() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let ai = http_bot(listener);
+
+    let mut app = App::new();
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::Server { ip: addr.ip(), port: addr.port(), game_id: "http_test".to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+    assert!(game_match.telemetry(0).unwrap().actions > 0);
+
+    // let the end of game message go out, dropping the match waits for it
+    game_match.app_mut().update();
+    drop(game_match);
+
+    assert_eq!(ai.join().unwrap(), ["start_game", "brain", "win"]);
+}
+
+#[test]
+fn web_socket_test() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    // aims at the enemy and shoots, and reports the messages it got
+    let ai = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+        let mut msg_types = Vec::new();
+
+        loop {
+            let msg = match socket.read() {
+                Ok(tungstenite::Message::Text(text)) => serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+            let msg_type = msg["type"].as_str().unwrap().to_string();
+
+            if msg_type == "start_game" {
+                assert_eq!(msg["protocol_version"], PROTOCOL_VERSION);
+                socket.send(tungstenite::Message::text(format!(r#"{{"name": "aim_bot", "protocol_version": {PROTOCOL_VERSION}}}"#))).unwrap();
+            }
+            if msg_type == "brain" {
+                let action = match msg["turret_vision"][NUM_OF_TURRET_RAY / 2].get("Enemy") {
+                    Some(_) => "shoot",
+                    None => "spin_left",
+                };
+                socket.send(tungstenite::Message::text(format!("{{\"action\": \"{action}\"}}"))).unwrap();
+            }
+            if msg_types.last() != Some(&msg_type) {
+                msg_types.push(msg_type);
+            }
+        }
+
+        msg_types
+    });
+
+    let mut app = App::new();
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::WebSocket { url, game_id: "web_socket_test".to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+
+    // let the end of game message go out
+    game_match.app_mut().update();
+
+    assert_eq!(ai.join().unwrap(), ["start_game", "brain", "win"]);
+}
+
+#[test]
+fn network_test() {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    // spins its turret until told to leave
+    let leave = Arc::new(RwLock::new(false));
+    let remote = {
+        let leave = leave.clone();
+
+        thread::spawn(move || {
+        let stream = loop {
+            match std::net::TcpStream::connect(addr) {
+                Ok(stream) => break stream,
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        let join_info: JoinInfo = serde_json::from_str(&line).unwrap();
+
+        let mut stream = stream;
+        while !*leave.read().unwrap() {
+            writeln!(stream, "{}", serde_json::to_string(&InputFrame { actions: vec![Action::SpinLeft] }).unwrap()).unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        join_info
+        })
+    };
+
+    let mut app = App::new();
+    app.insert_resource(InputDelay(2));
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::Network { addr: addr.to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+    // the map may load faster than the remote player sends its frames
+    thread::sleep(Duration::from_millis(50));
+
+    let before = game_match.observe(0).unwrap();
+    game_match.step();
+    assert_ne!(before.turret_rot, game_match.observe(0).unwrap().turret_rot);
+
+    *leave.write().unwrap() = true;
+    let join_info = remote.join().unwrap();
+    assert_eq!(join_info.player, 0);
+    assert_eq!(join_info.input_delay, 2);
+    assert_ne!(join_info.broadcast_port, 0);
+
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(5));
+        game_match.step();
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Forfeit { winner: 1, fault: Fault::Disconnected }));
+}
+
+#[test]
+fn network_timeout_test() {
+    // nobody joins
+    let Err(err) = NetworkPlayer::<1>::listen("127.0.0.1:0", 0, 0, Duration::from_millis(200)) else {
+        panic!("Nobody joined");
+    };
+
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn forfeit_test() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    // completes the handshake, then never answers
+    let ai = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+        let mut msg_types = Vec::new();
+
+        while let Ok(msg) = socket.read() {
+            let tungstenite::Message::Text(text) = msg else {
+                continue;
+            };
+            let msg = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+            let msg_type = msg["type"].as_str().unwrap().to_string();
+
+            if msg_type == "start_game" {
+                socket.send(tungstenite::Message::text("{}")).unwrap();
+            }
+            if msg_types.last() != Some(&msg_type) {
+                msg_types.push(msg_type);
+            }
+        }
+
+        msg_types
+    });
+
+    let mut app = App::new();
+    app.insert_resource(ConnectionPolicy { timeout: Some(Duration::from_millis(20)), max_failures: 3, ..Default::default() });
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::WebSocket { url, game_id: "forfeit_test".to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Forfeit { winner: 1, fault: Fault::Timeout }));
+    assert_eq!(game_match.telemetry(0).unwrap().actions, 0);
+    assert!(game_match.telemetry(0).unwrap().timeouts > 0);
+
+    // let the end of game message go out, dropping the match waits for it
+    game_match.app_mut().update();
+    drop(game_match);
+
+    assert_eq!(ai.join().unwrap(), ["start_game", "brain", "loss"]);
+}
+
+#[test]
+fn start_game_forfeit_test() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    // reads start_game, then never answers
+    let ai = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+
+        while socket.read().is_ok() {}
+    });
+
+    let mut app = App::new();
+    app.insert_resource(ConnectionPolicy { timeout: Some(Duration::from_millis(50)), ..Default::default() });
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::WebSocket { url, game_id: "start_game_forfeit_test".to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..100 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Forfeit { winner: 1, fault: Fault::Disconnected }));
+    assert_eq!(game_match.telemetry(0).unwrap().errors, 1);
+
+    // the transport is dropped with the failed handshake, which closes the connection
+    drop(game_match);
+    ai.join().unwrap();
+}
+
+#[test]
+fn forfeit_result_test() {
+    let mut app = App::new();
+    app.add_plugins(PlayerControllerPlugin(
+        // echoes start_game back as its bot info, then exits
+        PlayerController::<0>::Process { cmd: "head -n 1".to_string(), game_id: "forfeit_result_test".to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..1_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Forfeit { winner: 1, fault: Fault::Disconnected }));
+
+    // `end_game` prints the reason on the line before the result
+    let events = game_match.app_mut().world().resource::<Events<TankForfeited>>();
+    let forfeits = events.get_reader().read(events).cloned().collect::<Vec<_>>();
+    assert_eq!(forfeits, [TankForfeited { player: 0, reason: "disconnected".to_string() }]);
+}
+
+/// A WebSocket bot that always answers `fire`, returns the `errors` of every observation it got.
+fn fire_bot(listener: std::net::TcpListener) -> thread::JoinHandle<Vec<serde_json::Value>> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+        let mut errors = Vec::new();
+
+        while let Ok(msg) = socket.read() {
+            let tungstenite::Message::Text(text) = msg else {
+                continue;
+            };
+            let msg = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+
+            match msg["type"].as_str().unwrap() {
+                "start_game" => socket.send(tungstenite::Message::text("{}")).unwrap(),
+                "brain" => {
+                    errors.push(msg["errors"].clone());
+                    socket.send(tungstenite::Message::text(r#"{"action": "fire"}"#)).unwrap();
+                },
+                _ => {}
+            }
+        }
+
+        errors
+    })
+}
+
+#[test]
+fn action_errors_test() {
+    for strict in [false, true] {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let ai = fire_bot(listener);
+
+        let mut app = App::new();
+        app.insert_resource(ConnectionPolicy { strict, max_failures: 1000, ..Default::default() });
+        app.add_plugins(PlayerControllerPlugin(
+            PlayerController::<0>::WebSocket { url, game_id: "action_errors_test".to_string() },
+            PlayerController::<1>::Builtin("idle".to_string())
+        ));
+
+        let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+        for _ in 0..100 {
+            game_match.step();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        match strict {
+            true => assert_eq!(game_match.outcome(), Some(MatchOutcome::Forfeit { winner: 1, fault: Fault::InvalidResponse })),
+            false => assert_eq!(game_match.outcome(), None),
+        }
+        assert!(game_match.telemetry(0).unwrap().invalid_actions > 0);
+        drop(game_match);
+
+        let errors = ai.join().unwrap();
+        assert_eq!(errors[0], serde_json::json!([]));
+        match strict {
+            true => assert_eq!(errors.len(), 1),
+            false => assert!(errors[1][0].as_str().unwrap().starts_with("Unknown action (fire)")),
+        }
+    }
+}
+
+#[test]
+fn process_test() {
+    let python = if cfg!(target_os = "windows") { "python" } else { "python3" };
+
+    let mut app = App::new();
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::Process {
+            cmd: format!("{python} ai/templates/python/stdio_main.py"),
+            game_id: "process_test".to_string()
+        },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+}
+
+#[test]
+fn split_command_test() {
+    assert_eq!(split_command("python  bot.py --port 5000").unwrap(), ["python", "bot.py", "--port", "5000"]);
+    assert_eq!(split_command(r#"python "C:\My Bots\bot.py""#).unwrap(), ["python", r"C:\My Bots\bot.py"]);
+    assert_eq!(split_command(r#""C:\Program Files\Python\python.exe" 'my bot.py' """#).unwrap(), [r"C:\Program Files\Python\python.exe", "my bot.py", ""]);
+    assert_eq!(split_command("--name=\"aim bot\"").unwrap(), ["--name=aim bot"]);
+    assert!(split_command("").unwrap().is_empty());
+    assert!(split_command("python \"bot.py").is_err());
+}
+
+/// A gRPC AI that aims at the enemy and shoots, and logs the calls it receives.
+#[derive(Clone, Default)]
+struct GrpcAimBot {
+    calls: Arc<std::sync::Mutex<Vec<String>>>
+}
+
+struct UnaryFn<F>(F);
+
+impl<Req, Resp: Send + 'static, F: FnMut(Req) -> Resp> tonic::server::UnaryService<Req> for UnaryFn<F> {
+    type Response = Resp;
+    type Future = std::future::Ready<Result<tonic::Response<Resp>, tonic::Status>>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        std::future::ready(Ok(tonic::Response::new((self.0)(request.into_inner()))))
+    }
+}
+
+impl<B> tonic::codegen::Service<tonic::codegen::http::Request<B>> for GrpcAimBot
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<tonic::codegen::StdError> + Send + 'static,
+{
+    type Response = tonic::codegen::http::Response<tonic::body::BoxBody>;
+    type Error = std::convert::Infallible;
+    type Future = tonic::codegen::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: tonic::codegen::http::Request<B>) -> Self::Future {
+        use game::player::remote::grpc::proto;
+        use tonic::{codec::ProstCodec, server::Grpc};
+
+        let calls = self.calls.clone();
+        let path = req.uri().path().trim_start_matches("/tank_ai.TankAi/").to_string();
+
+        Box::pin(async move {
+            if calls.lock().unwrap().last() != Some(&path) {
+                calls.lock().unwrap().push(path.clone());
+            }
+
+            let response = match path.as_str() {
+                "Brain" => Grpc::new(ProstCodec::<proto::Action, proto::Brain>::default())
+                    .unary(UnaryFn(|brain: proto::Brain| {
+                        let action = match brain.turret_vision[NUM_OF_TURRET_RAY / 2].hit {
+                            Some(proto::vision_hit::Hit::Enemy(_)) => proto::action::Kind::Shoot,
+                            _ => proto::action::Kind::SpinLeft,
+                        };
+
+                        proto::Action { action: action as i32 }
+                    }), req)
+                    .await,
+                "StartGame" => Grpc::new(ProstCodec::<proto::BotInfo, proto::StartGame>::default())
+                    .unary(UnaryFn(|_: proto::StartGame| proto::BotInfo {
+                        name: Some("aim_bot".to_string()),
+                        protocol_version: Some(PROTOCOL_VERSION),
+                        ..Default::default()
+                    }), req)
+                    .await,
+                _ => Grpc::new(ProstCodec::<proto::Ack, proto::GameOver>::default())
+                    .unary(UnaryFn(|_: proto::GameOver| proto::Ack {}), req)
+                    .await,
+            };
+
+            Ok(response)
+        })
+    }
+}
+
+impl tonic::server::NamedService for GrpcAimBot {
+    const NAME: &'static str = "tank_ai.TankAi";
+}
+
+#[test]
+fn grpc_test() {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let bot = GrpcAimBot::default();
+
+    {
+        let bot = bot.clone();
+
+        thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(tonic::transport::Server::builder().add_service(bot).serve(addr))
+                .unwrap();
+        });
+    }
+    while std::net::TcpStream::connect(addr).is_err() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut app = App::new();
+    app.add_plugins(PlayerControllerPlugin(
+        PlayerController::<0>::Grpc { url: format!("grpc://{addr}"), game_id: "grpc_test".to_string() },
+        PlayerController::<1>::Builtin("idle".to_string())
+    ));
+
+    let mut game_match = Match::from_app(app, Some("test_1.ron".to_string()), 1. / 60.);
+
+    for _ in 0..10_000 {
+        if game_match.outcome().is_some() {
+            break;
+        }
+
+        game_match.step();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(game_match.outcome(), Some(MatchOutcome::Winner(0)));
+
+    let player_info = game_match.app_mut().world().resource::<PlayerInfo<0>>();
+    assert_eq!(player_info.0.name.as_deref(), Some("aim_bot"));
+
+    let telemetry = game_match.telemetry(0).unwrap();
+    assert!(telemetry.actions > 0 && telemetry.actions_per_second > 0.);
+    assert_eq!(telemetry.latency_histogram.iter().sum::<u64>(), telemetry.actions);
+    assert!(telemetry.p50_latency_ms <= telemetry.p95_latency_ms && telemetry.p95_latency_ms <= telemetry.max_latency_ms);
+    assert_eq!(game_match.telemetry(1), None);
+
+    // let the end of game call go out, dropping the match waits for it
+    game_match.app_mut().update();
+    drop(game_match);
+
+    assert_eq!(*bot.calls.lock().unwrap(), ["StartGame", "Brain", "Win"]);
+}
+
+#[test]
+fn handshake_test() {
+    let legacy = BotInfo::default();
+    assert!(legacy.check::<0>().is_ok());
+
+    let current = BotInfo { protocol_version: Some(PROTOCOL_VERSION), features: vec!["unknown".to_string()], ..Default::default() };
+    assert!(current.check::<0>().is_ok());
+
+    let future = BotInfo { protocol_version: Some(PROTOCOL_VERSION + 1), ..Default::default() };
+    assert!(future.check::<0>().is_err());
+
+    // bots can plan around the power-ups
+    let rules = serde_json::to_value(GameInfo::new("handshake_test", &Map::default()).rules).unwrap();
+    assert_eq!(rules["pickup_duration"], PICKUP_DURATION);
+    assert_eq!(rules["speed_boost_factor"], SPEED_BOOST_FACTOR);
+}